@@ -30,6 +30,16 @@ pub fn impl_playlist_child(name: &Ident, generics: &Generics) -> proc_macro2::To
                     anyhow::bail!("inner is none")
                 }
             }
+
+            /// prepare the child to be played soon
+            async fn prefetch(&mut self) -> anyhow::Result<()> {
+                self.init().await?;
+                if let Some(inner) = &mut self.inner {
+                    inner.prefetch().await
+                } else {
+                    anyhow::bail!("inner is none")
+                }
+            }
         }
     }
 }
@@ -0,0 +1,141 @@
+use std::sync::Arc;
+
+use http::{Method, Request};
+use serde_json::{Value, json};
+
+use crate::{
+    playlist::{LocalFileTrack, Playlist, PlaylistControl},
+    station::StationSnapshot,
+};
+
+use super::AdminState;
+
+/// The body of `POST /api/playlists/{playlist}/queue`.
+#[derive(Debug, serde::Deserialize)]
+struct QueueRequest {
+    /// path of the file in the file provider
+    path: String,
+    /// name of the file provider in the configuration,
+    ///     so only the files of the configured providers can be queued
+    provider: String,
+}
+
+/// status line and JSON body of an API response
+pub type ApiResponse = (&'static str, Value);
+
+fn error(status: &'static str, message: impl Into<String>) -> ApiResponse {
+    (status, json!({ "error": message.into() }))
+}
+
+fn ok() -> ApiResponse {
+    ("200 OK", json!({ "ok": true }))
+}
+
+async fn playlist_json(playlist: &Playlist) -> Value {
+    let (title, artist) = playlist.get_now_playing().await;
+    json!({
+        "name": playlist.name.as_str(),
+        "content_type": playlist.get_content_type().await.as_str(),
+        "title": title.as_str(),
+        "artist": artist.as_str(),
+        "listeners": playlist.get_listeners(),
+        "paused": playlist.is_paused(),
+        "live": playlist.is_live(),
+    })
+}
+
+/// Handle a request of the admin API, `path` is the path after `api/`.
+pub async fn handle(
+    request: &Request<()>,
+    path: &str,
+    body: &[u8],
+    state: &AdminState,
+) -> ApiResponse {
+    if path == "reload" {
+        if request.method() != Method::POST {
+            return error("405 Method Not Allowed", "method not allowed");
+        }
+        // the current configuration is kept if the new one is invalid
+        return match state.station.reload().await {
+            Ok(()) => ok(),
+            Err(e) => error("400 Bad Request", format!("failed to reload: {e}")),
+        };
+    }
+
+    let state = state.station.snapshot();
+    let segments: Vec<&str> = path.split('/').collect();
+    match (request.method(), segments.as_slice()) {
+        (&Method::GET, ["mounts"]) => {
+            let mut mounts = Vec::with_capacity(state.mounts.len());
+            for m in &state.mounts {
+                let mut mount = playlist_json(&m.mount.playlist).await;
+                mount["output"] = json!(m.output);
+                mount["mount"] = json!(format!("/{}", m.path));
+                mount["playlist"] = json!(m.playlist);
+                mounts.push(mount);
+            }
+            ("200 OK", Value::Array(mounts))
+        }
+        (&Method::GET, ["playlists"]) => {
+            let mut ids: Vec<&String> = state.playlists.keys().collect();
+            ids.sort();
+            let mut playlists = Vec::with_capacity(ids.len());
+            for id in ids {
+                let mut playlist = playlist_json(&state.playlists[id]).await;
+                playlist["id"] = json!(id);
+                playlists.push(playlist);
+            }
+            ("200 OK", Value::Array(playlists))
+        }
+        (&Method::POST, ["playlists", id, action]) => {
+            let Some(playlist) = state.playlists.get(*id) else {
+                return error("404 Not Found", format!("playlist not found: {id}"));
+            };
+            let control = match *action {
+                "skip" if playlist.is_live() => {
+                    return error("409 Conflict", "the live source can not be skipped");
+                }
+                "skip" => PlaylistControl::Skip,
+                "pause" => PlaylistControl::Pause,
+                "resume" => PlaylistControl::Resume,
+                "queue" => match queued_track(body, &state).await {
+                    Ok(track) => PlaylistControl::Queue(track),
+                    Err(response) => return response,
+                },
+                _ => return error("404 Not Found", format!("unknown action: {action}")),
+            };
+            match playlist.control(control).await {
+                Ok(()) => ok(),
+                Err(e) => error("503 Service Unavailable", e.to_string()),
+            }
+        }
+        (_, ["mounts"] | ["playlists"] | ["playlists", _, _]) => {
+            error("405 Method Not Allowed", "method not allowed")
+        }
+        _ => error("404 Not Found", format!("unknown API path: /api/{path}")),
+    }
+}
+
+/// Build the track of a `queue` request, the file must exist in the file provider.
+async fn queued_track(
+    body: &[u8],
+    state: &StationSnapshot,
+) -> Result<Box<LocalFileTrack>, ApiResponse> {
+    let QueueRequest { path, provider } = serde_json::from_slice(body)
+        .map_err(|e| error("400 Bad Request", format!("invalid queue request: {e}")))?;
+    let Some(file_provider) = state.file_providers.get(&provider).cloned() else {
+        return Err(error(
+            "404 Not Found",
+            format!("file provider not found: {provider}"),
+        ));
+    };
+
+    match file_provider.get_meta(&path).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(error("404 Not Found", format!("file not found: {path}"))),
+        Err(e) => return Err(error("502 Bad Gateway", e.to_string())),
+    }
+    let track = LocalFileTrack::new(Arc::new(path), file_provider, Some(false))
+        .map_err(|e| error("400 Bad Request", e.to_string()))?;
+    Ok(Box::new(track))
+}
@@ -0,0 +1,124 @@
+use std::sync::atomic::Ordering;
+
+use crate::metrics::{CacheMetrics, PlaylistMetrics, TextEncoder};
+
+use super::AdminState;
+
+/// content type of the Prometheus text exposition format
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// name, help and value of a counter family
+type Counter<M> = (&'static str, &'static str, fn(&M) -> f64);
+
+/// Render the metrics of the mounts, the playlists and the caches.
+pub fn render(state: &AdminState) -> String {
+    let state = state.station.snapshot();
+    let mut encoder = TextEncoder::new();
+
+    encoder.family(
+        "rustcast_listeners",
+        "gauge",
+        "Listeners currently connected to the mount.",
+    );
+    for m in &state.mounts {
+        let path = format!("/{}", m.path);
+        encoder.sample(
+            "rustcast_listeners",
+            &[
+                ("output", &m.output),
+                ("mount", &path),
+                ("playlist", &m.playlist),
+            ],
+            m.mount.metrics.listeners.load(Ordering::Relaxed) as f64,
+        );
+    }
+
+    encoder.family(
+        "rustcast_output_bytes_sent_total",
+        "counter",
+        "Bytes sent to the listeners of the mount.",
+    );
+    for m in &state.mounts {
+        let path = format!("/{}", m.path);
+        encoder.sample(
+            "rustcast_output_bytes_sent_total",
+            &[("output", &m.output), ("mount", &path)],
+            m.mount.metrics.bytes_sent.load(Ordering::Relaxed) as f64,
+        );
+    }
+
+    let mut playlists: Vec<_> = state.playlists.iter().collect();
+    playlists.sort_by(|a, b| a.0.cmp(b.0));
+    let playlist_counters: [Counter<PlaylistMetrics>; 4] = [
+        (
+            "rustcast_frames_prepared_total",
+            "Frames prepared by the playlist.",
+            |m| m.frames_prepared.load(Ordering::Relaxed) as f64,
+        ),
+        (
+            "rustcast_producer_stalls_total",
+            "Times the playlist waited for its producer to send a frame.",
+            |m| m.producer_stalls.load(Ordering::Relaxed) as f64,
+        ),
+        (
+            "rustcast_producer_stall_seconds_total",
+            "Time the playlist waited for its producer to send a frame.",
+            |m| m.producer_stall_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0,
+        ),
+        (
+            "rustcast_track_changes_total",
+            "Times the title or the artist of the prepared frames changed.",
+            |m| m.track_changes.load(Ordering::Relaxed) as f64,
+        ),
+    ];
+    for (name, help, value) in playlist_counters {
+        encoder.family(name, "counter", help);
+        for (id, playlist) in &playlists {
+            encoder.sample(name, &[("playlist", id)], value(&playlist.metrics()));
+        }
+    }
+
+    let mut caches: Vec<_> = state
+        .file_providers
+        .iter()
+        .filter_map(|(name, provider)| Some((name, provider.cache_metrics()?)))
+        .collect();
+    caches.sort_by(|a, b| a.0.cmp(b.0));
+    let cache_counters: [Counter<CacheMetrics>; 3] = [
+        (
+            "rustcast_cache_hits_total",
+            "Files served from the cache of the file provider.",
+            |m| m.hits.load(Ordering::Relaxed) as f64,
+        ),
+        (
+            "rustcast_cache_misses_total",
+            "Files downloaded to the cache of the file provider.",
+            |m| m.misses.load(Ordering::Relaxed) as f64,
+        ),
+        (
+            "rustcast_cache_download_bytes_total",
+            "Bytes downloaded to the cache of the file provider.",
+            |m| m.download_bytes.load(Ordering::Relaxed) as f64,
+        ),
+    ];
+    for (name, help, value) in cache_counters {
+        encoder.family(name, "counter", help);
+        for (provider, metrics) in &caches {
+            encoder.sample(name, &[("provider", provider)], value(metrics));
+        }
+    }
+    encoder.family(
+        "rustcast_cache_download_seconds",
+        "histogram",
+        "Duration of the downloads to the cache of the file provider.",
+    );
+    for (provider, metrics) in &caches {
+        encoder.histogram(
+            "rustcast_cache_download_seconds",
+            &[("provider", provider)],
+            &metrics.download_seconds,
+        );
+    }
+
+    encoder.finish()
+}
@@ -0,0 +1,189 @@
+use std::sync::Arc;
+
+use futures::SinkExt;
+use http::{Method, Request};
+use log::{debug, error, info};
+use tokio::{
+    io::AsyncReadExt,
+    net::{TcpListener, TcpStream},
+};
+use tokio_stream::StreamExt;
+use tokio_util::codec::Framed;
+
+use crate::{
+    CONTEXT,
+    shoutcast::{Http, Mount, basic_auth, constant_time_eq},
+    station::Station,
+};
+
+mod api;
+mod metrics;
+
+/// path of the Prometheus metrics, without the leading `/`
+const METRICS_PATH: &str = "metrics";
+
+/// prefix of the paths of the admin API, without the leading `/`
+const API_PATH_PREFIX: &str = "api/";
+
+/// maximum size of the body of an admin API request
+const MAX_BODY_SIZE: usize = 65536;
+
+/// A mount of an output, as seen by the admin listener.
+pub struct AdminMount {
+    /// `host:port` of the output
+    pub output: String,
+    /// path of the mount, without the leading `/`
+    pub path: String,
+    /// name of the playlist in the configuration
+    pub playlist: String,
+    pub mount: Mount,
+}
+
+/// Everything the admin listener reports on.
+pub struct AdminState {
+    /// the playlists and the mounts are read from the snapshot of the station,
+    ///     the configuration is reloaded through the station
+    pub station: Arc<Station>,
+    /// user name and password of the admin API, the API is disabled if None
+    pub credentials: Option<(String, String)>,
+}
+
+pub async fn listen(host: String, port: u16, state: Arc<AdminState>) {
+    let addr = format!("{host}:{port}");
+    let server = match TcpListener::bind(&addr).await {
+        Ok(server) => server,
+        Err(e) => {
+            error!("failed to bind admin listener to: {addr}, error: {e}");
+            return;
+        }
+    };
+    info!("Admin listening on: {addr}");
+
+    loop {
+        let accepted = tokio::select! {
+            accepted = server.accept() => accepted,
+            _ = CONTEXT.shutdown.cancelled() => {
+                info!("Admin stop listening on: {addr}");
+                return;
+            }
+        };
+        let (stream, _) = match accepted {
+            Ok(s) => s,
+            Err(e) => {
+                error!("failed to accept admin connection: {e}");
+                continue;
+            }
+        };
+        let state = state.clone();
+        // the shutdown waits for the requests being served
+        CONTEXT.tasks.spawn(async move {
+            if let Err(e) = process(stream, state).await {
+                error!("failed to process admin connection; error = {e}");
+            }
+        });
+    }
+}
+
+async fn process(stream: TcpStream, state: Arc<AdminState>) -> anyhow::Result<()> {
+    let mut transport = Framed::new(stream, Http);
+
+    let request = match transport.next().await {
+        Some(request) => request?,
+        None => return Ok(()),
+    };
+    debug!("handle admin request: {:?}", request);
+
+    let body = read_body(&mut transport, &request).await?;
+    let response = route(&request, &body, &state).await;
+    transport.send(response).await?;
+    Ok(())
+}
+
+/// Read the body of the request, up to its `Content-Length`.
+async fn read_body(
+    transport: &mut Framed<TcpStream, Http>,
+    request: &Request<()>,
+) -> anyhow::Result<Vec<u8>> {
+    let len = match request.headers().get(http::header::CONTENT_LENGTH) {
+        Some(len) => len.to_str()?.trim().parse::<usize>()?,
+        None => return Ok(Vec::new()),
+    };
+    if len > MAX_BODY_SIZE {
+        return Err(anyhow::anyhow!("request body too large: {len} bytes"));
+    }
+
+    // the decoder may have read the beginning of the body with the headers
+    let read_buf = transport.read_buffer_mut();
+    let mut body = read_buf.split_to(len.min(read_buf.len())).to_vec();
+    let start = body.len();
+    body.resize(len, 0);
+    transport.get_mut().read_exact(&mut body[start..]).await?;
+    Ok(body)
+}
+
+async fn route(request: &Request<()>, body: &[u8], state: &AdminState) -> String {
+    let path = request.uri().path().trim_matches('/');
+    let head = request.method() == Method::HEAD;
+
+    if let Some(api_path) = path.strip_prefix(API_PATH_PREFIX) {
+        let Some((user, password)) = &state.credentials else {
+            return json_response("403 Forbidden", "{\"error\":\"the admin API is disabled\"}");
+        };
+        // both credentials are compared in constant time
+        let authorized = basic_auth(request).is_some_and(|auth| {
+            constant_time_eq(auth.0.as_bytes(), user.as_bytes())
+                & constant_time_eq(auth.1.as_bytes(), password.as_bytes())
+        });
+        if !authorized {
+            return unauthorized_response();
+        }
+        let (status, body) = api::handle(request, api_path, body, state).await;
+        return json_response(status, &body.to_string());
+    }
+
+    if request.method() != Method::GET && !head {
+        return response("405 Method Not Allowed", "text/plain", "", head);
+    }
+    match path {
+        METRICS_PATH => {
+            let body = metrics::render(state);
+            response("200 OK", metrics::CONTENT_TYPE, &body, head)
+        }
+        _ => response("404 Not Found", "text/plain", "", head),
+    }
+}
+
+fn json_response(status: &str, body: &str) -> String {
+    response(status, "application/json; charset=utf-8", body, false)
+}
+
+fn unauthorized_response() -> String {
+    let body = "{\"error\":\"authentication required\"}";
+    format!(
+        "HTTP/1.1 401 Unauthorized\r\n\
+        WWW-Authenticate: Basic realm=\"RustCast Admin\"\r\n\
+        Content-Type: application/json; charset=utf-8\r\n\
+        Content-Length: {}\r\n\
+        Connection: close\r\n\
+        \r\n\
+        {body}",
+        body.len(),
+    )
+}
+
+/// A full HTTP response, the body is omitted for `HEAD` requests.
+fn response(status: &str, content_type: &str, body: &str, head: bool) -> String {
+    let mut res = format!(
+        "HTTP/1.1 {status}\r\n\
+        Content-Type: {content_type}\r\n\
+        Content-Length: {}\r\n\
+        Cache-Control: no-cache\r\n\
+        Connection: close\r\n\
+        \r\n",
+        body.len(),
+    );
+    if !head {
+        res.push_str(body);
+    }
+    res
+}
@@ -0,0 +1,445 @@
+use bytes::{Bytes, BytesMut};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use super::{AudioChunk, AudioInfo, READ_BLOCK_SIZE, VorbisComment};
+
+/// the first bytes of a FLAC stream
+const FLAC_MAGIC: &[u8; 4] = b"fLaC";
+
+/// size of the header of a metadata block
+const METADATA_BLOCK_HEADER_SIZE: usize = 4;
+
+/// type of the STREAMINFO metadata block
+const STREAMINFO: u8 = 0;
+
+/// type of the VORBIS_COMMENT metadata block
+const VORBIS_COMMENT: u8 = 4;
+
+/// size of the STREAMINFO metadata block, without its header
+const STREAMINFO_SIZE: usize = 34;
+
+/// minimum size of a frame header, the sync code and the CRC-8
+const MIN_FRAME_HEADER_SIZE: usize = 6;
+
+/// maximum size of a frame header
+const MAX_FRAME_HEADER_SIZE: usize = 16;
+
+/// The fields of the STREAMINFO metadata block used for the playback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamInfo {
+    /// sample rate in Hz
+    pub sample_rate: u32,
+    /// number of channels
+    pub channels: u8,
+    /// total number of samples per channel, 0 if unknown
+    pub total_samples: u64,
+}
+
+impl StreamInfo {
+    fn parse(b: &[u8]) -> Option<Self> {
+        if b.len() < STREAMINFO_SIZE {
+            return None;
+        }
+        let sample_rate = ((b[10] as u32) << 12) | ((b[11] as u32) << 4) | ((b[12] as u32) >> 4);
+        let total_samples = (((b[13] & 0x0F) as u64) << 32)
+            | u32::from_be_bytes([b[14], b[15], b[16], b[17]]) as u64;
+        if sample_rate == 0 {
+            return None;
+        }
+        Some(Self {
+            sample_rate,
+            channels: ((b[12] >> 1) & 0b111) + 1,
+            total_samples,
+        })
+    }
+
+    /// Read the STREAMINFO of the stream header sent to the listeners,
+    ///     the magic followed by the STREAMINFO block.
+    pub fn from_stream_header(header: &[u8]) -> Option<Self> {
+        let block = header.strip_prefix(FLAC_MAGIC)?;
+        if block.first()? & 0x7F != STREAMINFO {
+            return None;
+        }
+        Self::parse(block.get(METADATA_BLOCK_HEADER_SIZE..)?)
+    }
+}
+
+/// The metadata of a FLAC stream.
+struct FlacHeader {
+    stream_info: StreamInfo,
+    comment: Option<VorbisComment>,
+    /// the stream header sent to the listeners,
+    ///     the magic and the STREAMINFO block, the other blocks are removed
+    header: Bytes,
+}
+
+/// Read the magic and the metadata blocks at the beginning of a FLAC stream,
+///     returns None if the stream is not a FLAC stream.
+/// `buf` contains the data read after the metadata blocks.
+async fn read_header(
+    reader: &mut (impl AsyncRead + Unpin),
+    buf: &mut BytesMut,
+) -> std::io::Result<Option<FlacHeader>> {
+    async fn fill(
+        reader: &mut (impl AsyncRead + Unpin),
+        buf: &mut BytesMut,
+        len: usize,
+    ) -> std::io::Result<bool> {
+        while buf.len() < len {
+            buf.reserve(READ_BLOCK_SIZE);
+            if reader.read_buf(buf).await? == 0 {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    if !fill(reader, buf, FLAC_MAGIC.len()).await? || &buf[..FLAC_MAGIC.len()] != FLAC_MAGIC {
+        return Ok(None);
+    }
+    let _ = buf.split_to(FLAC_MAGIC.len());
+
+    let mut stream_info = None;
+    let mut stream_info_block = Bytes::new();
+    let mut comment = None;
+    loop {
+        if !fill(reader, buf, METADATA_BLOCK_HEADER_SIZE).await? {
+            return Ok(None);
+        }
+        let last = buf[0] & 0x80 != 0;
+        let block_type = buf[0] & 0x7F;
+        let len = ((buf[1] as usize) << 16) | ((buf[2] as usize) << 8) | buf[3] as usize;
+        if !fill(reader, buf, METADATA_BLOCK_HEADER_SIZE + len).await? {
+            return Ok(None);
+        }
+        let block = buf.split_to(METADATA_BLOCK_HEADER_SIZE + len).freeze();
+        let body = &block[METADATA_BLOCK_HEADER_SIZE..];
+        match block_type {
+            STREAMINFO => {
+                stream_info = StreamInfo::parse(body);
+                stream_info_block = block.clone();
+            }
+            VORBIS_COMMENT => comment = VorbisComment::parse(body),
+            _ => {}
+        }
+        if last {
+            break;
+        }
+    }
+
+    let Some(stream_info) = stream_info else {
+        return Ok(None);
+    };
+    let mut header = BytesMut::from(&FLAC_MAGIC[..]);
+    header.extend_from_slice(&stream_info_block);
+    // the STREAMINFO block is the last block of the header sent to the listeners
+    header[FLAC_MAGIC.len()] |= 0x80;
+
+    Ok(Some(FlacHeader {
+        stream_info,
+        comment,
+        header: header.freeze(),
+    }))
+}
+
+/// Read the duration, the title and the artist of a FLAC stream from its metadata blocks.
+pub async fn read_flac_info(reader: impl AsyncRead + Unpin) -> std::io::Result<Option<AudioInfo>> {
+    let mut reader = reader;
+    let mut buf = BytesMut::new();
+    let Some(header) = read_header(&mut reader, &mut buf).await? else {
+        return Ok(None);
+    };
+    if header.stream_info.total_samples == 0 {
+        return Ok(None);
+    }
+
+    let comment = header.comment.unwrap_or_default();
+    Ok(Some(AudioInfo {
+        duration: header.stream_info.total_samples as f64 * 1000.0
+            / header.stream_info.sample_rate as f64,
+        title: comment.title,
+        artist: comment.artist,
+    }))
+}
+
+/// CRC-8 with the polynomial x^8 + x^2 + x^1 + x^0, used by the frame headers
+fn crc8(b: &[u8]) -> u8 {
+    b.iter().fold(0u8, |crc, &x| {
+        (0..8).fold(crc ^ x, |crc, _| {
+            if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+/// CRC-16 with the polynomial x^16 + x^15 + x^2 + x^0, used by the frames
+fn crc16(b: &[u8]) -> u16 {
+    b.iter().fold(0u16, |crc, &x| {
+        (0..8).fold(crc ^ ((x as u16) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+/// Parse the frame header at the beginning of `b`,
+///     returns the number of samples per channel of the frame,
+///     or None if `b` does not start with a valid frame header.
+fn parse_frame_header(b: &[u8]) -> Option<u32> {
+    if b.len() < MIN_FRAME_HEADER_SIZE || b[0] != 0xFF || b[1] & 0xFE != 0xF8 {
+        return None;
+    }
+    let block_size_code = b[2] >> 4;
+    let sample_rate_code = b[2] & 0x0F;
+    let channels = b[3] >> 4;
+    let sample_size = (b[3] >> 1) & 0b111;
+    if block_size_code == 0 || sample_rate_code == 15 || channels > 10 || sample_size == 3 {
+        return None;
+    }
+    if b[3] & 1 != 0 {
+        return None;
+    }
+
+    // the frame or sample number is coded like UTF-8 on 1 to 7 bytes
+    let mut at = 4;
+    let extra_bytes = match b[at].leading_ones() {
+        0 => 0,
+        n @ 2..=7 => n as usize - 1,
+        _ => return None,
+    };
+    at += 1;
+    for _ in 0..extra_bytes {
+        if *b.get(at)? & 0xC0 != 0x80 {
+            return None;
+        }
+        at += 1;
+    }
+
+    let block_size = match block_size_code {
+        1 => 192,
+        2..=5 => 576 << (block_size_code - 2),
+        6 => {
+            at += 1;
+            *b.get(at - 1)? as u32 + 1
+        }
+        7 => {
+            at += 2;
+            u16::from_be_bytes([*b.get(at - 2)?, *b.get(at - 1)?]) as u32 + 1
+        }
+        _ => 256 << (block_size_code - 8),
+    };
+    at += match sample_rate_code {
+        12 => 1,
+        13 | 14 => 2,
+        _ => 0,
+    };
+
+    if crc8(b.get(..at)?) != *b.get(at)? {
+        return None;
+    }
+    Some(block_size)
+}
+
+/// FlacFrameReader reads chunks of whole FLAC frames from a reader.
+/// The frames have no length,
+///     a frame ends where the next valid frame header starts and the CRC-16 of the frame matches.
+pub struct FlacFrameReader<R> {
+    reader: R,
+    buf: BytesMut,
+    header: Option<FlacHeader>,
+    /// offset in `buf` to continue searching the end of the current frame from
+    scan_from: usize,
+    eof: bool,
+}
+
+impl<R: AsyncRead + Unpin> FlacFrameReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buf: BytesMut::new(),
+            header: None,
+            scan_from: 0,
+            eof: false,
+        }
+    }
+
+    /// Get the length of the frame at the beginning of `buf` and its number of samples,
+    ///     returns None if more data is needed.
+    fn next_frame_len(&mut self) -> Option<(usize, u32)> {
+        loop {
+            if self.buf.is_empty() {
+                return None;
+            }
+            let Some(samples) = parse_frame_header(&self.buf) else {
+                if self.buf.len() < MAX_FRAME_HEADER_SIZE && !self.eof {
+                    return None;
+                }
+                // skip the garbage until the next frame header
+                let next = self.buf[1..]
+                    .iter()
+                    .position(|&b| b == 0xFF)
+                    .map(|i| i + 1)
+                    .unwrap_or(self.buf.len());
+                let _ = self.buf.split_to(next);
+                self.scan_from = 0;
+                continue;
+            };
+
+            let mut at = self.scan_from.max(MIN_FRAME_HEADER_SIZE);
+            while at + MAX_FRAME_HEADER_SIZE <= self.buf.len() {
+                if self.buf[at] == 0xFF
+                    && parse_frame_header(&self.buf[at..]).is_some()
+                    && crc16(&self.buf[..at]) == 0
+                {
+                    self.scan_from = 0;
+                    return Some((at, samples));
+                }
+                at += 1;
+            }
+
+            if self.eof {
+                // the last frame ends with the stream
+                self.scan_from = 0;
+                return Some((self.buf.len(), samples));
+            }
+            self.scan_from = at;
+            return None;
+        }
+    }
+
+    /// Read the next chunk of at least `chunk_duration` milliseconds,
+    ///     the last chunk may be shorter.
+    /// Returns None at the end of the data,
+    ///     and an error if the data is not a FLAC stream.
+    pub async fn next_chunk(&mut self, chunk_duration: f64) -> std::io::Result<Option<AudioChunk>> {
+        if self.header.is_none() {
+            self.header = read_header(&mut self.reader, &mut self.buf).await?;
+        }
+        let Some(header) = self.header.as_ref() else {
+            return Err(std::io::Error::other("invalid FLAC stream"));
+        };
+        let sample_rate = header.stream_info.sample_rate as f64;
+        let stream_header = header.header.clone();
+
+        let mut data = BytesMut::new();
+        let mut duration = 0.0;
+        while duration < chunk_duration {
+            match self.next_frame_len() {
+                Some((len, samples)) => {
+                    data.extend_from_slice(&self.buf.split_to(len));
+                    duration += samples as f64 * 1000.0 / sample_rate;
+                }
+                None if self.eof => break,
+                None => {
+                    self.buf.reserve(READ_BLOCK_SIZE);
+                    if self.reader.read_buf(&mut self.buf).await? == 0 {
+                        self.eof = true;
+                    }
+                }
+            }
+        }
+
+        if data.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(AudioChunk {
+            data: data.freeze(),
+            duration,
+            header: Some(stream_header),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::vorbis_comment::build_vorbis_comment;
+    use super::*;
+
+    /// a frame of 4096 samples at 44.1 kHz with a fixed size body
+    fn frame(number: u8) -> Vec<u8> {
+        let mut frame = vec![0xFF, 0xF8, 0xC9, 0x18, number];
+        frame.push(crc8(&frame));
+        frame.extend([0x00; 100]);
+        let crc = crc16(&frame);
+        frame.extend(crc.to_be_bytes());
+        frame
+    }
+
+    fn flac_file(frames: usize) -> Vec<u8> {
+        let mut file = FLAC_MAGIC.to_vec();
+
+        let mut stream_info = vec![0; STREAMINFO_SIZE];
+        // 44100 Hz, 2 channels, 16 bits per sample
+        stream_info[10] = 0x0A;
+        stream_info[11] = 0xC4;
+        stream_info[12] = 0x42;
+        stream_info[13] = 0xF0;
+        stream_info[14..18].copy_from_slice(&(frames as u32 * 4096).to_be_bytes());
+        file.extend([STREAMINFO, 0, 0, STREAMINFO_SIZE as u8]);
+        file.extend(stream_info);
+
+        let comment = build_vorbis_comment(&["TITLE=Song", "ARTIST=Artist"]);
+        file.extend([VORBIS_COMMENT, 0, 0, comment.len() as u8]);
+        file.extend(comment);
+
+        // a padding block that is not sent to the listeners
+        file.extend([0x80 | 1, 0, 0, 16]);
+        file.extend([0; 16]);
+
+        for i in 0..frames {
+            file.extend(frame(i as u8));
+        }
+        file
+    }
+
+    #[test]
+    fn test_parse_frame_header() {
+        assert_eq!(parse_frame_header(&frame(0)), Some(4096));
+        let mut invalid = frame(0);
+        invalid[5] ^= 1;
+        assert_eq!(parse_frame_header(&invalid), None);
+    }
+
+    #[tokio::test]
+    async fn test_read_flac_info() {
+        let file = flac_file(10);
+        let info = read_flac_info(std::io::Cursor::new(file))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!((info.duration - 40960.0 * 1000.0 / 44100.0).abs() < 0.001);
+        assert_eq!(info.title.as_deref(), Some("Song"));
+        assert_eq!(info.artist.as_deref(), Some("Artist"));
+    }
+
+    #[tokio::test]
+    async fn test_read_chunks() {
+        let file = flac_file(30);
+        let mut reader = FlacFrameReader::new(std::io::Cursor::new(file));
+        let mut frames = Vec::new();
+        while let Some(chunk) = reader.next_chunk(500.0).await.unwrap() {
+            assert_eq!(chunk.data.len() % frame(0).len(), 0);
+            frames.push(chunk.data.len() / frame(0).len());
+
+            let header = chunk.header.unwrap();
+            assert_eq!(&header[..4], FLAC_MAGIC);
+            // only the STREAMINFO block is kept, and it is the last block
+            assert_eq!(
+                header.len(),
+                4 + METADATA_BLOCK_HEADER_SIZE + STREAMINFO_SIZE
+            );
+            assert_eq!(header[4], 0x80);
+            let stream_info = StreamInfo::from_stream_header(&header).unwrap();
+            assert_eq!(stream_info.sample_rate, 44100);
+            assert_eq!(stream_info.channels, 2);
+        }
+        // 500 ms is 5.38 frames of 4096 samples
+        assert_eq!(frames, vec![6, 6, 6, 6, 6]);
+    }
+}
@@ -0,0 +1,122 @@
+use bytes::Bytes;
+use tokio::io::AsyncRead;
+
+mod flac;
+mod mp3;
+mod ogg;
+mod vorbis_comment;
+
+pub use flac::{FlacFrameReader, read_flac_info};
+pub use mp3::{Mp3FrameReader, read_mp3_info, split_chunks};
+pub use ogg::{OggPageReader, read_ogg_info};
+use vorbis_comment::VorbisComment;
+
+/// size of the blocks read from the underlying readers
+const READ_BLOCK_SIZE: usize = 65536;
+
+/// content type of the MPEG audio files
+pub const MP3_CONTENT_TYPE: &str = "audio/mpeg";
+
+/// content type of the FLAC files
+pub const FLAC_CONTENT_TYPE: &str = "audio/flac";
+
+/// content type of the Ogg files
+pub const OGG_CONTENT_TYPE: &str = "audio/ogg";
+
+/// A chunk of whole audio frames or pages.
+#[derive(Debug, Clone)]
+pub struct AudioChunk {
+    pub data: Bytes,
+    /// duration of the chunk in milliseconds
+    pub duration: f64,
+    /// the stream header needed to decode the chunk,
+    ///     the FLAC metadata or the Ogg header pages, None for MP3
+    pub header: Option<Bytes>,
+}
+
+/// Bitrate, sample rate and channels of a stream, advertised to the listeners.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioFormat {
+    /// bitrate in kbit/s
+    pub bitrate: u32,
+    /// sample rate in Hz, None if unknown
+    pub sample_rate: Option<u32>,
+    /// number of channels, None if unknown
+    pub channels: Option<u8>,
+}
+
+impl AudioFormat {
+    /// Read the format of a chunk of `duration` milliseconds.
+    /// The bitrate of MP3 is read from the header of the first frame,
+    ///     it is estimated from the size of the chunk for the other content types.
+    /// Returns None if the chunk is empty.
+    pub fn of_chunk(
+        content_type: &str,
+        data: &[u8],
+        header: Option<&[u8]>,
+        duration: f64,
+    ) -> Option<Self> {
+        if content_type == MP3_CONTENT_TYPE
+            && let Some(frame) = mp3::FrameHeader::parse(data)
+        {
+            return Some(Self {
+                bitrate: frame.bitrate,
+                sample_rate: Some(frame.sample_rate),
+                channels: Some(if frame.mono { 1 } else { 2 }),
+            });
+        }
+        if data.is_empty() || duration <= 0.0 {
+            return None;
+        }
+        // bytes per millisecond are kbytes per second
+        let bitrate = (data.len() as f64 * 8.0 / duration).round() as u32;
+        let stream_info = header
+            .filter(|_| content_type == FLAC_CONTENT_TYPE)
+            .and_then(flac::StreamInfo::from_stream_header);
+        Some(Self {
+            bitrate,
+            sample_rate: stream_info.map(|info| info.sample_rate),
+            channels: stream_info.map(|info| info.channels),
+        })
+    }
+}
+
+/// Duration and tags of an audio file read by the native readers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioInfo {
+    /// duration in milliseconds
+    pub duration: f64,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+}
+
+/// ChunkReader reads chunks of whole frames or pages of the supported containers.
+pub enum ChunkReader<R> {
+    Mp3(Mp3FrameReader<R>),
+    Flac(FlacFrameReader<R>),
+    Ogg(OggPageReader<R>),
+}
+
+impl<R: AsyncRead + Unpin> ChunkReader<R> {
+    /// Create the reader of the content type,
+    ///     the reader is given back if the content type is not supported.
+    pub fn new(content_type: &str, reader: R) -> Result<Self, R> {
+        match content_type {
+            MP3_CONTENT_TYPE => Ok(Self::Mp3(Mp3FrameReader::new(reader))),
+            FLAC_CONTENT_TYPE => Ok(Self::Flac(FlacFrameReader::new(reader))),
+            OGG_CONTENT_TYPE => Ok(Self::Ogg(OggPageReader::new(reader))),
+            _ => Err(reader),
+        }
+    }
+
+    /// Read the next chunk of at least `chunk_duration` milliseconds,
+    ///     the last chunk may be shorter.
+    /// Returns None at the end of the data.
+    pub async fn next_chunk(&mut self, chunk_duration: f64) -> std::io::Result<Option<AudioChunk>> {
+        match self {
+            Self::Mp3(reader) => reader.next_chunk(chunk_duration).await,
+            Self::Flac(reader) => reader.next_chunk(chunk_duration).await,
+            Self::Ogg(reader) => reader.next_chunk(chunk_duration).await,
+        }
+    }
+}
@@ -0,0 +1,776 @@
+use bytes::{Bytes, BytesMut};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use super::{AudioChunk, READ_BLOCK_SIZE};
+
+/// size of an ID3v2 tag header, also the size of its optional footer
+const ID3V2_HEADER_SIZE: usize = 10;
+
+/// size of an ID3v1 tag
+const ID3V1_SIZE: usize = 128;
+
+/// size of an APE tag header or footer
+const APE_HEADER_SIZE: usize = 32;
+
+/// size of an MPEG audio frame header
+const FRAME_HEADER_SIZE: usize = 4;
+
+/// bitrates in kbit/s indexed by `[version][layer][bitrate index]`,
+///     `version` is 0 for MPEG 1 and 1 for MPEG 2 and 2.5,
+///     `layer` is 0 for Layer I, 1 for Layer II and 2 for Layer III
+const BITRATES: [[[u32; 15]; 3]; 2] = [
+    [
+        [
+            0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448,
+        ],
+        [
+            0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+        ],
+        [
+            0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+        ],
+    ],
+    [
+        [
+            0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256,
+        ],
+        [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+        [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+    ],
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MpegVersion {
+    Mpeg1,
+    Mpeg2,
+    Mpeg25,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    Layer1,
+    Layer2,
+    Layer3,
+}
+
+/// The header of an MPEG audio frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub version: MpegVersion,
+    pub layer: Layer,
+    /// bitrate in kbit/s
+    pub bitrate: u32,
+    /// sample rate in Hz
+    pub sample_rate: u32,
+    pub padding: bool,
+    /// whether the frame has a single channel
+    pub mono: bool,
+}
+
+impl FrameHeader {
+    /// Parse the header at the beginning of `b`,
+    ///     returns None if `b` does not start with a valid header.
+    /// Free format frames are not supported, their length is unknown.
+    pub fn parse(b: &[u8]) -> Option<Self> {
+        if b.len() < FRAME_HEADER_SIZE || b[0] != 0xFF || b[1] & 0xE0 != 0xE0 {
+            return None;
+        }
+
+        let version = match (b[1] >> 3) & 0b11 {
+            0b00 => MpegVersion::Mpeg25,
+            0b10 => MpegVersion::Mpeg2,
+            0b11 => MpegVersion::Mpeg1,
+            _ => return None,
+        };
+        let layer = match (b[1] >> 1) & 0b11 {
+            0b01 => Layer::Layer3,
+            0b10 => Layer::Layer2,
+            0b11 => Layer::Layer1,
+            _ => return None,
+        };
+
+        let bitrate_index = (b[2] >> 4) as usize;
+        if bitrate_index == 0 || bitrate_index == 15 {
+            return None;
+        }
+        let version_index = match version {
+            MpegVersion::Mpeg1 => 0,
+            _ => 1,
+        };
+        let layer_index = match layer {
+            Layer::Layer1 => 0,
+            Layer::Layer2 => 1,
+            Layer::Layer3 => 2,
+        };
+        let bitrate = BITRATES[version_index][layer_index][bitrate_index];
+
+        let sample_rate = match ((b[2] >> 2) & 0b11, version) {
+            (0b11, _) => return None,
+            (i, MpegVersion::Mpeg1) => [44100, 48000, 32000][i as usize],
+            (i, MpegVersion::Mpeg2) => [22050, 24000, 16000][i as usize],
+            (i, MpegVersion::Mpeg25) => [11025, 12000, 8000][i as usize],
+        };
+
+        // the emphasis value 0b10 is reserved
+        if b[3] & 0b11 == 0b10 {
+            return None;
+        }
+
+        Some(Self {
+            version,
+            layer,
+            bitrate,
+            sample_rate,
+            padding: (b[2] >> 1) & 1 == 1,
+            mono: b[3] >> 6 == 0b11,
+        })
+    }
+
+    /// length of the frame in bytes, including the header
+    pub fn frame_len(&self) -> usize {
+        let bitrate = self.bitrate as usize * 1000;
+        let sample_rate = self.sample_rate as usize;
+        let padding = self.padding as usize;
+        match (self.layer, self.version) {
+            (Layer::Layer1, _) => (12 * bitrate / sample_rate + padding) * 4,
+            (Layer::Layer3, MpegVersion::Mpeg2 | MpegVersion::Mpeg25) => {
+                72 * bitrate / sample_rate + padding
+            }
+            _ => 144 * bitrate / sample_rate + padding,
+        }
+    }
+
+    /// number of samples per channel in the frame
+    pub fn samples(&self) -> usize {
+        match (self.layer, self.version) {
+            (Layer::Layer1, _) => 384,
+            (Layer::Layer3, MpegVersion::Mpeg2 | MpegVersion::Mpeg25) => 576,
+            _ => 1152,
+        }
+    }
+
+    /// duration of the frame in milliseconds
+    pub fn duration(&self) -> f64 {
+        self.samples() as f64 * 1000.0 / self.sample_rate as f64
+    }
+
+    /// whether a frame with the header `other` can follow a frame with this header,
+    ///     the bitrate and padding may change between frames
+    fn is_compatible(&self, other: &Self) -> bool {
+        self.version == other.version
+            && self.layer == other.layer
+            && self.sample_rate == other.sample_rate
+    }
+}
+
+/// length of the tag at the beginning of `b`
+enum TagLen {
+    NotTag,
+    Tag(usize),
+    /// more bytes are needed to know the length of the tag
+    Incomplete,
+}
+
+/// Get the length of the ID3v2, ID3v1 or APE tag at the beginning of `b`.
+fn tag_len(b: &[u8]) -> TagLen {
+    fn is_partial_magic(b: &[u8], magic: &[u8]) -> bool {
+        b.len() < magic.len() && magic.starts_with(b)
+    }
+
+    if b.starts_with(b"ID3") {
+        if b.len() < ID3V2_HEADER_SIZE {
+            return TagLen::Incomplete;
+        }
+        // the size is a 28 bits sync safe integer
+        if b[6..10].iter().any(|&x| x & 0x80 != 0) {
+            return TagLen::NotTag;
+        }
+        let size = b[6..10]
+            .iter()
+            .fold(0usize, |acc, &x| (acc << 7) | x as usize);
+        let footer = if b[5] & 0x10 != 0 {
+            ID3V2_HEADER_SIZE
+        } else {
+            0
+        };
+        return TagLen::Tag(ID3V2_HEADER_SIZE + size + footer);
+    }
+
+    if b.starts_with(b"TAG") {
+        return TagLen::Tag(ID3V1_SIZE);
+    }
+
+    if b.starts_with(b"APETAGEX") {
+        if b.len() < APE_HEADER_SIZE {
+            return TagLen::Incomplete;
+        }
+        // the size includes the items and the footer, but not the header
+        let size = u32::from_le_bytes([b[12], b[13], b[14], b[15]]) as usize;
+        let flags = u32::from_le_bytes([b[20], b[21], b[22], b[23]]);
+        let is_header = flags & (1 << 29) != 0;
+        return if is_header {
+            TagLen::Tag(APE_HEADER_SIZE + size)
+        } else {
+            // a footer without a header, the items before it have already been skipped
+            TagLen::Tag(APE_HEADER_SIZE)
+        };
+    }
+
+    if is_partial_magic(b, b"ID3")
+        || is_partial_magic(b, b"TAG")
+        || is_partial_magic(b, b"APETAGEX")
+    {
+        return TagLen::Incomplete;
+    }
+
+    TagLen::NotTag
+}
+
+/// whether `b` starts with the beginning of a tag
+fn starts_with_tag(b: &[u8]) -> bool {
+    b.starts_with(b"ID3") || b.starts_with(b"TAG") || b.starts_with(b"APETAGEX")
+}
+
+/// A complete MPEG audio frame.
+#[derive(Debug, Clone)]
+pub struct Mp3Frame {
+    pub header: FrameHeader,
+    pub data: Bytes,
+}
+
+/// Mp3FrameParser splits MPEG audio data into frames,
+///     the ID3v2, ID3v1 and APE tags and the garbage between frames are skipped.
+///
+/// A frame is only accepted if it is followed by another compatible frame, a tag,
+///     or the end of the data, to avoid syncing on a false frame header.
+#[derive(Default)]
+pub struct Mp3FrameParser {
+    buf: BytesMut,
+    /// number of bytes of a tag that still need to be skipped
+    skip: usize,
+    /// the header of the first frame, the next frames must be compatible with it
+    reference: Option<FrameHeader>,
+    /// number of bytes consumed from the beginning of the data
+    position: usize,
+    /// offset of the first frame from the beginning of the data
+    audio_start: Option<usize>,
+    /// the Xing, Info or VBRI header of the first frame
+    vbr_header: Option<VbrHeader>,
+}
+
+impl Mp3FrameParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// push more data to the parser
+    pub fn push(&mut self, data: &[u8]) {
+        let skipped = self.skip.min(data.len());
+        self.skip -= skipped;
+        self.position += skipped;
+        self.buf.extend_from_slice(&data[skipped..]);
+    }
+
+    /// Get the next complete frame,
+    ///     returns None if more data is needed,
+    ///     or if `eof` is true and there is no frame left.
+    pub fn next_frame(&mut self, eof: bool) -> Option<Mp3Frame> {
+        loop {
+            if self.buf.is_empty() {
+                return None;
+            }
+
+            match tag_len(&self.buf) {
+                TagLen::Tag(len) => {
+                    if self.buf.len() >= len {
+                        self.advance(len);
+                    } else {
+                        self.skip = len - self.buf.len();
+                        self.advance(self.buf.len());
+                    }
+                    continue;
+                }
+                TagLen::Incomplete if !eof => return None,
+                TagLen::Incomplete | TagLen::NotTag => {}
+            }
+
+            if self.buf.len() < FRAME_HEADER_SIZE {
+                if eof {
+                    self.advance(self.buf.len());
+                }
+                return None;
+            }
+
+            let header = match FrameHeader::parse(&self.buf) {
+                Some(header)
+                    if self
+                        .reference
+                        .is_none_or(|reference| reference.is_compatible(&header)) =>
+                {
+                    header
+                }
+                _ => {
+                    self.skip_garbage();
+                    continue;
+                }
+            };
+
+            let len = header.frame_len();
+            if self.buf.len() < len + FRAME_HEADER_SIZE && !eof {
+                return None;
+            }
+            if self.buf.len() < len {
+                // the last frame is truncated
+                self.advance(self.buf.len());
+                return None;
+            }
+
+            // check the frame is followed by a valid frame, a tag, or the end of the data
+            let next = &self.buf[len..];
+            let next_is_valid = next.len() < FRAME_HEADER_SIZE
+                || starts_with_tag(next)
+                || FrameHeader::parse(next).is_some_and(|next| header.is_compatible(&next));
+            if !next_is_valid && self.reference.is_none() {
+                self.skip_garbage();
+                continue;
+            }
+
+            let frame = Mp3Frame {
+                header,
+                data: self.buf.split_to(len).freeze(),
+            };
+            if self.reference.is_none() {
+                self.reference = Some(header);
+                self.audio_start = Some(self.position);
+                self.position += len;
+                // the VBR header frame is a silent frame that only carries information
+                self.vbr_header = VbrHeader::parse(&frame);
+                if self.vbr_header.is_some() {
+                    continue;
+                }
+            } else {
+                self.position += len;
+            }
+            return Some(frame);
+        }
+    }
+
+    /// offset of the first frame from the beginning of the data,
+    ///     None before the first frame is parsed
+    pub fn audio_start(&self) -> Option<usize> {
+        self.audio_start
+    }
+
+    /// the Xing, Info or VBRI header of the data, the header frame is not returned as audio
+    pub fn vbr_header(&self) -> Option<&VbrHeader> {
+        self.vbr_header.as_ref()
+    }
+
+    fn advance(&mut self, len: usize) {
+        let _ = self.buf.split_to(len);
+        self.position += len;
+    }
+
+    /// skip the garbage until the next possible frame header or tag
+    fn skip_garbage(&mut self) {
+        let next = self.buf[1..]
+            .iter()
+            .position(|&b| b == 0xFF || b == b'I' || b == b'T' || b == b'A')
+            .map(|i| i + 1)
+            .unwrap_or(self.buf.len());
+        self.advance(next);
+    }
+}
+
+/// The Xing, Info or VBRI header stored in the first frame of an MPEG audio stream,
+///     with the encoder delay and padding of the LAME tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VbrHeader {
+    /// number of audio frames, without the header frame
+    pub frames: Option<u32>,
+    /// number of bytes of audio frames, with the header frame
+    pub bytes: Option<u32>,
+    /// number of samples added by the encoder at the beginning of the stream
+    pub encoder_delay: u32,
+    /// number of samples added by the encoder at the end of the stream
+    pub encoder_padding: u32,
+}
+
+impl VbrHeader {
+    /// Parse the VBR header of a frame, returns None if the frame does not carry one.
+    pub fn parse(frame: &Mp3Frame) -> Option<Self> {
+        if frame.header.layer != Layer::Layer3 {
+            return None;
+        }
+        let data = &frame.data;
+        let read_u32 = |at: usize| -> Option<u32> {
+            let b = data.get(at..at + 4)?;
+            Some(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        };
+
+        // the Xing header is right after the side information
+        let side_info_len = match (frame.header.version, frame.header.mono) {
+            (MpegVersion::Mpeg1, true) => 17,
+            (MpegVersion::Mpeg1, false) => 32,
+            (_, true) => 9,
+            (_, false) => 17,
+        };
+        let xing = FRAME_HEADER_SIZE + side_info_len;
+        if let Some(magic) = data.get(xing..xing + 4)
+            && (magic == b"Xing" || magic == b"Info")
+        {
+            let flags = read_u32(xing + 4)?;
+            let mut at = xing + 8;
+            let mut field = |flag: u32, len: usize| {
+                if flags & flag == 0 {
+                    return None;
+                }
+                let field_at = at;
+                at += len;
+                Some(field_at)
+            };
+            let frames = field(0x1, 4).and_then(read_u32);
+            let bytes = field(0x2, 4).and_then(read_u32);
+            // the table of contents and the quality indicator are not used
+            let _ = field(0x4, 100);
+            let _ = field(0x8, 4);
+
+            // the LAME tag (or the Lavc tag of ffmpeg) follows the Xing header,
+            //     the delay and padding are two 12 bits integers 21 bytes after its start
+            let (encoder_delay, encoder_padding) = match data.get(at..at + 24) {
+                Some(tag) if tag.starts_with(b"LAME") || tag.starts_with(b"Lavc") => (
+                    ((tag[21] as u32) << 4) | ((tag[22] as u32) >> 4),
+                    (((tag[22] & 0x0F) as u32) << 8) | tag[23] as u32,
+                ),
+                _ => (0, 0),
+            };
+
+            return Some(Self {
+                frames,
+                bytes,
+                encoder_delay,
+                encoder_padding,
+            });
+        }
+
+        // the VBRI header of the Fraunhofer encoder is always 32 bytes after the frame header
+        let vbri = FRAME_HEADER_SIZE + 32;
+        if data.get(vbri..vbri + 4)? == b"VBRI" {
+            let delay = data.get(vbri + 6..vbri + 8)?;
+            return Some(Self {
+                frames: read_u32(vbri + 14),
+                bytes: read_u32(vbri + 10),
+                encoder_delay: u16::from_be_bytes([delay[0], delay[1]]) as u32,
+                encoder_padding: 0,
+            });
+        }
+
+        None
+    }
+}
+
+/// Duration and size of the audio of an MPEG audio stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mp3Info {
+    /// duration in milliseconds
+    pub duration: f64,
+    /// number of bytes of the audio frames, the tags are not included
+    pub audio_bytes: usize,
+}
+
+impl Mp3Info {
+    /// average bitrate in kbit/s
+    pub fn bitrate(&self) -> f64 {
+        self.audio_bytes as f64 * 8.0 / self.duration
+    }
+}
+
+/// Read the duration and the size of the audio from the first frame of an MPEG audio stream.
+/// The Xing, Info or VBRI header is used if there is one, which is exact for VBR streams,
+///     otherwise the stream is assumed to be CBR and the duration is computed from `size`,
+///     the size of the whole stream in bytes.
+/// Only the tags before the first frame and the first frame are read.
+pub async fn read_mp3_info(
+    reader: impl AsyncRead + Unpin,
+    size: usize,
+) -> std::io::Result<Option<Mp3Info>> {
+    let mut reader = reader;
+    let mut parser = Mp3FrameParser::new();
+    let mut buf = vec![0; READ_BLOCK_SIZE];
+    let mut eof = false;
+    let frame = loop {
+        if let Some(frame) = parser.next_frame(eof) {
+            break Some(frame);
+        }
+        if parser.vbr_header().is_some() || eof {
+            break None;
+        }
+        let read = reader.read(&mut buf).await?;
+        eof = read == 0;
+        parser.push(&buf[..read]);
+    };
+
+    let Some(audio_start) = parser.audio_start() else {
+        return Ok(None);
+    };
+    let header = match (&frame, parser.reference) {
+        (Some(frame), _) => frame.header,
+        (None, Some(reference)) => reference,
+        (None, None) => return Ok(None),
+    };
+    let audio_bytes = size.saturating_sub(audio_start);
+
+    if let Some(vbr) = parser.vbr_header()
+        && let Some(frames) = vbr.frames
+    {
+        let samples = (frames as u64 * header.samples() as u64)
+            .saturating_sub(vbr.encoder_delay as u64 + vbr.encoder_padding as u64);
+        return Ok(Some(Mp3Info {
+            duration: samples as f64 * 1000.0 / header.sample_rate as f64,
+            audio_bytes: vbr.bytes.map(|b| b as usize).unwrap_or(audio_bytes),
+        }));
+    }
+
+    // CBR stream, every frame has the bitrate of the first frame
+    Ok(Some(Mp3Info {
+        duration: audio_bytes as f64 * 8.0 / header.bitrate as f64,
+        audio_bytes,
+    }))
+}
+
+/// Split the MPEG audio data in `data` into chunks of at least `chunk_duration` milliseconds,
+///     the last chunk may be shorter.
+pub fn split_chunks(data: &[u8], chunk_duration: f64) -> Vec<AudioChunk> {
+    let mut parser = Mp3FrameParser::new();
+    parser.push(data);
+    let mut chunks = Vec::new();
+    let mut chunk = ChunkBuilder::default();
+    while let Some(frame) = parser.next_frame(true) {
+        chunk.push(frame);
+        if chunk.duration >= chunk_duration {
+            chunks.push(chunk.build());
+        }
+    }
+    if chunk.duration > 0.0 {
+        chunks.push(chunk.build());
+    }
+    chunks
+}
+
+#[derive(Default)]
+struct ChunkBuilder {
+    data: BytesMut,
+    duration: f64,
+}
+
+impl ChunkBuilder {
+    fn push(&mut self, frame: Mp3Frame) {
+        self.data.extend_from_slice(&frame.data);
+        self.duration += frame.header.duration();
+    }
+
+    fn build(&mut self) -> AudioChunk {
+        let chunk = AudioChunk {
+            data: self.data.split().freeze(),
+            duration: self.duration,
+            header: None,
+        };
+        self.duration = 0.0;
+        chunk
+    }
+}
+
+/// Mp3FrameReader reads chunks of whole MPEG audio frames from a reader.
+pub struct Mp3FrameReader<R> {
+    reader: R,
+    parser: Mp3FrameParser,
+    /// the block read from `reader`, reused for every read
+    buf: Vec<u8>,
+    eof: bool,
+}
+
+impl<R: AsyncRead + Unpin> Mp3FrameReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            parser: Mp3FrameParser::new(),
+            buf: vec![0; READ_BLOCK_SIZE],
+            eof: false,
+        }
+    }
+
+    /// Read the next chunk of at least `chunk_duration` milliseconds,
+    ///     the last chunk may be shorter.
+    /// Returns None at the end of the data.
+    pub async fn next_chunk(&mut self, chunk_duration: f64) -> std::io::Result<Option<AudioChunk>> {
+        let mut chunk = ChunkBuilder::default();
+        while chunk.duration < chunk_duration {
+            match self.parser.next_frame(self.eof) {
+                Some(frame) => chunk.push(frame),
+                None if self.eof => break,
+                None => {
+                    let read = self.reader.read(&mut self.buf).await?;
+                    if read == 0 {
+                        self.eof = true;
+                    }
+                    self.parser.push(&self.buf[..read]);
+                }
+            }
+        }
+
+        if chunk.duration == 0.0 {
+            return Ok(None);
+        }
+        Ok(Some(chunk.build()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// header of a 128 kbit/s 44.1 kHz MPEG 1 Layer III frame without padding,
+    ///     the frame is 417 bytes long
+    const HEADER: [u8; 4] = [0xFF, 0xFB, 0x90, 0x64];
+
+    fn frame() -> Vec<u8> {
+        let mut frame = HEADER.to_vec();
+        frame.resize(417, 0x55);
+        frame
+    }
+
+    fn id3v2(body_len: usize) -> Vec<u8> {
+        let mut tag = b"ID3\x04\x00\x00".to_vec();
+        let len = body_len as u32;
+        tag.extend([
+            (len >> 21) as u8 & 0x7F,
+            (len >> 14) as u8 & 0x7F,
+            (len >> 7) as u8 & 0x7F,
+            len as u8 & 0x7F,
+        ]);
+        // the body contains a fake frame header that must not be synced on
+        tag.extend(HEADER);
+        tag.resize(ID3V2_HEADER_SIZE + body_len, 0xFF);
+        tag
+    }
+
+    /// an Info frame of `frames` frames and `bytes` bytes, with a LAME tag
+    fn info_frame(frames: u32, bytes: u32) -> Vec<u8> {
+        let mut frame = HEADER.to_vec();
+        frame.resize(36, 0);
+        frame.extend(b"Info");
+        frame.extend(3u32.to_be_bytes());
+        frame.extend(frames.to_be_bytes());
+        frame.extend(bytes.to_be_bytes());
+        frame.extend(b"LAME3.100");
+        frame.resize(frame.len() + 12, 0);
+        // encoder delay 576, encoder padding 1728
+        frame.extend([0x24, 0x06, 0xC0]);
+        frame.resize(417, 0);
+        frame
+    }
+
+    #[test]
+    fn test_parse_header() {
+        let header = FrameHeader::parse(&HEADER).unwrap();
+        assert_eq!(header.version, MpegVersion::Mpeg1);
+        assert_eq!(header.layer, Layer::Layer3);
+        assert_eq!(header.bitrate, 128);
+        assert_eq!(header.sample_rate, 44100);
+        assert_eq!(header.frame_len(), 417);
+        assert_eq!(header.samples(), 1152);
+        assert!((header.duration() - 26.122).abs() < 0.001);
+
+        assert!(FrameHeader::parse(&[0xFF, 0xFB, 0xF0, 0x64]).is_none());
+        assert!(FrameHeader::parse(b"TAG!").is_none());
+    }
+
+    #[test]
+    fn test_skip_tags() {
+        let mut data = id3v2(300);
+        data.extend(frame());
+        data.extend(frame());
+        data.extend(b"garbage");
+        data.extend(frame());
+        let mut ape = b"APETAGEX".to_vec();
+        ape.extend(2000u32.to_le_bytes());
+        ape.extend(40u32.to_le_bytes());
+        ape.extend(0u32.to_le_bytes());
+        ape.extend((1u32 << 29).to_le_bytes());
+        ape.resize(APE_HEADER_SIZE + 40, 0xFF);
+        data.extend(ape);
+        let mut id3v1 = b"TAG".to_vec();
+        id3v1.resize(ID3V1_SIZE, 0xFF);
+        data.extend(id3v1);
+
+        let chunks = split_chunks(&data, 1000.0);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].data, frame().repeat(3));
+        assert!((chunks[0].duration - 3.0 * 26.122).abs() < 0.01);
+    }
+
+    #[tokio::test]
+    async fn test_read_vbr_info() {
+        let mut data = id3v2(5000);
+        data.extend(info_frame(100, 417 * 101));
+        for _ in 0..100 {
+            data.extend(frame());
+        }
+
+        let mut parser = Mp3FrameParser::new();
+        parser.push(&data);
+        let vbr_header = VbrHeader {
+            frames: Some(100),
+            bytes: Some(417 * 101),
+            encoder_delay: 576,
+            encoder_padding: 1728,
+        };
+        // the info frame is not returned as audio
+        assert_eq!(parser.next_frame(true).unwrap().data, frame());
+        assert_eq!(parser.vbr_header(), Some(&vbr_header));
+
+        let info = read_mp3_info(std::io::Cursor::new(&data), data.len())
+            .await
+            .unwrap()
+            .unwrap();
+        // 100 frames of 1152 samples, minus the delay and the padding
+        let duration = (100.0 * 1152.0 - 576.0 - 1728.0) * 1000.0 / 44100.0;
+        assert!((info.duration - duration).abs() < 0.001);
+        assert_eq!(info.audio_bytes, 417 * 101);
+    }
+
+    #[tokio::test]
+    async fn test_read_cbr_info() {
+        let mut data = id3v2(5000);
+        for _ in 0..100 {
+            data.extend(frame());
+        }
+
+        let info = read_mp3_info(std::io::Cursor::new(&data), data.len())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(info.audio_bytes, 41700);
+        assert!((info.duration - 41700.0 * 8.0 / 128.0).abs() < 0.001);
+        assert!((info.bitrate() - 128.0).abs() < 0.001);
+    }
+
+    #[tokio::test]
+    async fn test_reader_chunks() {
+        let mut data = id3v2(100_000);
+        for _ in 0..100 {
+            data.extend(frame());
+        }
+        // a truncated frame at the end is dropped
+        data.extend(&frame()[..200]);
+
+        let mut reader = Mp3FrameReader::new(std::io::Cursor::new(data));
+        let mut frames = Vec::new();
+        while let Some(chunk) = reader.next_chunk(500.0).await.unwrap() {
+            assert_eq!(chunk.data.len() % 417, 0);
+            frames.push(chunk.data.len() / 417);
+        }
+        // 500 ms is 19.14 frames
+        assert_eq!(frames, vec![20, 20, 20, 20, 20]);
+    }
+}
@@ -0,0 +1,480 @@
+use bytes::{Bytes, BytesMut};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+use super::{AudioChunk, AudioInfo, READ_BLOCK_SIZE, VorbisComment};
+
+/// the capture pattern at the beginning of each page
+const OGG_MAGIC: &[u8; 4] = b"OggS";
+
+/// size of the page header, without the segment table
+const PAGE_HEADER_SIZE: usize = 27;
+
+/// flag of the header type of the first page of a logical stream
+const BOS_FLAG: u8 = 0x02;
+
+/// granule position of the pages on which no packet ends
+const NO_GRANULE_POSITION: i64 = -1;
+
+/// sample rate of the granule positions of Opus, whatever the sample rate of the input
+const OPUS_GRANULE_RATE: u32 = 48000;
+
+/// number of bytes read from the end of a file to find the last page
+const LAST_PAGE_SEARCH_SIZE: u64 = 65536;
+
+/// A page of an Ogg stream.
+struct Page {
+    header_type: u8,
+    granule_position: i64,
+    /// the lacing values of the segments of the page
+    segments: Vec<u8>,
+    /// the whole page, with its header
+    data: Bytes,
+    /// offset of the body in `data`
+    body_start: usize,
+}
+
+/// length of the page at the beginning of some data
+enum PageLen {
+    NotPage,
+    Page(usize),
+    /// more bytes are needed to know the length of the page
+    Incomplete,
+}
+
+impl Page {
+    /// Get the length of the page at the beginning of `b`.
+    fn len(b: &[u8]) -> PageLen {
+        if b.len() < OGG_MAGIC.len() {
+            return match OGG_MAGIC.starts_with(b) {
+                true => PageLen::Incomplete,
+                false => PageLen::NotPage,
+            };
+        }
+        if &b[..4] != OGG_MAGIC || b.get(4).is_some_and(|&version| version != 0) {
+            return PageLen::NotPage;
+        }
+        if b.len() < PAGE_HEADER_SIZE {
+            return PageLen::Incomplete;
+        }
+        match b.get(PAGE_HEADER_SIZE..PAGE_HEADER_SIZE + b[26] as usize) {
+            Some(segments) => PageLen::Page(
+                PAGE_HEADER_SIZE
+                    + segments.len()
+                    + segments.iter().map(|&s| s as usize).sum::<usize>(),
+            ),
+            None => PageLen::Incomplete,
+        }
+    }
+
+    fn parse(data: Bytes) -> Self {
+        let segment_count = data[26] as usize;
+        Self {
+            header_type: data[5],
+            granule_position: i64::from_le_bytes(data[6..14].try_into().unwrap()),
+            segments: data[PAGE_HEADER_SIZE..PAGE_HEADER_SIZE + segment_count].to_vec(),
+            body_start: PAGE_HEADER_SIZE + segment_count,
+            data,
+        }
+    }
+
+    fn body(&self) -> &[u8] {
+        &self.data[self.body_start..]
+    }
+
+    /// number of packets ending on the page
+    fn packets_ended(&self) -> usize {
+        self.segments.iter().filter(|&&s| s < 255).count()
+    }
+}
+
+/// Supported codecs of the Ogg streams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Codec {
+    Vorbis {
+        sample_rate: u32,
+    },
+    Opus {
+        /// number of samples to skip at the beginning of the stream
+        pre_skip: u32,
+    },
+}
+
+impl Codec {
+    /// Identify the codec from the first packet of a logical stream.
+    fn identify(packet: &[u8]) -> Option<Self> {
+        if packet.starts_with(b"\x01vorbis") {
+            let rate = packet.get(12..16)?;
+            let sample_rate = u32::from_le_bytes(rate.try_into().ok()?);
+            if sample_rate == 0 {
+                return None;
+            }
+            return Some(Self::Vorbis { sample_rate });
+        }
+        if packet.starts_with(b"OpusHead") {
+            let pre_skip = packet.get(10..12)?;
+            return Some(Self::Opus {
+                pre_skip: u16::from_le_bytes(pre_skip.try_into().ok()?) as u32,
+            });
+        }
+        None
+    }
+
+    /// number of header packets at the beginning of a logical stream
+    fn header_packets(&self) -> usize {
+        match self {
+            Self::Vorbis { .. } => 3,
+            Self::Opus { .. } => 2,
+        }
+    }
+
+    /// prefix of the comment header packet
+    fn comment_prefix(&self) -> &'static [u8] {
+        match self {
+            Self::Vorbis { .. } => b"\x03vorbis",
+            Self::Opus { .. } => b"OpusTags",
+        }
+    }
+
+    fn granule_rate(&self) -> u32 {
+        match self {
+            Self::Vorbis { sample_rate } => *sample_rate,
+            Self::Opus { .. } => OPUS_GRANULE_RATE,
+        }
+    }
+
+    /// granule position of the first sample played
+    fn start_granule(&self) -> i64 {
+        match self {
+            Self::Vorbis { .. } => 0,
+            Self::Opus { pre_skip } => *pre_skip as i64,
+        }
+    }
+}
+
+/// The header pages of a logical stream.
+struct StreamHeader {
+    codec: Codec,
+    comment: Option<VorbisComment>,
+    /// the header pages, sent to the listeners before the audio pages
+    pages: Bytes,
+}
+
+impl StreamHeader {
+    /// Parse the header pages of a logical stream, returns None if the codec is not supported.
+    fn parse(pages: &[Page]) -> Option<Self> {
+        // the header packets may span several pages
+        let mut packets = vec![Vec::new()];
+        for page in pages {
+            let mut at = 0;
+            for &segment in &page.segments {
+                let segment = segment as usize;
+                packets
+                    .last_mut()?
+                    .extend_from_slice(page.body().get(at..at + segment)?);
+                at += segment;
+                if segment < 255 {
+                    packets.push(Vec::new());
+                }
+            }
+        }
+
+        let codec = Codec::identify(packets.first()?)?;
+        let comment = packets
+            .get(1)
+            .and_then(|p| p.strip_prefix(codec.comment_prefix()))
+            .and_then(VorbisComment::parse);
+        let mut data = BytesMut::new();
+        for page in pages {
+            data.extend_from_slice(&page.data);
+        }
+        Some(Self {
+            codec,
+            comment,
+            pages: data.freeze(),
+        })
+    }
+}
+
+/// OggPageReader reads chunks of whole Ogg pages from a reader.
+/// The header pages of each logical stream are not part of the chunks,
+///     they are set as the header of the chunks.
+/// Chained streams are supported, the header changes with the logical stream.
+pub struct OggPageReader<R> {
+    reader: R,
+    buf: BytesMut,
+    header: Option<StreamHeader>,
+    /// granule position of the end of the previous audio page
+    granule_position: i64,
+    eof: bool,
+}
+
+impl<R: AsyncRead + Unpin> OggPageReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buf: BytesMut::new(),
+            header: None,
+            granule_position: 0,
+            eof: false,
+        }
+    }
+
+    /// Read the next page, returns None at the end of the data.
+    async fn next_page(&mut self) -> std::io::Result<Option<Page>> {
+        loop {
+            match Page::len(&self.buf) {
+                PageLen::Page(len) if self.buf.len() >= len => {
+                    return Ok(Some(Page::parse(self.buf.split_to(len).freeze())));
+                }
+                PageLen::Page(_) | PageLen::Incomplete => {}
+                PageLen::NotPage => {
+                    // skip the garbage until the next capture pattern
+                    let next = self.buf[1..]
+                        .iter()
+                        .position(|&b| b == OGG_MAGIC[0])
+                        .map(|i| i + 1)
+                        .unwrap_or(self.buf.len());
+                    let _ = self.buf.split_to(next);
+                    continue;
+                }
+            }
+
+            if self.eof {
+                return Ok(None);
+            }
+            self.buf.reserve(READ_BLOCK_SIZE);
+            if self.reader.read_buf(&mut self.buf).await? == 0 {
+                self.eof = true;
+            }
+        }
+    }
+
+    /// Read the header pages of a logical stream starting with `first`.
+    async fn read_header(&mut self, first: Page) -> std::io::Result<()> {
+        let Some(codec) = first
+            .body()
+            .get(..first.segments.first().copied().unwrap_or(0) as usize)
+            .and_then(Codec::identify)
+        else {
+            return Err(std::io::Error::other("unsupported Ogg codec"));
+        };
+
+        let mut packets = first.packets_ended();
+        let mut pages = vec![first];
+        while packets < codec.header_packets() {
+            let Some(page) = self.next_page().await? else {
+                return Err(std::io::Error::other("truncated Ogg header"));
+            };
+            packets += page.packets_ended();
+            pages.push(page);
+        }
+
+        let Some(header) = StreamHeader::parse(&pages) else {
+            return Err(std::io::Error::other("invalid Ogg header"));
+        };
+        self.granule_position = header.codec.start_granule();
+        self.header = Some(header);
+        Ok(())
+    }
+
+    /// Read the next chunk of at least `chunk_duration` milliseconds,
+    ///     the last chunk may be shorter, and a chunk never spans two logical streams.
+    /// Returns None at the end of the data,
+    ///     and an error if the data is not an Ogg Vorbis or Ogg Opus stream.
+    pub async fn next_chunk(&mut self, chunk_duration: f64) -> std::io::Result<Option<AudioChunk>> {
+        let mut data = BytesMut::new();
+        let mut duration = 0.0;
+        while duration < chunk_duration {
+            let Some(page) = self.next_page().await? else {
+                break;
+            };
+
+            if page.header_type & BOS_FLAG != 0 {
+                if !data.is_empty() {
+                    // a new logical stream starts, keep its pages for the next chunk
+                    let mut rest = BytesMut::from(&page.data[..]);
+                    rest.extend_from_slice(&self.buf);
+                    self.buf = rest;
+                    break;
+                }
+                self.read_header(page).await?;
+                continue;
+            }
+            let Some(header) = self.header.as_ref() else {
+                return Err(std::io::Error::other("Ogg stream without header"));
+            };
+
+            if page.granule_position != NO_GRANULE_POSITION {
+                let samples = (page.granule_position - self.granule_position).max(0);
+                duration += samples as f64 * 1000.0 / header.codec.granule_rate() as f64;
+                self.granule_position = page.granule_position;
+            }
+            data.extend_from_slice(&page.data);
+        }
+
+        if data.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(AudioChunk {
+            data: data.freeze(),
+            duration,
+            header: self.header.as_ref().map(|h| h.pages.clone()),
+        }))
+    }
+}
+
+/// Read the duration, the title and the artist of an Ogg Vorbis or Ogg Opus file,
+///     the duration is the granule position of the last page.
+pub async fn read_ogg_info(
+    reader: impl AsyncRead + AsyncSeek + Unpin,
+) -> std::io::Result<Option<AudioInfo>> {
+    let mut reader = OggPageReader::new(reader);
+    let Some(first) = reader.next_page().await? else {
+        return Ok(None);
+    };
+    if first.header_type & BOS_FLAG == 0 || reader.read_header(first).await.is_err() {
+        return Ok(None);
+    }
+    let Some(header) = reader.header.take() else {
+        return Ok(None);
+    };
+
+    // search the last page with a granule position at the end of the file
+    let mut file = reader.reader;
+    let size = file.seek(std::io::SeekFrom::End(0)).await?;
+    file.seek(std::io::SeekFrom::Start(
+        size.saturating_sub(LAST_PAGE_SEARCH_SIZE),
+    ))
+    .await?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).await?;
+
+    let mut last_granule = None;
+    let mut at = 0;
+    while let Some(i) = tail[at..]
+        .windows(OGG_MAGIC.len())
+        .position(|w| w == OGG_MAGIC)
+    {
+        at += i;
+        if let PageLen::Page(len) = Page::len(&tail[at..])
+            && tail.len() >= at + len
+        {
+            let granule = i64::from_le_bytes(tail[at + 6..at + 14].try_into().unwrap());
+            if granule != NO_GRANULE_POSITION {
+                last_granule = Some(granule);
+            }
+        }
+        at += 1;
+    }
+
+    let Some(last_granule) = last_granule else {
+        return Ok(None);
+    };
+    let samples = (last_granule - header.codec.start_granule()).max(0);
+    let comment = header.comment.unwrap_or_default();
+    Ok(Some(AudioInfo {
+        duration: samples as f64 * 1000.0 / header.codec.granule_rate() as f64,
+        title: comment.title,
+        artist: comment.artist,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::vorbis_comment::build_vorbis_comment;
+    use super::*;
+
+    /// build a page containing `packets`, each packet must fit in the page
+    fn page(header_type: u8, granule_position: i64, packets: &[&[u8]]) -> Vec<u8> {
+        let mut segments = Vec::new();
+        let mut body = Vec::new();
+        for packet in packets {
+            let mut len = packet.len();
+            while len >= 255 {
+                segments.push(255);
+                len -= 255;
+            }
+            segments.push(len as u8);
+            body.extend_from_slice(packet);
+        }
+
+        let mut page = OGG_MAGIC.to_vec();
+        page.push(0);
+        page.push(header_type);
+        page.extend(granule_position.to_le_bytes());
+        // serial number, sequence number and checksum
+        page.extend([0; 12]);
+        page.push(segments.len() as u8);
+        page.extend(segments);
+        page.extend(body);
+        page
+    }
+
+    fn opus_file(title: &str, pages: i64) -> Vec<u8> {
+        let mut head = b"OpusHead\x01\x02".to_vec();
+        // pre skip of 312 samples
+        head.extend(312u16.to_le_bytes());
+        head.extend(48000u32.to_le_bytes());
+        head.extend([0, 0, 0]);
+        let mut tags = b"OpusTags".to_vec();
+        tags.extend(build_vorbis_comment(&[
+            &format!("TITLE={title}"),
+            "ARTIST=Artist",
+        ]));
+
+        let mut file = page(BOS_FLAG, 0, &[&head]);
+        file.extend(page(0, 0, &[&tags]));
+        // each page has 0.5 second of audio
+        for i in 1..=pages {
+            file.extend(page(0, 312 + i * 24000, &[&[0xAA; 100]]));
+        }
+        file
+    }
+
+    #[tokio::test]
+    async fn test_read_ogg_info() {
+        let file = opus_file("Song", 10);
+        let info = read_ogg_info(std::io::Cursor::new(file))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!((info.duration - 5000.0).abs() < 0.001);
+        assert_eq!(info.title.as_deref(), Some("Song"));
+        assert_eq!(info.artist.as_deref(), Some("Artist"));
+    }
+
+    #[tokio::test]
+    async fn test_read_chained_chunks() {
+        let first = opus_file("First", 4);
+        let second = opus_file("Second", 2);
+        let audio_page_len = page(0, 0, &[&[0xAA; 100]]).len();
+        let first_header_len = first.len() - 4 * audio_page_len;
+        let second_header_len = second.len() - 2 * audio_page_len;
+        let mut file = first.clone();
+        file.extend(&second);
+
+        let mut reader = OggPageReader::new(std::io::Cursor::new(file));
+        let mut chunks = Vec::new();
+        while let Some(chunk) = reader.next_chunk(1000.0).await.unwrap() {
+            chunks.push(chunk);
+        }
+
+        let durations = chunks.iter().map(|c| c.duration).collect::<Vec<_>>();
+        assert_eq!(durations, vec![1000.0, 1000.0, 1000.0]);
+        assert_eq!(
+            chunks[0].header.as_deref(),
+            Some(&first[..first_header_len])
+        );
+        assert_eq!(chunks[1].header, chunks[0].header);
+        assert_eq!(
+            chunks[2].header.as_deref(),
+            Some(&second[..second_header_len])
+        );
+        // the header pages are not part of the chunks
+        assert!(
+            chunks
+                .iter()
+                .all(|c| !c.data.windows(8).any(|w| w == b"OpusHead"))
+        );
+    }
+}
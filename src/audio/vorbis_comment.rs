@@ -0,0 +1,68 @@
+/// The fields of a Vorbis comment used by the playlists,
+///     the Vorbis comment is the tag format of FLAC, Vorbis and Opus.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VorbisComment {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+}
+
+impl VorbisComment {
+    /// Parse a Vorbis comment, without the packet type prefix of Vorbis and Opus.
+    /// Returns None if the comment is malformed.
+    pub fn parse(b: &[u8]) -> Option<Self> {
+        let read_u32 = |at: usize| -> Option<usize> {
+            let v = b.get(at..at + 4)?;
+            Some(u32::from_le_bytes([v[0], v[1], v[2], v[3]]) as usize)
+        };
+        let vendor_len = read_u32(0)?;
+        let count = read_u32(4 + vendor_len)?;
+        let mut at = 8 + vendor_len;
+
+        let mut comment = Self::default();
+        for _ in 0..count {
+            let len = read_u32(at)?;
+            at += 4;
+            let field = b.get(at..at + len)?;
+            at += len;
+
+            let Some((key, value)) = std::str::from_utf8(field).ok()?.split_once('=') else {
+                continue;
+            };
+            if key.eq_ignore_ascii_case("TITLE") && comment.title.is_none() {
+                comment.title = Some(value.to_string());
+            } else if key.eq_ignore_ascii_case("ARTIST") && comment.artist.is_none() {
+                comment.artist = Some(value.to_string());
+            }
+        }
+
+        Some(comment)
+    }
+}
+
+#[cfg(test)]
+pub(super) fn build_vorbis_comment(fields: &[&str]) -> Vec<u8> {
+    let mut b = Vec::new();
+    b.extend(6u32.to_le_bytes());
+    b.extend(b"vendor");
+    b.extend((fields.len() as u32).to_le_bytes());
+    for field in fields {
+        b.extend((field.len() as u32).to_le_bytes());
+        b.extend(field.as_bytes());
+    }
+    b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let b = build_vorbis_comment(&["title=Song", "ARTIST=Artist", "ALBUM=Album"]);
+        let comment = VorbisComment::parse(&b).unwrap();
+        assert_eq!(comment.title.as_deref(), Some("Song"));
+        assert_eq!(comment.artist.as_deref(), Some("Artist"));
+
+        assert!(VorbisComment::parse(&b[..b.len() - 1]).is_none());
+    }
+}
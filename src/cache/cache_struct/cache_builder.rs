@@ -2,10 +2,12 @@ use std::default::Default;
 use std::env;
 use std::path::PathBuf;
 
+use tokio::sync::Semaphore;
+
 use super::super::{FileDownloader, LocalDownloader};
 use super::Error;
 
-use super::Cache;
+use super::{Cache, PREFETCH_CONCURRENCY};
 
 /// Builder to facilitate creating [`Cache`] objects.
 pub struct CacheBuilder {
@@ -46,19 +48,24 @@ impl CacheBuilder {
 
     /// Build the `Cache` object.
     pub async fn build(self) -> Result<Cache, Error> {
-        let dir = self
-            .config
-            .dir
-            .unwrap_or_else(|| env::temp_dir().join("cache/"));
+        let dir = self.config.dir.unwrap_or_else(default_dir);
         tokio::fs::create_dir_all(&dir).await?;
         Ok(Cache {
             dir,
             freshness_lifetime: self.config.freshness_lifetime,
             file_downloader: self.config.file_downloader,
+            metrics: Default::default(),
+            downloads: Default::default(),
+            prefetches: Semaphore::new(PREFETCH_CONCURRENCY),
         })
     }
 }
 
+/// Get the default cache location, the `cache` subdirectory of the system temp directory.
+pub fn default_dir() -> PathBuf {
+    env::temp_dir().join("cache/")
+}
+
 impl Default for CacheBuilder {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,96 @@
+use async_stream::stream;
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::{mpsc, watch};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::io::StreamReader;
+
+use super::super::Error;
+
+/// size of the blocks read from a file being downloaded
+const READ_BLOCK_SIZE: usize = 65536;
+
+/// number of blocks buffered between a remote stream and its reader
+const STREAM_BUFFER_SIZE: usize = 4;
+
+/// The progress of a download, followed by the readers of the file being written.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Progress {
+    /// the bytes written to the file
+    pub written: u64,
+    /// set when the download ended, with the error message if it failed
+    pub end: Option<Result<(), String>>,
+}
+
+/// Read the file being downloaded, the bytes are served as soon as they are written.
+///
+/// `file` is opened before the download starts, it can be read after it is renamed.
+pub(crate) fn follow_download(
+    file: tokio::fs::File,
+    mut progress: watch::Receiver<Progress>,
+) -> impl AsyncRead + Send + Sync + Unpin {
+    let s = stream! {
+        let mut file = file;
+        let mut pos = 0;
+        let mut buf = vec![0; READ_BLOCK_SIZE];
+        loop {
+            let (written, end) = {
+                let progress = progress.borrow_and_update();
+                (progress.written, progress.end.clone())
+            };
+            if pos < written {
+                let len = (written - pos).min(buf.len() as u64) as usize;
+                match file.read(&mut buf[..len]).await {
+                    Ok(0) => {
+                        yield Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof));
+                        break;
+                    }
+                    Ok(read) => {
+                        pos += read as u64;
+                        yield Ok(Bytes::copy_from_slice(&buf[..read]));
+                    }
+                    Err(e) => {
+                        yield Err(e);
+                        break;
+                    }
+                }
+                continue;
+            }
+            match end {
+                Some(Ok(())) => break,
+                Some(Err(e)) => {
+                    yield Err(std::io::Error::other(e));
+                    break;
+                }
+                None => {
+                    if progress.changed().await.is_err() {
+                        yield Err(std::io::Error::other("download interrupted"));
+                        break;
+                    }
+                }
+            }
+        }
+    };
+    StreamReader::new(Box::pin(s))
+}
+
+/// Read a remote stream without caching it.
+///
+/// The stream is forwarded by a task, dropped with the reader.
+pub(crate) fn read_stream(
+    stream: impl Stream<Item = Result<Bytes, Error>> + Send + 'static,
+) -> impl AsyncRead + Send + Sync + Unpin {
+    let (sender, receiver) = mpsc::channel(STREAM_BUFFER_SIZE);
+    tokio::spawn(async move {
+        let mut stream = Box::pin(stream);
+        while let Some(b) = stream.next().await {
+            let b = b.map_err(std::io::Error::other);
+            let failed = b.is_err();
+            if sender.send(b).await.is_err() || failed {
+                break;
+            }
+        }
+    });
+    StreamReader::new(ReceiverStream::new(receiver))
+}
@@ -1,18 +1,76 @@
+use bytes::Bytes;
 use futures::StreamExt;
 use glob::glob;
-use log::{debug, info};
+use log::{debug, info, warn};
 use sha2::Digest;
+use std::collections::HashMap;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use tempfile::NamedTempFile;
 use tokio::fs::OpenOptions;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncRead, AsyncWriteExt};
+use tokio::sync::{Semaphore, watch};
 
-use super::utils::hash_str;
-use super::{Error, meta::Meta};
+use super::utils::{hash_str, read_file_range, temp_file_in};
+use super::{Error, lock::ResourceLock, meta::Meta};
 use super::{FileDownloader, FileMetadata};
+use crate::{CONTEXT, metrics::CacheMetrics};
 
 mod cache_builder;
+mod download_reader;
 pub use cache_builder::*;
+use download_reader::{Progress, follow_download, read_stream};
+
+/// the number of resources a cache prefetches at the same time
+pub(crate) const PREFETCH_CONCURRENCY: usize = 2;
+
+/// A resource looked up in the cache.
+enum Lookup {
+    /// an up-to-date version is cached
+    Cached(Meta),
+    /// the resource has to be downloaded to `path`
+    Missing {
+        file_meta: FileMetadata,
+        path: PathBuf,
+        /// the cached versions, superseded once the download completes
+        versions: Vec<Meta>,
+    },
+}
+
+/// A download of this process in flight.
+#[derive(Clone)]
+struct Download {
+    /// the temporary file written
+    temp_path: PathBuf,
+    /// the path of the resource, the temporary file is renamed to it once complete
+    path: PathBuf,
+    progress: watch::Receiver<Progress>,
+}
+
+impl Download {
+    /// Read the resource, served as soon as it is written.
+    async fn open(&self) -> std::io::Result<impl AsyncRead + Send + Sync + Unpin + use<>> {
+        let file = match tokio::fs::File::open(&self.temp_path).await {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                tokio::fs::File::open(&self.path).await?
+            }
+            res => res?,
+        };
+        Ok(follow_download(file, self.progress.clone()))
+    }
+}
+
+/// Where to read a resource missing from the cache.
+enum Source {
+    Download(Download),
+    /// the resource was cached meanwhile
+    Cached,
+    /// another process downloads the resource
+    Remote,
+}
 
 /// Fetches and manages resources in a local cache directory.
 pub struct Cache {
@@ -26,6 +84,12 @@ pub struct Cache {
     freshness_lifetime: Option<u64>,
     /// downloader for files
     file_downloader: Box<dyn FileDownloader>,
+    /// hits, misses and downloads of the cache
+    metrics: Arc<CacheMetrics>,
+    /// the downloads of this process in flight, by path of their lock file
+    downloads: Mutex<HashMap<PathBuf, Download>>,
+    /// limits the resources prefetched at the same time
+    prefetches: Semaphore,
 }
 
 impl Cache {
@@ -59,7 +123,199 @@ impl Cache {
         Ok(cached_path)
     }
 
-    async fn fetch_remote_resource(&self, resource: &str) -> Result<Meta, Error> {
+    /// Open a resource, served while it is downloaded if no up-to-date version is cached.
+    ///
+    /// The download goes on when the reader is dropped, the resource is cached once complete.
+    /// The readers opening the resource meanwhile follow the same download,
+    ///     while another process downloads it, it is read from the remote without being cached.
+    pub async fn open(
+        self: &Arc<Self>,
+        resource: &str,
+    ) -> Result<Box<dyn AsyncRead + Send + Sync + Unpin>, Error> {
+        if self.file_downloader.is_local() {
+            let path = self.cached_path(resource).await?;
+            return Ok(Box::new(tokio::fs::File::open(path).await?));
+        }
+
+        let (file_meta, path, versions) = match self.lookup(resource).await? {
+            Lookup::Cached(meta) => {
+                return Ok(Box::new(tokio::fs::File::open(meta.resource_path).await?));
+            }
+            Lookup::Missing {
+                file_meta,
+                path,
+                versions,
+            } => (file_meta, path, versions),
+        };
+
+        match self.start_download(resource, file_meta.clone(), path.clone(), versions.clone())? {
+            Source::Download(download) => match download.open().await {
+                Ok(reader) => return Ok(Box::new(reader)),
+                // the download failed before it could be followed
+                Err(e) => debug!("failed to follow the download of {resource}: {e}"),
+            },
+            Source::Cached => {
+                info!("{} was downloaded while taking its lock", resource);
+                let meta = self.use_cached(&path, &versions).await?;
+                return Ok(Box::new(tokio::fs::File::open(meta.resource_path).await?));
+            }
+            Source::Remote => {
+                info!(
+                    "{resource} is being downloaded by another process, reading it without caching"
+                );
+            }
+        }
+        let stream = self.file_downloader.get_file(&file_meta.location).await?;
+        Ok(Box::new(read_stream(stream)))
+    }
+
+    /// Download a resource in the background if no up-to-date version is cached.
+    ///
+    /// At most [`PREFETCH_CONCURRENCY`] resources are prefetched at the same time,
+    ///     the others wait for their turn.
+    pub fn prefetch(self: &Arc<Self>, resource: &str) {
+        if self.file_downloader.is_local() {
+            return;
+        }
+        let cache = self.clone();
+        let resource = resource.to_string();
+        tokio::spawn(async move {
+            let _permit = tokio::select! {
+                permit = cache.prefetches.acquire() => permit.unwrap(),
+                _ = CONTEXT.shutdown.cancelled() => return,
+            };
+            let res = match cache.lookup(&resource).await {
+                Ok(Lookup::Missing {
+                    file_meta,
+                    path,
+                    versions,
+                }) => cache.start_download(&resource, file_meta, path, versions),
+                Ok(Lookup::Cached(_)) => return,
+                Err(e) => Err(e),
+            };
+            match res {
+                Ok(Source::Download(mut download)) => {
+                    debug!("prefetching {resource}");
+                    // the permit is held until the download ends
+                    let _ = download.progress.wait_for(|p| p.end.is_some()).await;
+                }
+                Ok(_) => {}
+                Err(e) => warn!("failed to prefetch {resource}: {e}"),
+            }
+        });
+    }
+
+    /// Start the download of a resource missing from the cache,
+    ///     unless this process or another one is downloading it.
+    fn start_download(
+        self: &Arc<Self>,
+        resource: &str,
+        file_meta: FileMetadata,
+        path: PathBuf,
+        versions: Vec<Meta>,
+    ) -> Result<Source, Error> {
+        let lock_path = self.resource_to_filepath(&file_meta.location, Some(".lock"), None);
+        // the registry is only locked to look up and insert the downloads,
+        //     the lock file and the temporary file are created without it
+        let following = || {
+            let downloads = self.downloads.lock().unwrap();
+            downloads.get(&lock_path).cloned().map(Source::Download)
+        };
+        if let Some(source) = following() {
+            return Ok(source);
+        }
+        let Some(lock) = ResourceLock::try_acquire(lock_path.clone())? else {
+            // the download may have been started by this process since the lookup
+            return Ok(following().unwrap_or(Source::Remote));
+        };
+        if path.exists() {
+            return Ok(Source::Cached);
+        }
+
+        self.metrics.misses.fetch_add(1, Ordering::Relaxed);
+        let tempfile = temp_file_in(&self.dir)?;
+        let (progress, receiver) = watch::channel(Progress::default());
+        let download = Download {
+            temp_path: tempfile.path().to_path_buf(),
+            path: path.clone(),
+            progress: receiver,
+        };
+        // the downloads are only registered by the holder of the lock file
+        self.downloads
+            .lock()
+            .unwrap()
+            .insert(lock_path.clone(), download.clone());
+
+        let cache = self.clone();
+        let resource = resource.to_string();
+        // the shutdown waits for the downloads in flight
+        CONTEXT.tasks.spawn(async move {
+            let start = Instant::now();
+            let res = cache
+                .download_resource(&path, &file_meta, tempfile, Some(&progress))
+                .await;
+            match &res {
+                Ok(_) => {
+                    cache.metrics.download_seconds.observe(start.elapsed());
+                    info!("New version of {} cached", resource);
+                    remove_superseded(&versions, &path).await;
+                }
+                Err(e) => warn!("failed to download {resource}: {e}"),
+            }
+            let end = res.map(|_| ()).map_err(|e| e.to_string());
+            progress.send_modify(|progress| progress.end = Some(end));
+            cache.downloads.lock().unwrap().remove(&lock_path);
+            drop(lock);
+        });
+
+        Ok(Source::Download(download))
+    }
+
+    /// Read the bytes `range` of a resource, the range must be in the resource.
+    ///
+    /// A fresh cached version is read if there is one,
+    ///     otherwise only the range is fetched from the remote,
+    ///     or read from the latest cached version if the remote fails.
+    pub async fn read_range(&self, resource: &str, range: Range<u64>) -> Result<Bytes, Error> {
+        let cached = if self.file_downloader.is_local() {
+            Some(PathBuf::from(resource))
+        } else {
+            self.find_existing(resource)
+                .await
+                .into_iter()
+                .next()
+                .filter(|meta| meta.is_fresh(self.freshness_lifetime))
+                .map(|meta| meta.resource_path)
+        };
+        let path = match cached {
+            Some(path) => path,
+            None => match self
+                .file_downloader
+                .get_range(resource, range.clone())
+                .await
+            {
+                Err(e) if e.is_remote_failure() => {
+                    let Some(meta) = self.find_existing(resource).await.into_iter().next() else {
+                        return Err(e);
+                    };
+                    warn!(
+                        "failed to read {resource} from the remote, reading its cached version: {e}"
+                    );
+                    meta.resource_path
+                }
+                res => return res,
+            },
+        };
+        match read_file_range(&path, range).await {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err(Error::ResourceNotFound(resource.to_string()))
+            }
+            res => Ok(res?),
+        }
+    }
+
+    /// Look up the up-to-date version of a resource in the cache.
+    async fn lookup(&self, resource: &str) -> Result<Lookup, Error> {
         // Ensure root directory exists in case it has changed or been removed.
         tokio::fs::create_dir_all(&self.dir).await?;
 
@@ -69,7 +325,11 @@ impl Cache {
         if !versions.is_empty() && versions[0].is_fresh(self.freshness_lifetime) {
             // Oh hey, the latest version is still fresh!
             info!("Latest cached version of {} is still fresh", resource);
-            return Ok(versions[0].clone());
+            self.metrics.hits.fetch_add(1, Ordering::Relaxed);
+            let mut meta = versions[0].clone();
+            meta.touch().await?;
+            remove_superseded(&versions, &meta.resource_path).await;
+            return Ok(Lookup::Cached(meta));
         }
 
         // No existing version or the existing versions are older than their freshness
@@ -79,23 +339,63 @@ impl Cache {
         let path = self.resource_to_filepath(&file_meta.location, None, file_meta.e_tag.as_deref());
         debug!("Resource path: {:?}", path);
 
-        // TODO do we need to lock the file here?
-        // Before going further we need to obtain a lock on the file to provide
-        // parallel downloads of the same resource.
-
         if path.exists() {
             // Oh cool! The cache is up-to-date according to the ETAG.
             // We'll return the up-to-date version and clean up any other
             // dangling ones.
             info!("Cached version of {} is up-to-date", resource);
-            return Meta::from_cache(&path).await;
+            return Ok(Lookup::Cached(self.use_cached(&path, &versions).await?));
+        }
+
+        Ok(Lookup::Missing {
+            file_meta,
+            path,
+            versions,
+        })
+    }
+
+    async fn fetch_remote_resource(&self, resource: &str) -> Result<Meta, Error> {
+        let (file_meta, path, versions) = match self.lookup(resource).await? {
+            Lookup::Cached(meta) => return Ok(meta),
+            Lookup::Missing {
+                file_meta,
+                path,
+                versions,
+            } => (file_meta, path, versions),
+        };
+
+        // Only one download of a resource at a time, the other tasks of this process
+        // and the other processes sharing the cache directory wait for it.
+        let lock_path = self.resource_to_filepath(&file_meta.location, Some(".lock"), None);
+        let _lock = ResourceLock::acquire(lock_path).await?;
+        if path.exists() {
+            info!("{} was downloaded while waiting for its lock", resource);
+            return self.use_cached(&path, &versions).await;
         }
 
         // No up-to-date version cached, so we have to try downloading it.
-        let meta = self.download_resource(&path, &file_meta).await?;
+        self.metrics.misses.fetch_add(1, Ordering::Relaxed);
+        let start = Instant::now();
+        let tempfile = temp_file_in(&self.dir)?;
+        // the shutdown waits for the downloads in flight
+        let meta = CONTEXT
+            .tasks
+            .track_future(self.download_resource(&path, &file_meta, tempfile, None))
+            .await?;
+        self.metrics.download_seconds.observe(start.elapsed());
 
         info!("New version of {} cached", resource);
+        remove_superseded(&versions, &path).await;
+
+        Ok(meta)
+    }
 
+    /// Use the cached version `path` of a resource, the other versions are removed.
+    async fn use_cached(&self, path: &Path, versions: &[Meta]) -> Result<Meta, Error> {
+        self.metrics.hits.fetch_add(1, Ordering::Relaxed);
+        let mut meta = Meta::from_cache(path).await?;
+        meta.touch().await?;
+        remove_superseded(versions, path).await;
         Ok(meta)
     }
 
@@ -118,25 +418,47 @@ impl Cache {
         existing_meta
     }
 
+    /// Download a resource to `tempfile`, renamed to `path` once complete.
+    ///
+    /// The bytes written to `tempfile` are reported to `progress` if set.
     async fn download_resource(
         &self,
         path: &Path,
         file_meta: &FileMetadata,
+        tempfile: NamedTempFile,
+        progress: Option<&watch::Sender<Progress>>,
     ) -> Result<Meta, Error> {
         let mut response = self.file_downloader.get_file(&file_meta.location).await?;
 
         // First we make a temporary file and download the contents of the resource into it.
         // Otherwise if we wrote directly to the cache file and the download got
         // interrupted we could be left with a corrupted cache file.
-        let tempfile = NamedTempFile::new_in(path.parent().unwrap())?;
         let mut tempfile_write_handle =
             OpenOptions::new().write(true).open(tempfile.path()).await?;
 
         info!("Starting download of {}", file_meta.location);
 
-        while let Some(b) = response.next().await {
-            let b = b?;
+        loop {
+            let b = tokio::select! {
+                b = response.next() => match b {
+                    Some(b) => b?,
+                    None => break,
+                },
+                // the temporary file is removed when dropped
+                _ = CONTEXT.abort.cancelled() => {
+                    info!("Download of {} aborted", file_meta.location);
+                    return Err(Error::Aborted);
+                }
+            };
             tempfile_write_handle.write_all(&b).await?;
+            if let Some(progress) = progress {
+                // the readers of the temporary file only read what was flushed
+                tempfile_write_handle.flush().await?;
+                progress.send_modify(|progress| progress.written += b.len() as u64);
+            }
+            self.metrics
+                .download_bytes
+                .fetch_add(b.len() as u64, Ordering::Relaxed);
         }
 
         tempfile_write_handle.flush().await?;
@@ -155,8 +477,60 @@ impl Cache {
         Ok(meta)
     }
 
+    /// Get the metrics of the cache.
+    pub fn metrics(&self) -> Arc<CacheMetrics> {
+        self.metrics.clone()
+    }
+
+    /// Get the meta data of a resource from the remote.
+    ///
+    /// If the remote fails, the meta data of the latest cached version is used,
+    ///     so the cached version is served until the remote is back.
     pub async fn get_file_meta(&self, resource: &str) -> Result<FileMetadata, Error> {
-        self.file_downloader.get_meta(resource).await
+        match self.file_downloader.get_meta(resource).await {
+            Err(e) if e.is_remote_failure() => {
+                let Some(meta) = self.find_existing(resource).await.into_iter().next() else {
+                    return Err(e);
+                };
+                warn!("failed to reach the remote of {resource}, using its cached version: {e}");
+                Ok(meta.meta_data)
+            }
+            res => res,
+        }
+    }
+
+    /// Get the files of the last complete listing of a folder saved by [`Cache::save_listing`],
+    ///     None if it was never saved.
+    pub async fn listing(&self, folder: &str, recursive: bool) -> Option<Vec<String>> {
+        let listing = tokio::fs::read(self.listing_path(folder, recursive))
+            .await
+            .ok()?;
+        serde_json::from_slice(&listing).ok()
+    }
+
+    /// Save the complete listing of a folder, replacing the previous one atomically.
+    pub async fn save_listing(
+        &self,
+        folder: &str,
+        recursive: bool,
+        files: &[String],
+    ) -> Result<(), Error> {
+        let serialized = serde_json::to_string(files).unwrap();
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let tempfile = temp_file_in(&self.dir)?;
+        tokio::fs::write(tempfile.path(), &serialized[..]).await?;
+        tokio::fs::rename(tempfile.path(), self.listing_path(folder, recursive)).await?;
+        Ok(())
+    }
+
+    /// Get the path of the saved listing of a folder.
+    fn listing_path(&self, folder: &str, recursive: bool) -> PathBuf {
+        let suffix = if recursive {
+            ".listing-recursive"
+        } else {
+            ".listing"
+        };
+        self.resource_to_filepath(folder, Some(suffix), None)
     }
 
     fn resource_to_filepath(
@@ -185,3 +559,318 @@ impl Cache {
         self.dir.join(filepath)
     }
 }
+
+/// Remove the cached versions of a resource other than `latest`.
+async fn remove_superseded(versions: &[Meta], latest: &Path) {
+    for version in versions.iter().filter(|v| v.resource_path != latest) {
+        debug!("Removing superseded version {:?}", version.resource_path);
+        if let Err(e) = version.remove().await {
+            warn!(
+                "failed to remove superseded version {:?}: {e}",
+                version.resource_path
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use bytes::Bytes;
+    use futures::Stream;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicBool, AtomicUsize};
+    use std::time::Duration;
+    use tokio::io::AsyncReadExt;
+    use tokio::sync::Semaphore;
+
+    /// Counts the downloads, each one taking a while.
+    struct SlowDownloader {
+        downloads: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl FileDownloader for SlowDownloader {
+        async fn get_file(
+            &self,
+            _path: &str,
+        ) -> Result<Pin<Box<dyn Stream<Item = Result<Bytes, Error>> + Send>>, Error> {
+            self.downloads.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(Box::pin(futures::stream::iter([Ok(Bytes::from_static(
+                b"audio",
+            ))])))
+        }
+
+        async fn get_meta(&self, path: &str) -> Result<FileMetadata, Error> {
+            Ok(FileMetadata {
+                location: path.to_string(),
+                last_modified: Default::default(),
+                size: 5,
+                e_tag: Some("v1".to_string()),
+            })
+        }
+    }
+
+    /// Sends the first half of the file, the second half once `gate` is opened.
+    struct GatedDownloader {
+        data: Bytes,
+        gate: Arc<Semaphore>,
+        downloads: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl FileDownloader for GatedDownloader {
+        async fn get_file(
+            &self,
+            _path: &str,
+        ) -> Result<Pin<Box<dyn Stream<Item = Result<Bytes, Error>> + Send>>, Error> {
+            self.downloads.fetch_add(1, Ordering::SeqCst);
+            let (data, gate) = (self.data.clone(), self.gate.clone());
+            let half = data.len() / 2;
+            Ok(Box::pin(async_stream::stream! {
+                yield Ok(data.slice(..half));
+                let _permit = gate.acquire().await.unwrap();
+                yield Ok(data.slice(half..));
+            }))
+        }
+
+        async fn get_meta(&self, path: &str) -> Result<FileMetadata, Error> {
+            Ok(FileMetadata {
+                location: path.to_string(),
+                last_modified: Default::default(),
+                size: self.data.len(),
+                e_tag: Some("v1".to_string()),
+            })
+        }
+
+        async fn get_range(&self, _path: &str, range: Range<u64>) -> Result<Bytes, Error> {
+            Ok(self.data.slice(range.start as usize..range.end as usize))
+        }
+    }
+
+    /// Fails like an unreachable remote while `offline` is set.
+    struct FlakyDownloader {
+        offline: Arc<AtomicBool>,
+    }
+
+    impl FlakyDownloader {
+        fn check(&self) -> Result<(), Error> {
+            if self.offline.load(Ordering::SeqCst) {
+                return Err(std::io::Error::from(std::io::ErrorKind::ConnectionRefused).into());
+            }
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl FileDownloader for FlakyDownloader {
+        async fn get_file(
+            &self,
+            _path: &str,
+        ) -> Result<Pin<Box<dyn Stream<Item = Result<Bytes, Error>> + Send>>, Error> {
+            self.check()?;
+            Ok(Box::pin(futures::stream::iter([Ok(Bytes::from_static(
+                b"audio",
+            ))])))
+        }
+
+        async fn get_meta(&self, path: &str) -> Result<FileMetadata, Error> {
+            self.check()?;
+            Ok(FileMetadata {
+                location: path.to_string(),
+                last_modified: Default::default(),
+                size: 5,
+                e_tag: Some("v1".to_string()),
+            })
+        }
+
+        async fn get_range(&self, _path: &str, range: Range<u64>) -> Result<Bytes, Error> {
+            self.check()?;
+            Ok(Bytes::from_static(b"audio").slice(range.start as usize..range.end as usize))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stale_if_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let offline = Arc::new(AtomicBool::new(false));
+        let cache = Cache::builder()
+            .dir(dir.path().to_path_buf())
+            .file_downloader(Box::new(FlakyDownloader {
+                offline: offline.clone(),
+            }))
+            .build()
+            .await
+            .unwrap();
+        let cache = Arc::new(cache);
+        let cached = cache.cached_path("music/a.mp3").await.unwrap();
+
+        // the cached version is served while the remote is unreachable
+        offline.store(true, Ordering::SeqCst);
+        assert_eq!(cache.get_file_meta("music/a.mp3").await.unwrap().size, 5);
+        assert_eq!(cache.cached_path("music/a.mp3").await.unwrap(), cached);
+        let mut all = Vec::new();
+        let mut file = cache.open("music/a.mp3").await.unwrap();
+        file.read_to_end(&mut all).await.unwrap();
+        assert_eq!(all, b"audio");
+        assert_eq!(
+            cache.read_range("music/a.mp3", 1..3).await.unwrap(),
+            Bytes::from_static(b"ud")
+        );
+        assert!(cache.open("music/b.mp3").await.is_err());
+
+        // a listing is saved for each folder and recursion
+        assert!(cache.listing("music", false).await.is_none());
+        let files = vec!["music/a.mp3".to_string()];
+        cache.save_listing("music", false, &files).await.unwrap();
+        assert_eq!(cache.listing("music", false).await, Some(files));
+        assert!(cache.listing("music", true).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_open_while_downloading() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = Bytes::from((0..200_000u32).map(|i| i as u8).collect::<Vec<_>>());
+        let gate = Arc::new(Semaphore::new(0));
+        let downloads = Arc::new(AtomicUsize::new(0));
+        let cache = Cache::builder()
+            .dir(dir.path().to_path_buf())
+            .file_downloader(Box::new(GatedDownloader {
+                data: data.clone(),
+                gate: gate.clone(),
+                downloads: downloads.clone(),
+            }))
+            .build()
+            .await
+            .unwrap();
+        let cache = Arc::new(cache);
+
+        // the first half is read before the download completes
+        let mut file = cache.open("music/mix.mp3").await.unwrap();
+        let mut head = vec![0; data.len() / 2];
+        file.read_exact(&mut head).await.unwrap();
+        assert_eq!(head, data[..data.len() / 2]);
+        // the other readers follow the same download
+        let mut other = cache.open("music/mix.mp3").await.unwrap();
+        assert_eq!(
+            cache.read_range("music/mix.mp3", 10..20).await.unwrap(),
+            data.slice(10..20)
+        );
+
+        gate.add_permits(1);
+        let mut rest = Vec::new();
+        file.read_to_end(&mut rest).await.unwrap();
+        assert_eq!(rest, data[data.len() / 2..]);
+        let mut all = Vec::new();
+        other.read_to_end(&mut all).await.unwrap();
+        assert_eq!(all, data);
+        assert_eq!(downloads.load(Ordering::SeqCst), 1);
+
+        // cached once the download completes
+        let path = cache.cached_path("music/mix.mp3").await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), data);
+        assert_eq!(downloads.load(Ordering::SeqCst), 1);
+        assert_eq!(cache.metrics().hits.load(Ordering::Relaxed), 1);
+
+        // downloaded by another process, read from the remote
+        let lock_path = cache.resource_to_filepath("music/other.mp3", Some(".lock"), None);
+        let lock = std::fs::File::create(&lock_path).unwrap();
+        lock.lock().unwrap();
+        gate.add_permits(1);
+        let mut all = Vec::new();
+        let mut file = cache.open("music/other.mp3").await.unwrap();
+        file.read_to_end(&mut all).await.unwrap();
+        assert_eq!(all, data);
+        assert_eq!(downloads.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_prefetch() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = Bytes::from((0..200_000u32).map(|i| i as u8).collect::<Vec<_>>());
+        let gate = Arc::new(Semaphore::new(0));
+        let downloads = Arc::new(AtomicUsize::new(0));
+        let cache = Cache::builder()
+            .dir(dir.path().to_path_buf())
+            .file_downloader(Box::new(GatedDownloader {
+                data: data.clone(),
+                gate: gate.clone(),
+                downloads: downloads.clone(),
+            }))
+            .build()
+            .await
+            .unwrap();
+        let cache = Arc::new(cache);
+
+        // at most `PREFETCH_CONCURRENCY` downloads at a time
+        let resources: Vec<_> = (0..PREFETCH_CONCURRENCY + 1)
+            .map(|i| format!("music/{i}.mp3"))
+            .collect();
+        for resource in &resources {
+            cache.prefetch(resource);
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(downloads.load(Ordering::SeqCst), PREFETCH_CONCURRENCY);
+
+        // the reader follows the prefetch
+        let mut file = cache.open(&resources[0]).await.unwrap();
+        gate.add_permits(resources.len());
+        let mut all = Vec::new();
+        file.read_to_end(&mut all).await.unwrap();
+        assert_eq!(all, data);
+        for resource in &resources {
+            while cache.find_existing(resource).await.is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }
+        assert_eq!(downloads.load(Ordering::SeqCst), resources.len());
+
+        // nothing to prefetch once cached
+        cache.prefetch(&resources[0]);
+        let path = cache.cached_path(&resources[0]).await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), data);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(downloads.load(Ordering::SeqCst), resources.len());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_downloads() {
+        let dir = tempfile::tempdir().unwrap();
+        let downloads = Arc::new(AtomicUsize::new(0));
+        // two caches sharing the directory, as two file providers would
+        let mut caches = Vec::new();
+        for _ in 0..2 {
+            let cache = Cache::builder()
+                .dir(dir.path().to_path_buf())
+                .file_downloader(Box::new(SlowDownloader {
+                    downloads: downloads.clone(),
+                }))
+                .build()
+                .await
+                .unwrap();
+            caches.push(Arc::new(cache));
+        }
+
+        let tasks: Vec<_> = (0..6)
+            .map(|i| {
+                let cache = caches[i % 2].clone();
+                tokio::spawn(async move { cache.cached_path("music/a.mp3").await.unwrap() })
+            })
+            .collect();
+        let mut paths = Vec::new();
+        for task in tasks {
+            paths.push(task.await.unwrap());
+        }
+
+        assert_eq!(downloads.load(Ordering::SeqCst), 1);
+        assert!(paths.iter().all(|path| *path == paths[0]));
+        assert_eq!(std::fs::read(&paths[0]).unwrap(), b"audio");
+        let hits: u64 = caches
+            .iter()
+            .map(|cache| cache.metrics().hits.load(Ordering::Relaxed))
+            .sum();
+        assert_eq!(hits, 5);
+    }
+}
@@ -27,11 +27,34 @@ pub enum Error {
     #[error("Failed to parse object storage path")]
     ObjectStoragePath(#[from] object_store::path::Error),
 
+    /// An HTTP error that could occur while attempting to fetch a remote resource.
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    /// Failed to parse the URL of a remote resource.
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(#[from] url::ParseError),
+
+    /// The response of a WebDAV server could not be parsed.
+    #[error("Invalid WebDAV response: {0}")]
+    InvalidWebDavResponse(String),
+
+    /// Arises when a download is aborted by the shutdown of the server.
+    #[error("Download aborted by the shutdown")]
+    Aborted,
+
     /// A method that should never be called was called.
     #[error("Method not implemented")]
     NotImplemented,
 }
 
-// TODO An HTTP error that could occur while attempting to fetch a remote resource.
-// #[error(transparent)]
-// HttpError(#[from] reqwest::Error),
+impl Error {
+    /// Whether the remote failed or could not be reached,
+    ///     as opposed to a missing resource or an aborted download.
+    pub fn is_remote_failure(&self) -> bool {
+        !matches!(
+            self,
+            Error::ResourceNotFound(_) | Error::Aborted | Error::NotImplemented
+        )
+    }
+}
@@ -0,0 +1,230 @@
+use std::ops::Range;
+
+use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use futures::{Stream, StreamExt};
+use log::debug;
+use percent_encoding::{AsciiSet, CONTROLS, percent_decode_str, utf8_percent_encode};
+use reqwest::{
+    Method, StatusCode,
+    header::{self, HeaderMap},
+};
+use sha2::Digest;
+use url::Url;
+
+use super::super::{Error, FileDownloader, FileMetadata};
+
+/// the characters escaped in a segment of the path of a file
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// Parse the URL of a folder, the paths of its files are joined to it.
+pub fn folder_url(url: &str) -> Result<Url, url::ParseError> {
+    if url.ends_with('/') {
+        Url::parse(url)
+    } else {
+        Url::parse(&format!("{url}/"))
+    }
+}
+
+/// Get the URL of a file from its path relative to the folder `base`.
+pub fn file_url(base: &Url, path: &str) -> Result<Url, url::ParseError> {
+    let path = path
+        .trim_start_matches('/')
+        .split('/')
+        .map(|segment| utf8_percent_encode(segment, PATH_SEGMENT).to_string())
+        .collect::<Vec<_>>()
+        .join("/");
+    base.join(&path)
+}
+
+/// Get the path of `url` relative to the folder `base`, None if `url` is not in `base`.
+pub fn relative_path(base: &Url, url: &Url) -> Option<String> {
+    let mut url = url.clone();
+    url.set_query(None);
+    url.set_fragment(None);
+    let path = url.as_str().strip_prefix(base.as_str())?;
+    if path.is_empty() {
+        return None;
+    }
+    Some(percent_decode_str(path).decode_utf8_lossy().into_owned())
+}
+
+/// Get the value of the `Range` header asking for the bytes `range`, which must not be empty.
+pub(crate) fn range_header(range: &Range<u64>) -> String {
+    format!("bytes={}-{}", range.start, range.end - 1)
+}
+
+/// Read the body of the response to a request of the bytes `range`,
+///     the servers ignoring the `Range` header send the whole file.
+pub(crate) async fn range_body(
+    response: reqwest::Response,
+    range: &Range<u64>,
+) -> Result<Bytes, Error> {
+    if response.status() == StatusCode::PARTIAL_CONTENT {
+        return Ok(response.bytes().await?);
+    }
+    let mut body = BytesMut::new();
+    let mut offset = 0;
+    let mut stream = response.bytes_stream();
+    while offset < range.end
+        && let Some(b) = stream.next().await
+    {
+        let b = b?;
+        let len = b.len() as u64;
+        let start = range.start.saturating_sub(offset).min(len) as usize;
+        let end = range.end.saturating_sub(offset).min(len) as usize;
+        body.extend_from_slice(&b[start..end]);
+        offset += len;
+    }
+    Ok(body.freeze())
+}
+
+/// Downloads the files relative to a base URL.
+pub struct HttpDownloader {
+    client: reqwest::Client,
+    base: Url,
+    hash: Bytes,
+}
+
+impl HttpDownloader {
+    /// `base` is the URL of the folder holding the files,
+    ///     `headers` are sent with every request.
+    pub fn new(base: Url, headers: HeaderMap) -> Result<Self, Error> {
+        let mut hasher = sha2::Sha256::new();
+        hasher.update("http:".as_bytes());
+        hasher.update(base.as_str().as_bytes());
+        for (name, value) in &headers {
+            hasher.update(name.as_str().as_bytes());
+            hasher.update(":".as_bytes());
+            hasher.update(value.as_bytes());
+            hasher.update(",".as_bytes());
+        }
+        let hash = hasher.finalize();
+        let hash = Bytes::copy_from_slice(hash.as_slice());
+
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .build()?;
+        Ok(Self { client, base, hash })
+    }
+
+    pub fn get_client(&self) -> reqwest::Client {
+        self.client.clone()
+    }
+
+    /// Send a request for the file `path`, for the bytes `range` of the file if set.
+    async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        range: Option<&Range<u64>>,
+    ) -> Result<reqwest::Response, Error> {
+        let url = file_url(&self.base, path)?;
+        debug!("{method} {url}");
+        let mut request = self.client.request(method, url);
+        if let Some(range) = range {
+            request = request.header(header::RANGE, range_header(range));
+        }
+        let response = request.send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(Error::ResourceNotFound(path.to_string()));
+        }
+        Ok(response.error_for_status()?)
+    }
+}
+
+#[async_trait]
+impl FileDownloader for HttpDownloader {
+    async fn get_file(
+        &self,
+        path: &str,
+    ) -> Result<std::pin::Pin<Box<dyn Stream<Item = Result<bytes::Bytes, Error>> + Send>>, Error>
+    {
+        let response = self.request(Method::GET, path, None).await?;
+        Ok(Box::pin(
+            response.bytes_stream().map(|b| b.map_err(|e| e.into())),
+        ))
+    }
+
+    async fn get_meta(&self, path: &str) -> Result<FileMetadata, Error> {
+        debug!("get meta of {}", path);
+        let response = match self.request(Method::HEAD, path, None).await {
+            // some servers only answer GET, the body is not read
+            Err(Error::Http(e))
+                if e.status() == Some(StatusCode::METHOD_NOT_ALLOWED)
+                    || e.status() == Some(StatusCode::NOT_IMPLEMENTED) =>
+            {
+                self.request(Method::GET, path, None).await?
+            }
+            res => res?,
+        };
+        let headers = response.headers();
+        let header = |name| headers.get(name).and_then(|v| v.to_str().ok());
+
+        let last_modified = header(header::LAST_MODIFIED);
+        Ok(FileMetadata {
+            size: header(header::CONTENT_LENGTH)
+                .and_then(|v| v.parse().ok())
+                .unwrap_or_default(),
+            location: path.to_string(),
+            last_modified: last_modified
+                .and_then(|v| chrono::DateTime::parse_from_rfc2822(v).ok())
+                .map(|v| v.to_utc())
+                .unwrap_or_default(),
+            // the cached version of a file without ETag is checked by its modification time
+            e_tag: header(header::ETAG)
+                .or(last_modified)
+                .map(|v| v.to_string()),
+        })
+    }
+
+    async fn get_range(&self, path: &str, range: Range<u64>) -> Result<Bytes, Error> {
+        if range.is_empty() {
+            return Ok(Bytes::new());
+        }
+        let response = self.request(Method::GET, path, Some(&range)).await?;
+        range_body(response, &range).await
+    }
+
+    fn hash(&self) -> Bytes {
+        self.hash.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_url() {
+        let base = folder_url("http://host/music").unwrap();
+        assert_eq!(base.as_str(), "http://host/music/");
+        assert_eq!(
+            file_url(&base, "album/Song #1?.mp3").unwrap().as_str(),
+            "http://host/music/album/Song%20%231%3F.mp3"
+        );
+    }
+
+    #[test]
+    fn test_relative_path() {
+        let base = Url::parse("http://host/music/").unwrap();
+        let url = |u| Url::parse(u).unwrap();
+        assert_eq!(
+            relative_path(&base, &url("http://host/music/album/Song%20One.mp3?x=1")),
+            Some("album/Song One.mp3".to_string())
+        );
+        assert_eq!(relative_path(&base, &url("http://host/music/")), None);
+        assert_eq!(relative_path(&base, &url("http://host/other.mp3")), None);
+        assert_eq!(relative_path(&base, &url("http://other/music/a.mp3")), None);
+    }
+}
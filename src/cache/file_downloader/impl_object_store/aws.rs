@@ -8,6 +8,7 @@ use object_store::{
     aws::{AmazonS3, AmazonS3Builder, AmazonS3ConfigKey},
 };
 use sha2::Digest;
+use std::ops::Range;
 use std::sync::Arc;
 
 use super::super::{Error, FileDownloader, FileMetadata};
@@ -150,6 +151,17 @@ impl FileDownloader for AwsS3Downloader {
         }
     }
 
+    async fn get_range(&self, path: &str, range: Range<u64>) -> Result<Bytes, Error> {
+        let path = object_store::path::Path::parse(path)?;
+        match self.object_store.get_range(&path, range).await {
+            Ok(bytes) => Ok(bytes),
+            Err(object_store::Error::NotFound { .. }) => {
+                Err(Error::ResourceNotFound(path.to_string()))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
     fn hash(&self) -> Bytes {
         self.hash.clone()
     }
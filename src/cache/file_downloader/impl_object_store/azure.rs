@@ -0,0 +1,169 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use async_stream::stream;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use log::debug;
+use object_store::{
+    ObjectStore,
+    azure::{AzureConfigKey, MicrosoftAzure, MicrosoftAzureBuilder},
+};
+use sha2::Digest;
+
+use super::super::{Error, FileDownloader, FileMetadata};
+
+use super::{CLIENT_CONFIG_KEYS, object_store_config_key_to_string};
+
+static AZURE_CONFIG_KEYS: &[AzureConfigKey; 19] = &[
+    AzureConfigKey::AccountName,
+    AzureConfigKey::AccessKey,
+    AzureConfigKey::ContainerName,
+    AzureConfigKey::ClientId,
+    AzureConfigKey::ClientSecret,
+    AzureConfigKey::AuthorityId,
+    AzureConfigKey::AuthorityHost,
+    AzureConfigKey::SasKey,
+    AzureConfigKey::Token,
+    AzureConfigKey::UseEmulator,
+    AzureConfigKey::Endpoint,
+    AzureConfigKey::UseFabricEndpoint,
+    AzureConfigKey::MsiEndpoint,
+    AzureConfigKey::ObjectId,
+    AzureConfigKey::MsiResourceId,
+    AzureConfigKey::FederatedTokenFile,
+    AzureConfigKey::UseAzureCli,
+    AzureConfigKey::SkipSignature,
+    AzureConfigKey::DisableTagging,
+    // And a list of AzureConfigKey::Client(CLIENT_CONFIG_KEYS),
+];
+
+fn azure_config_key_to_string(key: &AzureConfigKey) -> &'static str {
+    match key {
+        AzureConfigKey::AccountName => "AccountName",
+        AzureConfigKey::AccessKey => "AccessKey",
+        AzureConfigKey::ContainerName => "ContainerName",
+        AzureConfigKey::ClientId => "ClientId",
+        AzureConfigKey::ClientSecret => "ClientSecret",
+        AzureConfigKey::AuthorityId => "AuthorityId",
+        AzureConfigKey::AuthorityHost => "AuthorityHost",
+        AzureConfigKey::SasKey => "SasKey",
+        AzureConfigKey::Token => "Token",
+        AzureConfigKey::UseEmulator => "UseEmulator",
+        AzureConfigKey::Endpoint => "Endpoint",
+        AzureConfigKey::UseFabricEndpoint => "UseFabricEndpoint",
+        AzureConfigKey::MsiEndpoint => "MsiEndpoint",
+        AzureConfigKey::ObjectId => "ObjectId",
+        AzureConfigKey::MsiResourceId => "MsiResourceId",
+        AzureConfigKey::FederatedTokenFile => "FederatedTokenFile",
+        AzureConfigKey::UseAzureCli => "UseAzureCli",
+        AzureConfigKey::SkipSignature => "SkipSignature",
+        AzureConfigKey::DisableTagging => "DisableTagging",
+        AzureConfigKey::Client(c) => object_store_config_key_to_string(c),
+        &_ => "Unknown",
+    }
+}
+
+pub struct AzureDownloader {
+    object_store: Arc<MicrosoftAzure>,
+    hash: Bytes,
+}
+
+impl AzureDownloader {
+    pub fn new(builder: MicrosoftAzureBuilder) -> Result<Self, Error> {
+        let mut hasher = sha2::Sha256::new();
+        hasher.update("azure:".as_bytes());
+        // iter over AZURE_CONFIG_KEYS and set the value from the builder
+        for key in AZURE_CONFIG_KEYS.iter() {
+            let v = builder.get_config_value(key);
+            if let Some(value) = v {
+                hasher.update(azure_config_key_to_string(key).as_bytes());
+                hasher.update(":".as_bytes());
+                hasher.update(value.as_bytes());
+                hasher.update(",".as_bytes());
+            }
+        }
+        for key in CLIENT_CONFIG_KEYS.iter() {
+            let v = builder.get_config_value(&AzureConfigKey::Client(*key));
+            if let Some(value) = v {
+                hasher.update(object_store_config_key_to_string(key).as_bytes());
+                hasher.update(":".as_bytes());
+                hasher.update(value.as_bytes());
+                hasher.update(",".as_bytes());
+            }
+        }
+
+        // TODO optimize this
+        let hash = hasher.finalize();
+        let hash = Bytes::copy_from_slice(hash.as_slice());
+        let object_store = builder.build()?;
+        Ok(Self {
+            object_store: Arc::new(object_store),
+            hash,
+        })
+    }
+
+    pub fn get_object_store(&self) -> Arc<MicrosoftAzure> {
+        self.object_store.clone()
+    }
+}
+
+#[async_trait]
+impl FileDownloader for AzureDownloader {
+    async fn get_file(
+        &self,
+        path: &str,
+    ) -> Result<std::pin::Pin<Box<dyn Stream<Item = Result<bytes::Bytes, Error>> + Send>>, Error>
+    {
+        let path = object_store::path::Path::parse(path)?;
+        match self.object_store.get(&path).await {
+            Ok(file) => {
+                let s = stream! {
+                    let mut stream = file.into_stream();
+                    while let Some(bytes) = stream.next().await {
+                        yield bytes.map_err(|e| e.into());
+                    }
+                };
+                Ok(Box::pin(s))
+            }
+            Err(object_store::Error::NotFound { .. }) => {
+                Err(Error::ResourceNotFound(path.to_string()))
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    async fn get_meta(&self, path: &str) -> Result<FileMetadata, Error> {
+        debug!("get meta of {}", path);
+        let path = object_store::path::Path::parse(path)?;
+        match self.object_store.head(&path).await {
+            Ok(meta) => Ok(FileMetadata {
+                size: meta.size as usize,
+                location: meta.location.to_string(),
+                last_modified: meta.last_modified,
+                e_tag: meta.e_tag,
+            }),
+            Err(object_store::Error::NotFound { .. }) => {
+                debug!("file not found: {}", path);
+                Err(Error::ResourceNotFound(path.to_string()))
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    async fn get_range(&self, path: &str, range: Range<u64>) -> Result<Bytes, Error> {
+        let path = object_store::path::Path::parse(path)?;
+        match self.object_store.get_range(&path, range).await {
+            Ok(bytes) => Ok(bytes),
+            Err(object_store::Error::NotFound { .. }) => {
+                Err(Error::ResourceNotFound(path.to_string()))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn hash(&self) -> Bytes {
+        self.hash.clone()
+    }
+}
@@ -1,3 +1,4 @@
+use std::ops::Range;
 use std::sync::Arc;
 
 use async_stream::stream;
@@ -121,6 +122,17 @@ impl FileDownloader for GcpDownloader {
         }
     }
 
+    async fn get_range(&self, path: &str, range: Range<u64>) -> Result<Bytes, Error> {
+        let path = object_store::path::Path::parse(path)?;
+        match self.object_store.get_range(&path, range).await {
+            Ok(bytes) => Ok(bytes),
+            Err(object_store::Error::NotFound { .. }) => {
+                Err(Error::ResourceNotFound(path.to_string()))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
     fn hash(&self) -> Bytes {
         self.hash.clone()
     }
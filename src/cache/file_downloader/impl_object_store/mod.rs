@@ -1,9 +1,11 @@
 use object_store::ClientConfigKey;
 
 mod aws;
+mod azure;
 mod gcp;
 
 pub use aws::AwsS3Downloader;
+pub use azure::AzureDownloader;
 pub use gcp::GcpDownloader;
 
 static CLIENT_CONFIG_KEYS: &[ClientConfigKey; 17] = &[
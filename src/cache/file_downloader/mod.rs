@@ -1,13 +1,19 @@
+use std::ops::Range;
+
 use async_trait::async_trait;
 use bytes::Bytes;
 use futures::Stream;
 
+mod http_downloader;
 mod impl_object_store;
 mod local_downloader;
+mod webdav_downloader;
 
+pub use http_downloader::{HttpDownloader, file_url, folder_url, relative_path};
 pub use impl_object_store::*;
 pub use local_downloader::LocalDownloader;
 use serde::{Deserialize, Serialize};
+pub use webdav_downloader::{WebDavClient, WebDavDownloader};
 
 use super::Error;
 
@@ -39,6 +45,12 @@ pub trait FileDownloader: Send + Sync {
     /// Returns ResourceNotFound error if the file does not exist.
     async fn get_meta(&self, path: &str) -> Result<FileMetadata, Error>;
 
+    /// Get the bytes `range` of a file from the file provider, the range must be in the file.
+    /// Returns ResourceNotFound error if the file does not exist.
+    async fn get_range(&self, _path: &str, _range: Range<u64>) -> Result<Bytes, Error> {
+        Err(Error::NotImplemented)
+    }
+
     /// returns a hash of the credentials of the provider to be used as a cache key
     fn hash(&self) -> Bytes {
         Bytes::new()
@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+
+use base64::Engine;
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+/// The hash algorithm of a digest challenge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Algorithm {
+    Md5,
+    Md5Sess,
+    Sha256,
+    Sha256Sess,
+}
+
+impl Algorithm {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "MD5" => Some(Self::Md5),
+            "MD5-SESS" => Some(Self::Md5Sess),
+            "SHA-256" => Some(Self::Sha256),
+            "SHA-256-SESS" => Some(Self::Sha256Sess),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Md5 => "MD5",
+            Self::Md5Sess => "MD5-sess",
+            Self::Sha256 => "SHA-256",
+            Self::Sha256Sess => "SHA-256-sess",
+        }
+    }
+
+    fn hash(&self, data: &str) -> String {
+        match self {
+            Self::Md5 | Self::Md5Sess => format!("{:x}", Md5::digest(data.as_bytes())),
+            Self::Sha256 | Self::Sha256Sess => format!("{:x}", Sha256::digest(data.as_bytes())),
+        }
+    }
+}
+
+/// A digest challenge of the server, reused until the server sends a new one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct DigestChallenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    algorithm: Algorithm,
+    /// whether the server accepts `qop=auth`, RFC 2069 digest otherwise
+    qop_auth: bool,
+    /// the number of requests sent with the nonce
+    nc: u32,
+}
+
+/// The authentication scheme asked by the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Auth {
+    Basic,
+    Digest(DigestChallenge),
+}
+
+impl Auth {
+    /// Read the `WWW-Authenticate` headers of a `401` response, digest is preferred over basic.
+    pub(super) fn from_challenges<'a>(headers: impl Iterator<Item = &'a str>) -> Option<Self> {
+        let mut basic = false;
+        for header in headers {
+            let header = header.trim();
+            let (scheme, params) = header.split_once(' ').unwrap_or((header, ""));
+            if scheme.eq_ignore_ascii_case("basic") {
+                basic = true;
+            } else if scheme.eq_ignore_ascii_case("digest") {
+                let mut params = parse_params(params);
+                let algorithm = match params.get("algorithm") {
+                    Some(name) => match Algorithm::from_name(name) {
+                        Some(algorithm) => algorithm,
+                        None => continue,
+                    },
+                    None => Algorithm::Md5,
+                };
+                let Some(nonce) = params.remove("nonce") else {
+                    continue;
+                };
+                return Some(Self::Digest(DigestChallenge {
+                    realm: params.remove("realm").unwrap_or_default(),
+                    nonce,
+                    opaque: params.remove("opaque"),
+                    algorithm,
+                    qop_auth: params
+                        .get("qop")
+                        .is_some_and(|qop| qop.split(',').any(|q| q.trim() == "auth")),
+                    nc: 0,
+                }));
+            }
+        }
+        basic.then_some(Self::Basic)
+    }
+
+    /// Get the `Authorization` header of a request, `uri` is the path and the query of the request.
+    pub(super) fn authorization(
+        &mut self,
+        username: &str,
+        password: &str,
+        method: &str,
+        uri: &str,
+    ) -> String {
+        match self {
+            Self::Basic => {
+                let credentials = base64::engine::general_purpose::STANDARD
+                    .encode(format!("{username}:{password}"));
+                format!("Basic {credentials}")
+            }
+            Self::Digest(challenge) => {
+                challenge.nc += 1;
+                let cnonce = format!("{:016x}", rand::random::<u64>());
+                challenge.authorization(username, password, method, uri, &cnonce)
+            }
+        }
+    }
+}
+
+impl DigestChallenge {
+    fn authorization(
+        &self,
+        username: &str,
+        password: &str,
+        method: &str,
+        uri: &str,
+        cnonce: &str,
+    ) -> String {
+        let algorithm = self.algorithm;
+        let mut ha1 = algorithm.hash(&format!("{username}:{}:{password}", self.realm));
+        if matches!(algorithm, Algorithm::Md5Sess | Algorithm::Sha256Sess) {
+            ha1 = algorithm.hash(&format!("{ha1}:{}:{cnonce}", self.nonce));
+        }
+        let ha2 = algorithm.hash(&format!("{method}:{uri}"));
+        let nc = format!("{:08x}", self.nc);
+
+        let mut header = format!(
+            r#"Digest username="{username}", realm="{}", nonce="{}", uri="{uri}", algorithm={}"#,
+            self.realm,
+            self.nonce,
+            algorithm.name()
+        );
+        let response = if self.qop_auth {
+            header.push_str(&format!(r#", qop=auth, nc={nc}, cnonce="{cnonce}""#));
+            algorithm.hash(&format!("{ha1}:{}:{nc}:{cnonce}:auth:{ha2}", self.nonce))
+        } else {
+            algorithm.hash(&format!("{ha1}:{}:{ha2}", self.nonce))
+        };
+        header.push_str(&format!(r#", response="{response}""#));
+        if let Some(opaque) = &self.opaque {
+            header.push_str(&format!(r#", opaque="{opaque}""#));
+        }
+        header
+    }
+}
+
+/// Parse the `key=value` and `key="quoted, value"` parameters of a challenge.
+fn parse_params(params: &str) -> HashMap<String, String> {
+    let mut res = HashMap::new();
+    let mut rest = params;
+    while let Some((key, value)) = rest.split_once('=') {
+        let key = key.trim_matches(|c: char| c == ',' || c.is_whitespace());
+        let value = value.trim_start();
+        let (value, next) = match value.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"').unwrap_or(quoted.len());
+                (
+                    quoted[..end].to_string(),
+                    &quoted[(end + 1).min(quoted.len())..],
+                )
+            }
+            None => {
+                let end = value.find(',').unwrap_or(value.len());
+                (value[..end].trim().to_string(), &value[end..])
+            }
+        };
+        res.insert(key.to_ascii_lowercase(), value);
+        rest = next;
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_digest_authorization() {
+        // the example of RFC 2617
+        let auth = Auth::from_challenges(
+            [
+                r#"Basic realm="testrealm@host.com""#,
+                r#"Digest realm="testrealm@host.com", qop="auth,auth-int", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", opaque="5ccc069c403ebaf9f0171e9517f40e41""#,
+            ]
+            .into_iter(),
+        );
+        let Some(Auth::Digest(mut challenge)) = auth else {
+            panic!("Expected a digest challenge");
+        };
+        challenge.nc = 1;
+        assert_eq!(
+            challenge.authorization(
+                "Mufasa",
+                "Circle Of Life",
+                "GET",
+                "/dir/index.html",
+                "0a4f113b"
+            ),
+            r#"Digest username="Mufasa", realm="testrealm@host.com", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", uri="/dir/index.html", algorithm=MD5, qop=auth, nc=00000001, cnonce="0a4f113b", response="6629fae49393a05397450978507c4ef1", opaque="5ccc069c403ebaf9f0171e9517f40e41""#
+        );
+    }
+
+    #[test]
+    fn test_basic_authorization() {
+        let mut auth = Auth::from_challenges([r#"Basic realm="WebDAV""#].into_iter()).unwrap();
+        assert_eq!(auth, Auth::Basic);
+        assert_eq!(
+            auth.authorization("Aladdin", "open sesame", "GET", "/"),
+            "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="
+        );
+        assert_eq!(
+            Auth::from_challenges([r#"Bearer realm="x""#].into_iter()),
+            None
+        );
+    }
+}
@@ -0,0 +1,358 @@
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use log::debug;
+use quick_xml::events::Event;
+use reqwest::{
+    Method, StatusCode,
+    header::{AUTHORIZATION, CONTENT_TYPE, RANGE, WWW_AUTHENTICATE},
+};
+use sha2::Digest;
+use url::Url;
+
+use super::super::{Error, FileDownloader, FileMetadata};
+use super::http_downloader::{range_body, range_header};
+use super::{file_url, relative_path};
+
+mod auth;
+
+use auth::Auth;
+
+/// the properties of the files asked by `PROPFIND`
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:">
+  <d:prop>
+    <d:resourcetype/>
+    <d:getetag/>
+    <d:getlastmodified/>
+    <d:getcontentlength/>
+  </d:prop>
+</d:propfind>"#;
+
+/// A file or a folder of a `PROPFIND` response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DavEntry {
+    /// the path relative to the root folder of the share, without trailing `/`
+    pub path: String,
+    pub collection: bool,
+    pub size: usize,
+    pub last_modified: chrono::DateTime<chrono::Utc>,
+    pub e_tag: Option<String>,
+}
+
+/// Sends the requests to a WebDAV share, authenticated with basic or digest auth.
+pub struct WebDavClient {
+    client: reqwest::Client,
+    base: Url,
+    credentials: Option<(String, String)>,
+    /// the authentication scheme asked by the server, known after the first `401`
+    auth: Mutex<Option<Auth>>,
+}
+
+impl WebDavClient {
+    /// Send a request for the file `path`,
+    ///     a `PROPFIND` with the `Depth` header if `depth` is set,
+    ///     for the bytes `range` of the file if set.
+    async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        depth: Option<&'static str>,
+        range: Option<&Range<u64>>,
+    ) -> Result<reqwest::Response, Error> {
+        let url = file_url(&self.base, path)?;
+        let uri = match url.query() {
+            Some(query) => format!("{}?{query}", url.path()),
+            None => url.path().to_string(),
+        };
+        // the request is sent again once if the server asks for the credentials
+        let mut challenged = false;
+        loop {
+            debug!("{method} {url}");
+            let mut request = self.client.request(method.clone(), url.clone());
+            if let Some(depth) = depth {
+                request = request
+                    .header("Depth", depth)
+                    .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+                    .body(PROPFIND_BODY);
+            }
+            if let Some(range) = range {
+                request = request.header(RANGE, range_header(range));
+            }
+            if let Some((username, password)) = &self.credentials {
+                let authorization = self
+                    .auth
+                    .lock()
+                    .unwrap()
+                    .as_mut()
+                    .map(|auth| auth.authorization(username, password, method.as_str(), &uri));
+                if let Some(authorization) = authorization {
+                    request = request.header(AUTHORIZATION, authorization);
+                }
+            }
+
+            let response = request.send().await?;
+            match response.status() {
+                StatusCode::UNAUTHORIZED if self.credentials.is_some() && !challenged => {
+                    let challenges = response
+                        .headers()
+                        .get_all(WWW_AUTHENTICATE)
+                        .iter()
+                        .filter_map(|v| v.to_str().ok());
+                    match Auth::from_challenges(challenges) {
+                        Some(auth) => *self.auth.lock().unwrap() = Some(auth),
+                        None => return Ok(response.error_for_status()?),
+                    }
+                    challenged = true;
+                }
+                StatusCode::NOT_FOUND => return Err(Error::ResourceNotFound(path.to_string())),
+                _ => return Ok(response.error_for_status()?),
+            }
+        }
+    }
+
+    /// List the properties of the file or the folder `path`,
+    ///     and of its children up to `depth`: `0`, `1` or `infinity`.
+    pub async fn propfind(&self, path: &str, depth: &'static str) -> Result<Vec<DavEntry>, Error> {
+        let propfind = Method::from_bytes(b"PROPFIND").unwrap();
+        let xml = self
+            .request(propfind, path, Some(depth), None)
+            .await?
+            .text()
+            .await?;
+        parse_multistatus(&xml, &self.base)
+    }
+}
+
+/// Parse the `multistatus` response of a `PROPFIND`, the entries outside of `base` are skipped.
+fn parse_multistatus(xml: &str, base: &Url) -> Result<Vec<DavEntry>, Error> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut entries = Vec::new();
+    let mut elements: Vec<Vec<u8>> = Vec::new();
+    let mut href = String::new();
+    let mut entry: Option<DavEntry> = None;
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| Error::InvalidWebDavResponse(e.to_string()))?;
+        match event {
+            Event::Start(e) => {
+                let name = e.local_name().as_ref().to_vec();
+                match name.as_slice() {
+                    b"response" => {
+                        href.clear();
+                        entry = Some(DavEntry {
+                            path: String::new(),
+                            collection: false,
+                            size: 0,
+                            last_modified: Default::default(),
+                            e_tag: None,
+                        });
+                    }
+                    b"collection" => {
+                        if let Some(entry) = &mut entry {
+                            entry.collection = true;
+                        }
+                    }
+                    _ => {}
+                }
+                elements.push(name);
+            }
+            Event::Empty(e) => {
+                if e.local_name().as_ref() == b"collection"
+                    && let Some(entry) = &mut entry
+                {
+                    entry.collection = true;
+                }
+            }
+            Event::Text(e) => {
+                let text = e
+                    .unescape()
+                    .map_err(|e| Error::InvalidWebDavResponse(e.to_string()))?;
+                let (Some(entry), Some(element)) = (&mut entry, elements.last()) else {
+                    continue;
+                };
+                match element.as_slice() {
+                    b"href" => href.push_str(&text),
+                    b"getetag" => entry.e_tag.get_or_insert_default().push_str(&text),
+                    b"getlastmodified" => {
+                        entry.last_modified = chrono::DateTime::parse_from_rfc2822(&text)
+                            .map(|v| v.to_utc())
+                            .unwrap_or_default();
+                    }
+                    b"getcontentlength" => entry.size = text.parse().unwrap_or_default(),
+                    _ => {}
+                }
+            }
+            Event::End(e) => {
+                elements.pop();
+                if e.local_name().as_ref() == b"response"
+                    && let Some(mut entry) = entry.take()
+                {
+                    // the href is an absolute path or an absolute URL
+                    let path = base
+                        .join(&href)
+                        .ok()
+                        .and_then(|url| relative_path(base, &url));
+                    if let Some(path) = path {
+                        entry.path = path.trim_end_matches('/').to_string();
+                        entries.push(entry);
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(entries)
+}
+
+/// Downloads the files of a WebDAV share.
+pub struct WebDavDownloader {
+    client: Arc<WebDavClient>,
+    hash: Bytes,
+}
+
+impl WebDavDownloader {
+    /// `base` is the URL of the root folder of the share,
+    ///     `credentials` are the username and the password.
+    pub fn new(base: Url, credentials: Option<(String, String)>) -> Result<Self, Error> {
+        let mut hasher = sha2::Sha256::new();
+        hasher.update("webdav:".as_bytes());
+        hasher.update(base.as_str().as_bytes());
+        if let Some((username, password)) = &credentials {
+            hasher.update(username.as_bytes());
+            hasher.update(":".as_bytes());
+            hasher.update(password.as_bytes());
+        }
+        let hash = hasher.finalize();
+        let hash = Bytes::copy_from_slice(hash.as_slice());
+
+        let client = reqwest::Client::builder().build()?;
+        Ok(Self {
+            client: Arc::new(WebDavClient {
+                client,
+                base,
+                credentials,
+                auth: Mutex::new(None),
+            }),
+            hash,
+        })
+    }
+
+    pub fn get_client(&self) -> Arc<WebDavClient> {
+        self.client.clone()
+    }
+}
+
+#[async_trait]
+impl FileDownloader for WebDavDownloader {
+    async fn get_file(
+        &self,
+        path: &str,
+    ) -> Result<std::pin::Pin<Box<dyn Stream<Item = Result<bytes::Bytes, Error>> + Send>>, Error>
+    {
+        let response = self.client.request(Method::GET, path, None, None).await?;
+        Ok(Box::pin(
+            response.bytes_stream().map(|b| b.map_err(|e| e.into())),
+        ))
+    }
+
+    async fn get_meta(&self, path: &str) -> Result<FileMetadata, Error> {
+        debug!("get meta of {}", path);
+        let entry = self
+            .client
+            .propfind(path, "0")
+            .await?
+            .into_iter()
+            .find(|entry| !entry.collection)
+            .ok_or_else(|| Error::ResourceNotFound(path.to_string()))?;
+        Ok(FileMetadata {
+            size: entry.size,
+            location: path.to_string(),
+            last_modified: entry.last_modified,
+            e_tag: entry.e_tag,
+        })
+    }
+
+    async fn get_range(&self, path: &str, range: Range<u64>) -> Result<Bytes, Error> {
+        if range.is_empty() {
+            return Ok(Bytes::new());
+        }
+        let response = self
+            .client
+            .request(Method::GET, path, None, Some(&range))
+            .await?;
+        range_body(response, &range).await
+    }
+
+    fn hash(&self) -> Bytes {
+        self.hash.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_multistatus() {
+        let xml = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:" xmlns:oc="http://owncloud.org/ns">
+  <d:response>
+    <d:href>/remote.php/dav/files/user/Music/</d:href>
+    <d:propstat>
+      <d:prop>
+        <d:resourcetype><d:collection/></d:resourcetype>
+        <d:getetag>"5f0c"</d:getetag>
+        <d:getlastmodified>Sat, 18 Oct 2025 02:16:38 GMT</d:getlastmodified>
+      </d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/remote.php/dav/files/user/Music/Album/</d:href>
+    <d:propstat>
+      <d:prop><d:resourcetype><d:collection></d:collection></d:resourcetype></d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+  <D:response xmlns:D="DAV:">
+    <D:href>http://host/remote.php/dav/files/user/Music/Song%20One.mp3</D:href>
+    <D:propstat>
+      <D:prop>
+        <D:resourcetype/>
+        <D:getetag>&quot;a1b2&quot;</D:getetag>
+        <D:getlastmodified>Sat, 18 Oct 2025 02:16:38 GMT</D:getlastmodified>
+        <D:getcontentlength>186030</D:getcontentlength>
+      </D:prop>
+      <D:status>HTTP/1.1 200 OK</D:status>
+    </D:propstat>
+  </D:response>
+</d:multistatus>"#;
+        let base = Url::parse("http://host/remote.php/dav/files/user/").unwrap();
+        let entries = parse_multistatus(xml, &base).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].path, "Music");
+        assert!(entries[0].collection);
+        assert_eq!(entries[1].path, "Music/Album");
+        assert!(entries[1].collection);
+        assert_eq!(
+            entries[2],
+            DavEntry {
+                path: "Music/Song One.mp3".to_string(),
+                collection: false,
+                size: 186030,
+                last_modified: chrono::DateTime::parse_from_rfc3339("2025-10-18T02:16:38Z")
+                    .unwrap()
+                    .to_utc(),
+                e_tag: Some("\"a1b2\"".to_string()),
+            }
+        );
+    }
+}
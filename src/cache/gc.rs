@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use log::{debug, info, warn};
+
+use super::Error;
+use super::lock::remove_unused;
+use super::meta::Meta;
+use super::utils::{TEMP_PREFIX, now};
+
+/// the temporary files not written for this long belong to an interrupted download
+const TEMP_FILE_MAX_IDLE: Duration = Duration::from_secs(60 * 60);
+
+/// a meta without its resource is kept this long, the resource is renamed after the meta is written
+const ORPHAN_META_GRACE: Duration = Duration::from_secs(60);
+
+/// The limits of a cache folder, enforced by [`sweep`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CacheLimits {
+    /// the maximum size of the cached resources in bytes, unlimited if None
+    pub max_size: Option<u64>,
+    /// the resources not used for this long are removed, kept forever if None
+    pub max_age: Option<Duration>,
+}
+
+/// What a sweep removed and kept.
+#[derive(Debug, Default, PartialEq)]
+pub struct SweepReport {
+    pub removed_files: usize,
+    pub removed_bytes: u64,
+    /// the size of the resources kept
+    pub size: u64,
+}
+
+/// A resource of the cache and its meta.
+struct Entry {
+    meta: Meta,
+    /// the size of the resource and its meta
+    size: u64,
+}
+
+/// Whether `name` is a SHA-256 in hexadecimal.
+fn is_hash(name: &str) -> bool {
+    name.len() == 64 && name.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Get the hash of the resource of a cached file named `<hash>` or `<hash>.<etag hash>`,
+///     None for the files not written by the cache.
+fn resource_hash(name: &str) -> Option<&str> {
+    let (hash, e_tag) = match name.split_once('.') {
+        Some((hash, e_tag)) => (hash, Some(e_tag)),
+        None => (name, None),
+    };
+    (is_hash(hash) && e_tag.is_none_or(is_hash)).then_some(hash)
+}
+
+/// Get the time since the file was last modified.
+fn idle_time(metadata: &std::fs::Metadata) -> Duration {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .unwrap_or_default()
+}
+
+async fn remove_file(path: &Path, size: u64, report: &mut SweepReport) {
+    match tokio::fs::remove_file(path).await {
+        Ok(()) => {
+            report.removed_files += 1;
+            report.removed_bytes += size;
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => warn!("failed to remove {path:?} from the cache: {e}"),
+    }
+}
+
+async fn remove_entry(entry: &Entry, report: &mut SweepReport) {
+    match entry.meta.remove().await {
+        Ok(()) => {
+            report.removed_files += 2;
+            report.removed_bytes += entry.size;
+        }
+        Err(e) => warn!(
+            "failed to remove {:?} from the cache: {e}",
+            entry.meta.resource_path
+        ),
+    }
+}
+
+/// Clean the cache folder `dir` and enforce its limits.
+///
+/// The abandoned temporary and lock files, the orphaned resources and metas, and the superseded
+///     versions of the resources are removed. Then the resources not used within `max_age`,
+///     then the least recently used resources until the cache fits in `max_size`.
+/// The files not written by the cache are left untouched.
+pub async fn sweep(dir: &Path, limits: &CacheLimits) -> Result<SweepReport, Error> {
+    let mut report = SweepReport::default();
+    let mut read_dir = match tokio::fs::read_dir(dir).await {
+        Ok(read_dir) => read_dir,
+        // nothing was cached yet
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(report),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut resources: HashMap<PathBuf, u64> = HashMap::new();
+    let mut metas: Vec<(PathBuf, std::fs::Metadata)> = Vec::new();
+    while let Some(file) = read_dir.next_entry().await? {
+        let Ok(name) = file.file_name().into_string() else {
+            continue;
+        };
+        let Ok(metadata) = file.metadata().await else {
+            continue;
+        };
+        if !metadata.is_file() {
+            continue;
+        }
+        if name.starts_with(TEMP_PREFIX) {
+            if idle_time(&metadata) > TEMP_FILE_MAX_IDLE {
+                debug!("removing the abandoned temporary file {name}");
+                remove_file(&file.path(), metadata.len(), &mut report).await;
+            }
+        } else if let Some(resource) = name.strip_suffix(".lock") {
+            // the lock files are removed by their holder, unless it stopped
+            if resource_hash(resource).is_some()
+                && idle_time(&metadata) > TEMP_FILE_MAX_IDLE
+                && remove_unused(&file.path()).unwrap_or(false)
+            {
+                debug!("removing the abandoned lock file {name}");
+                report.removed_files += 1;
+            }
+        } else if let Some(resource) = name.strip_suffix(".meta") {
+            if resource_hash(resource).is_some() {
+                metas.push((file.path(), metadata));
+            }
+        } else if resource_hash(&name).is_some() {
+            resources.insert(file.path(), metadata.len());
+        }
+    }
+
+    // the latest version of each resource, the other versions are superseded
+    let mut latest: HashMap<String, Entry> = HashMap::new();
+    for (meta_path, metadata) in metas {
+        let mut meta = match Meta::from_path(&meta_path).await {
+            Ok(meta)
+                if Meta::meta_path(&meta.resource_path).file_name() == meta_path.file_name() =>
+            {
+                meta
+            }
+            _ => {
+                debug!("removing the invalid meta {meta_path:?}");
+                remove_file(&meta_path, metadata.len(), &mut report).await;
+                continue;
+            }
+        };
+        // the folder may have been moved since the meta was written
+        meta.resource_path = dir.join(meta.resource_path.file_name().unwrap());
+        meta.meta_path = meta_path;
+        let Some(size) = resources.remove(&meta.resource_path) else {
+            if idle_time(&metadata) > ORPHAN_META_GRACE {
+                debug!("removing the orphaned meta {:?}", meta.meta_path);
+                remove_file(&meta.meta_path, metadata.len(), &mut report).await;
+            }
+            continue;
+        };
+        let entry = Entry {
+            meta,
+            size: size + metadata.len(),
+        };
+        let name = entry
+            .meta
+            .resource_path
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap();
+        let hash = resource_hash(name).unwrap().to_string();
+        let superseded = match latest.remove(&hash) {
+            Some(other) if other.meta.creation_time > entry.meta.creation_time => {
+                latest.insert(hash, other);
+                entry
+            }
+            Some(other) => {
+                latest.insert(hash, entry);
+                other
+            }
+            None => {
+                latest.insert(hash, entry);
+                continue;
+            }
+        };
+        debug!(
+            "removing the superseded version {:?}",
+            superseded.meta.resource_path
+        );
+        remove_entry(&superseded, &mut report).await;
+    }
+    // the resources without meta were never completely cached
+    for (path, size) in resources {
+        debug!("removing the orphaned resource {path:?}");
+        remove_file(&path, size, &mut report).await;
+    }
+
+    let mut entries: Vec<Entry> = latest.into_values().collect();
+    if let Some(max_age) = limits.max_age {
+        let oldest = now() - max_age.as_secs_f64();
+        let (kept, expired): (Vec<_>, Vec<_>) = entries
+            .into_iter()
+            .partition(|entry| entry.meta.last_access() >= oldest);
+        for entry in expired {
+            debug!("removing the expired {:?}", entry.meta.resource_path);
+            remove_entry(&entry, &mut report).await;
+        }
+        entries = kept;
+    }
+
+    report.size = entries.iter().map(|entry| entry.size).sum();
+    if let Some(max_size) = limits.max_size {
+        // the least recently used first
+        entries.sort_unstable_by(|a, b| a.meta.last_access().total_cmp(&b.meta.last_access()));
+        for entry in entries {
+            if report.size <= max_size {
+                break;
+            }
+            debug!("evicting {:?}", entry.meta.resource_path);
+            remove_entry(&entry, &mut report).await;
+            report.size -= entry.size;
+        }
+    }
+
+    Ok(report)
+}
+
+/// Sweep the cache folder `dir` every `interval`.
+pub async fn sweep_periodically(dir: PathBuf, limits: CacheLimits, interval: Duration) {
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        interval.tick().await;
+        match sweep(&dir, &limits).await {
+            Ok(report) if report.removed_files > 0 => info!(
+                "cache {dir:?} swept: {} files removed, {} bytes freed, {} bytes cached",
+                report.removed_files, report.removed_bytes, report.size
+            ),
+            Ok(report) => debug!("cache {dir:?} swept: {} bytes cached", report.size),
+            Err(e) => warn!("failed to sweep the cache {dir:?}: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::FileMetadata;
+
+    /// Write a cached resource of `size` bytes, created and last used `age` seconds ago.
+    async fn cache_resource(dir: &Path, name: &str, size: usize, age: f64) -> PathBuf {
+        let path = dir.join(name);
+        tokio::fs::write(&path, vec![0u8; size]).await.unwrap();
+        let file_meta = FileMetadata {
+            location: name.to_string(),
+            last_modified: Default::default(),
+            size,
+            e_tag: None,
+        };
+        let mut meta = Meta::new(path.clone(), file_meta, None);
+        meta.creation_time = now() - age;
+        meta.to_file().await.unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn test_sweep_orphans_and_superseded() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b) = ("a".repeat(64), "b".repeat(64));
+        let old = cache_resource(dir.path(), &format!("{a}.{}", "1".repeat(64)), 10, 60.0).await;
+        let new = cache_resource(dir.path(), &format!("{a}.{}", "2".repeat(64)), 10, 1.0).await;
+        let orphan = dir.path().join(&b);
+        tokio::fs::write(&orphan, b"orphan").await.unwrap();
+        let unrelated = dir.path().join("notes.txt");
+        tokio::fs::write(&unrelated, b"keep").await.unwrap();
+        let abandoned = dir.path().join(format!("{TEMP_PREFIX}abandoned"));
+        let file = std::fs::File::create(&abandoned).unwrap();
+        file.set_modified(std::time::SystemTime::now() - 2 * TEMP_FILE_MAX_IDLE)
+            .unwrap();
+        let downloading = dir.path().join(format!("{TEMP_PREFIX}downloading"));
+        tokio::fs::write(&downloading, b"partial").await.unwrap();
+        let lock = dir.path().join(format!("{b}.lock"));
+        let file = std::fs::File::create(&lock).unwrap();
+        file.set_modified(std::time::SystemTime::now() - 2 * TEMP_FILE_MAX_IDLE)
+            .unwrap();
+
+        let report = sweep(dir.path(), &CacheLimits::default()).await.unwrap();
+        assert_eq!(report.removed_files, 5);
+        assert!(!old.exists() && !Meta::meta_path(&old).exists());
+        assert!(new.exists() && Meta::meta_path(&new).exists());
+        assert!(!orphan.exists() && !abandoned.exists() && !lock.exists());
+        assert!(unrelated.exists() && downloading.exists());
+    }
+
+    #[tokio::test]
+    async fn test_sweep_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let expired = cache_resource(dir.path(), &"a".repeat(64), 1000, 7200.0).await;
+        let least_recent = cache_resource(dir.path(), &"b".repeat(64), 1000, 600.0).await;
+        let recent = cache_resource(dir.path(), &"c".repeat(64), 1000, 300.0).await;
+        // played after the others were cached
+        let mut meta = Meta::from_cache(&least_recent).await.unwrap();
+        meta.touch().await.unwrap();
+
+        let limits = CacheLimits {
+            max_size: Some(2500),
+            max_age: Some(Duration::from_secs(3600)),
+        };
+        let report = sweep(dir.path(), &limits).await.unwrap();
+        assert_eq!(report.removed_files, 4);
+        assert!(report.size <= 2500);
+        assert!(!expired.exists() && !recent.exists());
+        assert!(least_recent.exists());
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use log::debug;
+use once_cell::sync::Lazy;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+use super::Error;
+use crate::CONTEXT;
+
+/// time between two attempts to lock a file locked by another process
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// the locks of the resources being downloaded by this process, by path of their lock file
+static LOCKS: Lazy<std::sync::Mutex<HashMap<PathBuf, Weak<Mutex<()>>>>> =
+    Lazy::new(Default::default);
+
+/// An exclusive lock on a resource of the cache, held while the resource is downloaded.
+///
+/// The tasks of this process wait on an in-memory lock,
+///     the processes sharing the cache folder on an advisory lock of the file `path`.
+/// The lock file is removed when the lock is released.
+pub(crate) struct ResourceLock {
+    path: PathBuf,
+    file: File,
+    _guard: OwnedMutexGuard<()>,
+}
+
+/// Get the in-process lock of the lock file `path`.
+fn mutex(path: &Path) -> Arc<Mutex<()>> {
+    let mut locks = LOCKS.lock().unwrap();
+    locks.retain(|_, lock| lock.strong_count() > 0);
+    match locks.get(path).and_then(Weak::upgrade) {
+        Some(mutex) => mutex,
+        None => {
+            let mutex = Arc::new(Mutex::new(()));
+            locks.insert(path.to_path_buf(), Arc::downgrade(&mutex));
+            mutex
+        }
+    }
+}
+
+impl ResourceLock {
+    /// Wait for the lock of the lock file `path`.
+    pub(crate) async fn acquire(path: PathBuf) -> Result<Self, Error> {
+        let mutex = mutex(&path);
+        let guard = tokio::select! {
+            guard = mutex.lock_owned() => guard,
+            _ = CONTEXT.abort.cancelled() => return Err(Error::Aborted),
+        };
+        let file = lock_file(&path).await?;
+        Ok(Self {
+            path,
+            file,
+            _guard: guard,
+        })
+    }
+
+    /// Take the lock of the lock file `path` if it is free, None if it is held.
+    pub(crate) fn try_acquire(path: PathBuf) -> Result<Option<Self>, Error> {
+        let Ok(guard) = mutex(&path).try_lock_owned() else {
+            return Ok(None);
+        };
+        let Some(file) = try_lock_file(&path)? else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            path,
+            file,
+            _guard: guard,
+        }))
+    }
+}
+
+impl Drop for ResourceLock {
+    fn drop(&mut self) {
+        // removed before the unlock, the waiting processes then lock a new file
+        let _ = std::fs::remove_file(&self.path);
+        let _ = self.file.unlock();
+    }
+}
+
+/// Lock the file `path`, created if missing, waiting for the other processes.
+async fn lock_file(path: &Path) -> Result<File, Error> {
+    loop {
+        if let Some(file) = try_lock_file(path)? {
+            return Ok(file);
+        }
+        debug!("{path:?} is locked by another process");
+        tokio::select! {
+            _ = tokio::time::sleep(LOCK_POLL_INTERVAL) => {}
+            _ = CONTEXT.abort.cancelled() => return Err(Error::Aborted),
+        }
+    }
+}
+
+/// Lock the file `path`, created if missing, None if another process holds it.
+fn try_lock_file(path: &Path) -> Result<Option<File>, Error> {
+    loop {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => return Ok(None),
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+        // the previous holder removed the file it locked, this one may not be at `path` anymore
+        if is_locked_file(&file, path) {
+            return Ok(Some(file));
+        }
+    }
+}
+
+/// Whether `file` is still the file at `path`.
+fn is_locked_file(file: &File, path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        match (file.metadata(), std::fs::metadata(path)) {
+            (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+            _ => false,
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+/// Remove the lock file `path` left by a process that stopped, unless it is locked.
+pub(crate) fn remove_unused(path: &Path) -> std::io::Result<bool> {
+    let file = OpenOptions::new().write(true).open(path)?;
+    match file.try_lock() {
+        Ok(()) => {
+            std::fs::remove_file(path)?;
+            Ok(true)
+        }
+        Err(TryLockError::WouldBlock) => Ok(false),
+        Err(TryLockError::Error(e)) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_resource_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("resource.lock");
+
+        // the tasks of this process hold the lock one at a time
+        let holders = Arc::new(AtomicUsize::new(0));
+        let tasks: Vec<_> = (0..4)
+            .map(|_| {
+                let (path, holders) = (path.clone(), holders.clone());
+                tokio::spawn(async move {
+                    let _lock = ResourceLock::acquire(path).await.unwrap();
+                    assert_eq!(holders.fetch_add(1, Ordering::SeqCst), 0);
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    holders.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert!(!path.exists());
+
+        // a lock held by another process, an open file description of its own
+        let other = File::create(&path).unwrap();
+        other.lock().unwrap();
+        assert!(!remove_unused(&path).unwrap());
+        assert!(ResourceLock::try_acquire(path.clone()).unwrap().is_none());
+        let waiting = tokio::spawn(ResourceLock::acquire(path.clone()));
+        tokio::time::sleep(3 * LOCK_POLL_INTERVAL).await;
+        assert!(!waiting.is_finished());
+        std::fs::remove_file(&path).unwrap();
+        drop(other);
+        let lock = waiting.await.unwrap().unwrap();
+        assert!(is_locked_file(&lock.file, &path));
+        assert!(ResourceLock::try_acquire(path.clone()).unwrap().is_none());
+        drop(lock);
+        assert!(ResourceLock::try_acquire(path.clone()).unwrap().is_some());
+        assert!(!path.exists());
+    }
+}
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 
-use super::utils::now;
+use super::utils::{now, temp_file_in};
 use super::{Error, FileMetadata};
 
 /// Holds information about a cached resource.
@@ -17,6 +17,9 @@ pub(crate) struct Meta {
     pub(crate) expires: Option<f64>,
     /// Time this version of the resource was cached.
     pub(crate) creation_time: f64,
+    /// Time this version of the resource was last used, the creation time if never used.
+    #[serde(default)]
+    pub(crate) last_access: Option<f64>,
 }
 
 impl Meta {
@@ -37,6 +40,7 @@ impl Meta {
             meta_path,
             expires,
             creation_time,
+            last_access: None,
         }
     }
 
@@ -48,9 +52,34 @@ impl Meta {
         meta_path
     }
 
+    /// Write the meta next to the resource, replacing the previous one atomically.
     pub(crate) async fn to_file(&self) -> Result<(), Error> {
         let serialized = serde_json::to_string(self).unwrap();
-        tokio::fs::write(&self.meta_path, &serialized[..]).await?;
+        let tempfile = temp_file_in(self.meta_path.parent().unwrap())?;
+        tokio::fs::write(tempfile.path(), &serialized[..]).await?;
+        tokio::fs::rename(tempfile.path(), &self.meta_path).await?;
+        Ok(())
+    }
+
+    /// Record that the resource is used now, the least recently used resources are evicted first.
+    pub(crate) async fn touch(&mut self) -> Result<(), Error> {
+        self.last_access = Some(now());
+        self.to_file().await
+    }
+
+    /// Time this version of the resource was last used.
+    pub(crate) fn last_access(&self) -> f64 {
+        self.last_access.unwrap_or(self.creation_time)
+    }
+
+    /// Remove the resource and its meta, the files already removed are ignored.
+    pub(crate) async fn remove(&self) -> Result<(), Error> {
+        for path in [&self.resource_path, &self.meta_path] {
+            match tokio::fs::remove_file(path).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
         Ok(())
     }
 
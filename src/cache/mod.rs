@@ -1,9 +1,13 @@
 mod cache_struct;
 mod error;
 mod file_downloader;
+mod gc;
+mod lock;
 mod meta;
 mod utils;
 
 pub use cache_struct::*;
 pub use error::Error;
 pub use file_downloader::*;
+pub use gc::{CacheLimits, sweep_periodically};
+pub use utils::read_file_range;
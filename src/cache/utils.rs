@@ -1,5 +1,14 @@
+use bytes::Bytes;
 use sha2::{Digest, Sha256};
+use std::io::SeekFrom;
+use std::ops::Range;
+use std::path::Path;
 use std::time::SystemTime;
+use tempfile::NamedTempFile;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+/// prefix of the temporary files of the cache, removed by the sweep once abandoned
+pub(crate) const TEMP_PREFIX: &str = ".rustcast-";
 
 pub(crate) fn hash_str(s: &str) -> String {
     format!("{:x}", Sha256::digest(s.as_bytes()))
@@ -12,3 +21,21 @@ pub(crate) fn now() -> f64 {
         .unwrap()
         .as_secs_f64()
 }
+
+/// Create a temporary file in the cache folder `dir`, removed when dropped.
+pub(crate) fn temp_file_in(dir: &Path) -> std::io::Result<NamedTempFile> {
+    tempfile::Builder::new()
+        .prefix(TEMP_PREFIX)
+        .tempfile_in(dir)
+}
+
+/// Read the bytes `range` of the file `path`, fewer bytes if the file ends before the range.
+pub async fn read_file_range(path: &Path, range: Range<u64>) -> std::io::Result<Bytes> {
+    let mut file = tokio::fs::File::open(path).await?;
+    file.seek(SeekFrom::Start(range.start)).await?;
+    let mut buf = Vec::new();
+    file.take(range.end.saturating_sub(range.start))
+        .read_to_end(&mut buf)
+        .await?;
+    Ok(buf.into())
+}
@@ -0,0 +1,314 @@
+use std::{collections::HashMap, net::IpAddr, path::Path, sync::Arc, time::Duration};
+
+use futures::StreamExt;
+
+use crate::{
+    config::{ConfigFormat, GlobalConfig, PlaylistChildConfig},
+    file_provider::build_file_provider,
+};
+
+/// time given to a file provider to list its root folder
+const LIST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A child of a playlist and where it is in the configuration.
+struct ChildRef<'a> {
+    location: String,
+    child: &'a PlaylistChildConfig,
+}
+
+/// Get every child of the playlists, the fail over children and the children of `Playlists` included.
+fn children(config: &GlobalConfig) -> Vec<ChildRef<'_>> {
+    let mut ids: Vec<_> = config.playlists.keys().collect();
+    ids.sort_unstable();
+    let mut pending: Vec<_> = ids
+        .into_iter()
+        .rev()
+        .map(|id| ChildRef {
+            location: format!("playlists.{id}.child"),
+            child: &config.playlists[id].child,
+        })
+        .collect();
+
+    let mut res = Vec::new();
+    while let Some(child_ref) = pending.pop() {
+        let (location, child) = (&child_ref.location, child_ref.child);
+        let fail_over = match child {
+            PlaylistChildConfig::Silent => None,
+            PlaylistChildConfig::LocalFolder { fail_over, .. }
+            | PlaylistChildConfig::LocalFiles { fail_over, .. }
+            | PlaylistChildConfig::RemoteFolder { fail_over, .. }
+            | PlaylistChildConfig::RemoteFiles { fail_over, .. }
+            | PlaylistChildConfig::Playlists { fail_over, .. } => fail_over.as_deref(),
+        };
+        if let Some(fail_over) = fail_over {
+            pending.push(ChildRef {
+                location: format!("{location}.fail_over"),
+                child: fail_over,
+            });
+        }
+        if let PlaylistChildConfig::Playlists { children, .. } = child {
+            for (i, c) in children.iter().enumerate().rev() {
+                pending.push(ChildRef {
+                    location: format!("{location}.children[{i}]"),
+                    child: c,
+                });
+            }
+        }
+        res.push(child_ref);
+    }
+    res
+}
+
+/// Whether two listeners can not be bound at the same time.
+fn same_address(a: &(String, u16), b: &(String, u16)) -> bool {
+    if a.1 != b.1 {
+        return false;
+    }
+    match (a.0.parse::<IpAddr>(), b.0.parse::<IpAddr>()) {
+        // `0.0.0.0` and `::` take the port on every address of their family
+        (Ok(x), Ok(y)) => {
+            x == y || (x.is_ipv4() == y.is_ipv4() && (x.is_unspecified() || y.is_unspecified()))
+        }
+        _ => a.0 == b.0,
+    }
+}
+
+/// Check the configuration without touching the file system or the network:
+///     the playlists of the outputs, the file providers of the playlists,
+///     and the addresses of the listeners.
+pub fn preflight(config: &GlobalConfig) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    let mut paths = HashMap::new();
+    // the servers of the outputs, the admin listener and the Shoutcast v1 source listeners
+    let mut listeners: Vec<((String, u16), String)> = Vec::new();
+    for (i, output) in config.outputs.iter().enumerate() {
+        let location = format!("outputs[{i}]");
+        if !config.playlists.contains_key(&output.playlist) {
+            problems.push(format!(
+                "{location}: playlist {} not found",
+                output.playlist
+            ));
+        }
+        let path = output.path.trim_matches('/');
+        let key = (output.host.clone(), output.port, path.to_string());
+        if let Some(other) = paths.insert(key, location.clone()) {
+            problems.push(format!(
+                "{location}: path /{path} of {}:{} is already used by {other}",
+                output.host, output.port
+            ));
+        }
+        let address = (output.host.clone(), output.port);
+        // the outputs of a server share its listener
+        if !listeners.iter().any(|(a, _)| *a == address) {
+            listeners.push((address, location));
+        }
+    }
+    if let Some(admin) = &config.admin {
+        listeners.push(((admin.host.clone(), admin.port), "admin".to_string()));
+    }
+    let mut ids: Vec<_> = config.playlists.keys().collect();
+    ids.sort_unstable();
+    for id in ids {
+        if let Some(live) = &config.playlists[id].live
+            && let Some(port) = live.shoutcast_v1_port
+        {
+            let host = live
+                .shoutcast_v1_host
+                .clone()
+                .unwrap_or_else(|| "0.0.0.0".to_string());
+            listeners.push(((host, port), format!("playlists.{id}.live")));
+        }
+    }
+    for (i, (address, location)) in listeners.iter().enumerate() {
+        for (other_address, other) in &listeners[..i] {
+            if same_address(address, other_address) {
+                problems.push(format!(
+                    "{location}: {}:{} collides with {other} on {}:{}",
+                    address.0, address.1, other_address.0, other_address.1
+                ));
+            }
+        }
+    }
+
+    if let Err(e) = config.cache.max_age() {
+        problems.push(format!("cache.max_age: {e}"));
+    }
+    if let Err(e) = config.cache.sweep_interval() {
+        problems.push(format!("cache.sweep_interval: {e}"));
+    }
+    if let Err(e) = config.shutdown.timeout() {
+        problems.push(format!("shutdown.timeout: {e}"));
+    }
+
+    for ChildRef { location, child } in children(config) {
+        let remote_client = match child {
+            PlaylistChildConfig::RemoteFolder { remote_client, .. } => remote_client.as_str(),
+            PlaylistChildConfig::RemoteFiles { remote_client, .. } => remote_client.as_str(),
+            _ => continue,
+        };
+        if !config.file_provider.contains_key(remote_client) {
+            problems.push(format!(
+                "{location}: file provider {remote_client} not found"
+            ));
+        }
+    }
+
+    problems
+}
+
+/// Check the configuration before it is used: the `preflight` checks,
+///     the local folders and files, and the root folder of each file provider.
+pub async fn check(config: &GlobalConfig) -> Vec<String> {
+    let mut problems = preflight(config);
+
+    for ChildRef { location, child } in children(config) {
+        match child {
+            PlaylistChildConfig::LocalFolder { folder, .. }
+                if !Path::new(folder.as_str()).is_dir() =>
+            {
+                problems.push(format!("{location}: folder {folder} not found"));
+            }
+            PlaylistChildConfig::LocalFiles { files, .. } => {
+                for file in files.iter() {
+                    if !Path::new(file.as_str()).is_file() {
+                        problems.push(format!("{location}: file {file} not found"));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut names: Vec<_> = config.file_provider.keys().collect();
+    names.sort_unstable();
+    let listings = names.into_iter().map(|name| async move {
+        let provider = HashMap::from([(name.clone(), config.file_provider[name].clone())]);
+        let res = tokio::time::timeout(LIST_TIMEOUT, list_root(provider))
+            .await
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("no answer in {LIST_TIMEOUT:?}")));
+        res.err()
+            .map(|e| format!("file_provider.{name}: failed to list the root folder: {e:#}"))
+    });
+    problems.extend(
+        futures::future::join_all(listings)
+            .await
+            .into_iter()
+            .flatten(),
+    );
+
+    problems
+}
+
+/// Build a file provider and read the first file of its root folder.
+///
+/// The file provider caches in a temporary folder,
+///     the listings saved in the cache folder would hide an unreachable file provider.
+async fn list_root(
+    provider: HashMap<String, crate::config::FileProviderConfig>,
+) -> anyhow::Result<()> {
+    let cache_dir = tempfile::tempdir()?;
+    let cache_dir = Some(Arc::new(cache_dir.path().to_string_lossy().into_owned()));
+    for provider in build_file_provider(cache_dir, provider)
+        .await?
+        .into_values()
+    {
+        let mut files = provider.list_files(None, false).await?;
+        if let Some(file) = files.next().await {
+            file?;
+        }
+    }
+    Ok(())
+}
+
+/// Read and check the configuration file, print the problems and get the exit code.
+pub async fn run(path: &str, format: Option<ConfigFormat>) -> i32 {
+    let problems = match GlobalConfig::from_path(path, format).await {
+        Ok(config) => check(&config).await,
+        Err(e) => vec![e.to_string()],
+    };
+    if problems.is_empty() {
+        println!("{path}: OK");
+        return 0;
+    }
+    println!("{path}: {} problem(s)", problems.len());
+    for problem in problems {
+        println!("  - {problem}");
+    }
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(json: &str) -> GlobalConfig {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_preflight() {
+        let config = config(
+            r#"{
+            "playlists": {
+                "main": {
+                    "name": "Main",
+                    "child": {"Playlists": {"children": [
+                        "Silent",
+                        {"RemoteFolder": {"folder": "music", "remote_client": "s4", "fail_over": {
+                            "RemoteFiles": {"files": ["a.mp3"], "remote_client": "s3"}
+                        }}}
+                    ]}},
+                    "live": {"password": "hackme", "shoutcast_v1_port": 8000}
+                }
+            },
+            "file_provider": {"s3": {"AwsS3": {"bucket": "music"}}},
+            "outputs": [
+                {"host": "127.0.0.1", "port": 8000, "path": "/live", "playlist": "main"},
+                {"host": "127.0.0.1", "port": 8000, "path": "live/", "playlist": "mian"},
+                {"host": "127.0.0.1", "port": 8001, "path": "/", "playlist": "main"}
+            ],
+            "admin": {"host": "localhost", "port": 8001},
+            "cache": {"max_age": 1e300},
+            "shutdown": {"timeout": 1e20}
+        }"#,
+        );
+        assert_eq!(
+            preflight(&config),
+            vec![
+                "outputs[1]: playlist mian not found",
+                "outputs[1]: path /live of 127.0.0.1:8000 is already used by outputs[0]",
+                "playlists.main.live: 0.0.0.0:8000 collides with outputs[0] on 127.0.0.1:8000",
+                "cache.max_age: invalid duration of 1e300 seconds: \
+                    cannot convert float seconds to Duration: value is either too big or NaN",
+                "shutdown.timeout: invalid duration of 1e20 seconds: \
+                    cannot convert float seconds to Duration: value is either too big or NaN",
+                "playlists.main.child.children[1]: file provider s4 not found",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_local_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path().to_str().unwrap();
+        let config = config(&format!(
+            r#"{{
+            "playlists": {{
+                "main": {{"name": "Main", "child": {{"LocalFolder": {{"folder": "{folder}", "fail_over": {{
+                    "LocalFiles": {{"files": ["{folder}/missing.mp3"]}}
+                }}}}}}}},
+                "other": {{"name": "Other", "child": {{"LocalFolder": {{"folder": "{folder}/missing"}}}}}}
+            }},
+            "outputs": []
+        }}"#
+        ));
+        assert_eq!(
+            check(&config).await,
+            vec![
+                format!("playlists.main.child.fail_over: file {folder}/missing.mp3 not found"),
+                format!("playlists.other.child: folder {folder}/missing not found"),
+            ]
+        );
+    }
+}
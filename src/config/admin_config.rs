@@ -0,0 +1,24 @@
+fn default_admin_host() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_admin_user() -> String {
+    "admin".to_string()
+}
+
+/// Configuration of the admin listener,
+/// the listener is only started if the admin section is set.
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema, Clone, PartialEq, Eq)]
+pub struct AdminConfig {
+    /// host of the admin listener, default to `127.0.0.1`
+    #[serde(default = "default_admin_host")]
+    pub host: String,
+    /// port of the admin listener
+    pub port: u16,
+    /// user name of the admin API, default to `admin`
+    #[serde(default = "default_admin_user")]
+    pub user: String,
+    /// password of the admin API, the API is disabled if not set
+    #[serde(default)]
+    pub password: Option<String>,
+}
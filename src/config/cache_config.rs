@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+use super::duration_from_secs;
+
+fn default_sweep_interval() -> f64 {
+    600.0
+}
+
+/// Limits of the folder of the files downloaded by the file providers,
+///     enforced by a sweep of the folder.
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema, Clone, PartialEq)]
+pub struct CacheConfig {
+    /// maximum size of the cached files in megabytes,
+    ///     the least recently played files are removed first, unlimited if not set
+    #[serde(default)]
+    pub max_size_mb: Option<u64>,
+    /// seconds after which a file not played is removed, the files are kept if not set
+    #[serde(default)]
+    pub max_age: Option<f64>,
+    /// seconds between two sweeps of the cache folder, default to `600`
+    #[serde(default = "default_sweep_interval")]
+    pub sweep_interval: f64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            max_size_mb: None,
+            max_age: None,
+            sweep_interval: default_sweep_interval(),
+        }
+    }
+}
+
+impl CacheConfig {
+    /// the age after which a file not played is removed, a negative age is zero
+    pub fn max_age(&self) -> anyhow::Result<Option<Duration>> {
+        self.max_age
+            .map(|age| duration_from_secs(age.max(0.0)))
+            .transpose()
+    }
+
+    /// the time between two sweeps, at least one second
+    pub fn sweep_interval(&self) -> anyhow::Result<Duration> {
+        duration_from_secs(self.sweep_interval.max(1.0))
+    }
+}
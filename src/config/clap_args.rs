@@ -1,13 +1,28 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
-use super::log_level::LogLevel;
+use super::{ConfigFormat, log_level::LogLevel};
 
 /// RustCast is a robust and efficient Shoutcast streaming server written in Rust.
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 pub struct ClapArgs {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// The path to the configuration file
-    pub config: String,
+    #[arg(required = true)]
+    pub config: Option<String>,
+
+    /// The format of the configuration file.
+    /// If not specified, the format is guessed from the extension of the file, JSON by default.
+    #[arg(short, long, value_enum)]
+    pub format: Option<ConfigFormat>,
 
     /// Log level.
     /// The log level specified here will override the log level in the configuration file.
@@ -21,3 +36,47 @@ pub struct ClapArgs {
     #[arg(long)]
     pub log_file: Vec<String>,
 }
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Check the configuration file without starting the servers.
+    /// Every problem found is printed, the exit code is not zero if there is any.
+    Check {
+        /// The path to the configuration file
+        config: String,
+
+        /// The format of the configuration file.
+        /// If not specified, the format is guessed from the extension of the file, JSON by default.
+        #[arg(short, long, value_enum)]
+        format: Option<ConfigFormat>,
+    },
+    /// Print the JSON Schema of the configuration file, to validate it in an editor or a CI.
+    Schema,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_check() {
+        let args = ClapArgs::try_parse_from(["rustcast", "config.json"]).unwrap();
+        assert!(args.command.is_none());
+        assert_eq!(args.config.as_deref(), Some("config.json"));
+
+        let args =
+            ClapArgs::try_parse_from(["rustcast", "check", "-f", "yaml", "config.txt"]).unwrap();
+        match args.command {
+            Some(Command::Check { config, format }) => {
+                assert_eq!(config, "config.txt");
+                assert_eq!(format, Some(ConfigFormat::Yaml));
+            }
+            _ => panic!("Expected the check command"),
+        }
+
+        let args = ClapArgs::try_parse_from(["rustcast", "schema"]).unwrap();
+        assert!(matches!(args.command, Some(Command::Schema)));
+
+        assert!(ClapArgs::try_parse_from(["rustcast"]).is_err());
+    }
+}
@@ -0,0 +1,46 @@
+use std::path::Path;
+
+use clap::ValueEnum;
+
+/// The format of the configuration file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Default)]
+pub enum ConfigFormat {
+    #[default]
+    Json,
+    /// `.yaml` or `.yml`
+    Yaml,
+    Toml,
+}
+
+impl ConfigFormat {
+    /// Get the format from the extension of the file, JSON if the extension is unknown.
+    pub fn from_path(path: &str) -> Self {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("yaml" | "yml") => Self::Yaml,
+            Some("toml") => Self::Toml,
+            _ => Self::Json,
+        }
+    }
+
+    /// Parse a document into a JSON value, the syntax errors point to the line and the column.
+    pub fn parse_value(&self, content: &str) -> anyhow::Result<serde_json::Value> {
+        Ok(match self {
+            Self::Json => serde_json::from_str(content)?,
+            Self::Yaml => serde_norway::from_str(content)?,
+            Self::Toml => toml::from_str(content)?,
+        })
+    }
+
+    /// Deserialize a document, the errors point to the line and the column.
+    pub fn deserialize<T: serde::de::DeserializeOwned>(&self, content: &str) -> anyhow::Result<T> {
+        Ok(match self {
+            Self::Json => serde_json::from_str(content)?,
+            Self::Yaml => serde_norway::from_str(content)?,
+            Self::Toml => toml::from_str(content)?,
+        })
+    }
+}
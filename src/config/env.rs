@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::file_provider_config::{
+    AwsS3ConfigKeys, AzureBlobStorageConfigKeys, GoogleCloudStorageConfigKeys,
+};
+
+/// prefix of the environment variables creating or overriding the file providers
+const PROVIDER_PREFIX: &str = "RUSTCAST_PROVIDER_";
+
+/// suffix of the environment variables naming a file holding the value
+const FILE_SUFFIX: &str = "_FILE";
+
+/// the types of the file providers, the variants of `FileProviderConfig`
+const PROVIDER_TYPES: &[&str] = &[
+    "AwsS3",
+    "GoogleCloudStorage",
+    "AzureBlobStorage",
+    "Http",
+    "WebDav",
+];
+
+/// the keys of the file providers configured by a struct instead of a key map
+const HTTP_KEYS: &[&str] = &["url", "manifest"];
+const WEB_DAV_KEYS: &[&str] = &["url", "username", "password"];
+
+/// Interpolate the environment variables in the strings of the configuration,
+///     and apply the `RUSTCAST_PROVIDER_<NAME>_<KEY>` overrides of the file providers.
+pub fn apply_env(config: &mut Value, env: &HashMap<String, String>) -> anyhow::Result<()> {
+    interpolate_value(config, env, &mut String::new())?;
+    override_file_providers(config, env)
+}
+
+/// Get the value of a variable, or the content of the file named by `<NAME>_FILE`.
+fn lookup(env: &HashMap<String, String>, name: &str) -> anyhow::Result<Option<String>> {
+    if let Some(value) = env.get(name) {
+        return Ok(Some(value.clone()));
+    }
+    match env.get(&format!("{name}{FILE_SUFFIX}")) {
+        Some(path) => read_secret(path).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Read a secret from a mounted file, without its trailing line break.
+fn read_secret(path: &str) -> anyhow::Result<String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("failed to read the secret file {path}: {e}"))?;
+    Ok(content.trim_end_matches(['\n', '\r']).to_string())
+}
+
+fn interpolate_value(
+    value: &mut Value,
+    env: &HashMap<String, String>,
+    path: &mut String,
+) -> anyhow::Result<()> {
+    match value {
+        Value::String(s) if s.contains('$') => {
+            *s = interpolate(s, env).map_err(|e| anyhow::anyhow!("{path}: {e}"))?;
+        }
+        Value::Array(values) => {
+            for (i, value) in values.iter_mut().enumerate() {
+                let len = path.len();
+                path.push_str(&format!("[{i}]"));
+                interpolate_value(value, env, path)?;
+                path.truncate(len);
+            }
+        }
+        Value::Object(values) => {
+            for (key, value) in values.iter_mut() {
+                let len = path.len();
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(key);
+                interpolate_value(value, env, path)?;
+                path.truncate(len);
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Replace `${NAME}` and `${NAME:-default}` by the value of the variable, `$$` by `$`.
+fn interpolate(s: &str, env: &HashMap<String, String>) -> anyhow::Result<String> {
+    let mut res = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('$') {
+        res.push_str(&rest[..i]);
+        rest = &rest[i..];
+        if let Some(r) = rest.strip_prefix("$$") {
+            res.push('$');
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix("${") {
+            let end = r
+                .find('}')
+                .ok_or_else(|| anyhow::anyhow!("unclosed `${{` in {s:?}"))?;
+            let (name, default) = match r[..end].split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (&r[..end], None),
+            };
+            match (lookup(env, name)?, default) {
+                (Some(value), _) => res.push_str(&value),
+                (None, Some(default)) => res.push_str(default),
+                (None, None) => {
+                    return Err(anyhow::anyhow!("environment variable {name} is not set"));
+                }
+            }
+            rest = &r[end + 1..];
+        } else {
+            res.push('$');
+            rest = &rest[1..];
+        }
+    }
+    res.push_str(rest);
+    Ok(res)
+}
+
+/// Get the name of a file provider in the environment variables: uppercase, `_` for the other characters.
+fn env_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Check that `key` is a key of the file providers of type `provider_type`.
+fn check_key(provider_type: &str, key: &str) -> anyhow::Result<()> {
+    let value = Value::String(key.to_string());
+    let known = match provider_type {
+        "AwsS3" => serde_json::from_value::<AwsS3ConfigKeys>(value).is_ok(),
+        "GoogleCloudStorage" => {
+            serde_json::from_value::<GoogleCloudStorageConfigKeys>(value).is_ok()
+        }
+        "AzureBlobStorage" => serde_json::from_value::<AzureBlobStorageConfigKeys>(value).is_ok(),
+        "Http" => HTTP_KEYS.contains(&key),
+        "WebDav" => WEB_DAV_KEYS.contains(&key),
+        _ => {
+            return Err(anyhow::anyhow!(
+                "unknown file provider type {provider_type}"
+            ));
+        }
+    };
+    if known {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("unknown key {key} of {provider_type}"))
+    }
+}
+
+/// Apply the `RUSTCAST_PROVIDER_<NAME>_TYPE` and `RUSTCAST_PROVIDER_<NAME>_<KEY>` variables.
+///
+/// `<NAME>_TYPE` creates the file provider `<name>` if no provider is named `<NAME>`.
+/// The `_FILE` variables read the value from a file.
+fn override_file_providers(
+    config: &mut Value,
+    env: &HashMap<String, String>,
+) -> anyhow::Result<()> {
+    let mut vars: Vec<(&str, &str)> = env
+        .iter()
+        .filter_map(|(k, v)| Some((k.strip_prefix(PROVIDER_PREFIX)?, v.as_str())))
+        .collect();
+    if vars.is_empty() {
+        return Ok(());
+    }
+    // the overrides are applied in a stable order
+    vars.sort_unstable();
+
+    let Value::Object(config) = config else {
+        return Err(anyhow::anyhow!("the configuration must be an object"));
+    };
+    let providers = config
+        .entry("file_provider")
+        .or_insert_with(|| Value::Object(Default::default()));
+    let Value::Object(providers) = providers else {
+        return Err(anyhow::anyhow!("file_provider must be an object"));
+    };
+
+    // the new providers first, their keys may be set by the other variables
+    for (var, value) in &vars {
+        let (name, value) = match var.strip_suffix("_TYPE_FILE") {
+            Some(name) => (name, read_secret(value)?),
+            None => match var.strip_suffix("_TYPE") {
+                Some(name) => (name, value.to_string()),
+                None => continue,
+            },
+        };
+        if providers.keys().any(|n| env_name(n) == name) {
+            continue;
+        }
+        if !PROVIDER_TYPES.contains(&value.as_str()) {
+            return Err(anyhow::anyhow!(
+                "{PROVIDER_PREFIX}{var}: unknown file provider type {value}"
+            ));
+        }
+        let mut provider = serde_json::Map::new();
+        provider.insert(value, Value::Object(Default::default()));
+        providers.insert(name.to_ascii_lowercase(), Value::Object(provider));
+    }
+
+    for (var, value) in vars {
+        if var.ends_with("_TYPE") || var.ends_with("_TYPE_FILE") {
+            continue;
+        }
+        // the longest provider name, `<NAME>_` may be the start of another name
+        let Some((provider_name, rest)) = providers
+            .keys()
+            .filter_map(|name| {
+                let rest = var.strip_prefix(&env_name(name))?.strip_prefix('_')?;
+                Some((name.clone(), rest))
+            })
+            .max_by_key(|(name, _)| name.len())
+        else {
+            return Err(anyhow::anyhow!(
+                "{PROVIDER_PREFIX}{var}: no file provider matches, set {PROVIDER_PREFIX}<NAME>_TYPE to create one"
+            ));
+        };
+        let Some(Value::Object(provider)) = providers.get_mut(&provider_name) else {
+            return Err(anyhow::anyhow!(
+                "file provider {provider_name} must be an object"
+            ));
+        };
+        let Some((provider_type, keys)) = provider.iter_mut().next() else {
+            return Err(anyhow::anyhow!("file provider {provider_name} has no type"));
+        };
+        let Value::Object(keys) = keys else {
+            return Err(anyhow::anyhow!(
+                "file provider {provider_name} must be an object"
+            ));
+        };
+
+        // a key may end with `_FILE` itself, e.g. `federated_token_file`
+        let key = rest.to_ascii_lowercase();
+        let (key, value) = match check_key(provider_type, &key) {
+            Ok(()) => (key, value.to_string()),
+            Err(e) => match key.strip_suffix("_file") {
+                Some(k) if check_key(provider_type, k).is_ok() => {
+                    (k.to_string(), read_secret(value)?)
+                }
+                _ => return Err(anyhow::anyhow!("{PROVIDER_PREFIX}{var}: {e}")),
+            },
+        };
+        keys.insert(key, Value::String(value));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(vars: &[(&str, &str)]) -> HashMap<String, String> {
+        vars.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let secret = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(secret.path(), "from-file\n").unwrap();
+        let env = vars(&[
+            ("BUCKET", "music"),
+            ("SECRET_FILE", secret.path().to_str().unwrap()),
+        ]);
+
+        assert_eq!(
+            interpolate("s3://${BUCKET}/${PREFIX:-tracks}", &env).unwrap(),
+            "s3://music/tracks"
+        );
+        assert_eq!(interpolate("${SECRET}", &env).unwrap(), "from-file");
+        assert_eq!(interpolate("$$5 and $1", &env).unwrap(), "$5 and $1");
+        assert!(interpolate("${MISSING}", &env).is_err());
+        assert!(interpolate("${BUCKET", &env).is_err());
+    }
+
+    #[test]
+    fn test_apply_env() {
+        let secret = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(secret.path(), "secret-key\n").unwrap();
+        let mut config = serde_json::json!({
+            "playlists": {"main": {"name": "${NAME}", "child": {"Silent": {}}}},
+            "file_provider": {
+                "my-s3": {"AwsS3": {"bucket": "music", "region": "us-west-2"}}
+            },
+            "outputs": []
+        });
+        let env = vars(&[
+            ("NAME", "Radio"),
+            ("RUSTCAST_PROVIDER_MY_S3_REGION", "eu-west-1"),
+            (
+                "RUSTCAST_PROVIDER_MY_S3_SECRET_ACCESS_KEY_FILE",
+                secret.path().to_str().unwrap(),
+            ),
+            ("RUSTCAST_PROVIDER_GCS_TYPE", "GoogleCloudStorage"),
+            ("RUSTCAST_PROVIDER_GCS_BUCKET", "archive"),
+        ]);
+        apply_env(&mut config, &env).unwrap();
+
+        assert_eq!(config["playlists"]["main"]["name"], "Radio");
+        assert_eq!(
+            config["file_provider"]["my-s3"]["AwsS3"],
+            serde_json::json!({
+                "bucket": "music",
+                "region": "eu-west-1",
+                "secret_access_key": "secret-key"
+            })
+        );
+        assert_eq!(
+            config["file_provider"]["gcs"],
+            serde_json::json!({"GoogleCloudStorage": {"bucket": "archive"}})
+        );
+
+        let env = vars(&[("RUSTCAST_PROVIDER_MY_S3_BUCKETT", "music")]);
+        assert!(apply_env(&mut config, &env).is_err());
+        let env = vars(&[("RUSTCAST_PROVIDER_OTHER_BUCKET", "music")]);
+        assert!(apply_env(&mut config, &env).is_err());
+    }
+}
@@ -1,7 +1,17 @@
 use serde::Deserialize;
 
 #[derive(
-    strum::Display, strum::EnumIter, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug,
+    strum::Display,
+    strum::EnumIter,
+    Deserialize,
+    schemars::JsonSchema,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Debug,
 )]
 #[serde(rename_all = "snake_case")]
 pub enum AwsS3ConfigKeys {
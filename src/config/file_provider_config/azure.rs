@@ -0,0 +1,60 @@
+use object_store::azure::AzureConfigKey;
+use serde::Deserialize;
+
+#[derive(
+    strum::Display,
+    strum::EnumIter,
+    Deserialize,
+    schemars::JsonSchema,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Debug,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum AzureBlobStorageConfigKeys {
+    AccountName,
+    AccessKey,
+    ContainerName,
+    ClientId,
+    ClientSecret,
+    AuthorityId,
+    AuthorityHost,
+    SasKey,
+    Token,
+    UseEmulator,
+    Endpoint,
+    MsiEndpoint,
+    ObjectId,
+    MsiResourceId,
+    FederatedTokenFile,
+    UseAzureCli,
+    SkipSignature,
+}
+
+impl From<AzureBlobStorageConfigKeys> for AzureConfigKey {
+    fn from(value: AzureBlobStorageConfigKeys) -> Self {
+        match value {
+            AzureBlobStorageConfigKeys::AccountName => Self::AccountName,
+            AzureBlobStorageConfigKeys::AccessKey => Self::AccessKey,
+            AzureBlobStorageConfigKeys::ContainerName => Self::ContainerName,
+            AzureBlobStorageConfigKeys::ClientId => Self::ClientId,
+            AzureBlobStorageConfigKeys::ClientSecret => Self::ClientSecret,
+            AzureBlobStorageConfigKeys::AuthorityId => Self::AuthorityId,
+            AzureBlobStorageConfigKeys::AuthorityHost => Self::AuthorityHost,
+            AzureBlobStorageConfigKeys::SasKey => Self::SasKey,
+            AzureBlobStorageConfigKeys::Token => Self::Token,
+            AzureBlobStorageConfigKeys::UseEmulator => Self::UseEmulator,
+            AzureBlobStorageConfigKeys::Endpoint => Self::Endpoint,
+            AzureBlobStorageConfigKeys::MsiEndpoint => Self::MsiEndpoint,
+            AzureBlobStorageConfigKeys::ObjectId => Self::ObjectId,
+            AzureBlobStorageConfigKeys::MsiResourceId => Self::MsiResourceId,
+            AzureBlobStorageConfigKeys::FederatedTokenFile => Self::FederatedTokenFile,
+            AzureBlobStorageConfigKeys::UseAzureCli => Self::UseAzureCli,
+            AzureBlobStorageConfigKeys::SkipSignature => Self::SkipSignature,
+        }
+    }
+}
@@ -2,7 +2,17 @@ use object_store::gcp::GoogleConfigKey;
 use serde::Deserialize;
 
 #[derive(
-    strum::Display, strum::EnumIter, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug,
+    strum::Display,
+    strum::EnumIter,
+    Deserialize,
+    schemars::JsonSchema,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Debug,
 )]
 #[serde(rename_all = "snake_case")]
 pub enum GoogleCloudStorageConfigKeys {
@@ -0,0 +1,16 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, schemars::JsonSchema, Clone, PartialEq)]
+pub struct HttpConfig {
+    /// the base URL of the files, the paths of the tracks are relative to it
+    pub url: String,
+    /// URL of a JSON or M3U manifest listing the files, relative to `url`.
+    /// The autoindex pages of the folders are parsed if None
+    #[serde(default)]
+    pub manifest: Option<String>,
+    /// headers sent with every request, e.g. `Authorization`
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
@@ -1,16 +1,30 @@
 pub use aws::AwsS3ConfigKeys;
+pub use azure::AzureBlobStorageConfigKeys;
 pub use gcp::GoogleCloudStorageConfigKeys;
+pub use http::HttpConfig;
 use serde::Deserialize;
 use serde_json::Value;
 use std::collections::BTreeMap;
+pub use webdav::WebDavConfig;
 
 mod aws;
+mod azure;
 mod gcp;
+mod http;
+mod webdav;
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, schemars::JsonSchema, Clone, PartialEq)]
 pub enum FileProviderConfig {
+    /// the keys of an Amazon S3 bucket
     AwsS3(BTreeMap<AwsS3ConfigKeys, Value>),
+    /// the keys of a Google Cloud Storage bucket
     GoogleCloudStorage(BTreeMap<GoogleCloudStorageConfigKeys, Value>),
+    /// the keys of an Azure Blob Storage container
+    AzureBlobStorage(BTreeMap<AzureBlobStorageConfigKeys, Value>),
+    /// files served over HTTP, listed from autoindex pages or a manifest
+    Http(HttpConfig),
+    /// a WebDAV share
+    WebDav(WebDavConfig),
 }
 
 impl FileProviderConfig {
@@ -105,6 +119,80 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_from_json_azure_blob_storage() {
+        let json = r#"{
+            "AzureBlobStorage": {
+                "account_name": "devstoreaccount1",
+                "container_name": "music",
+                "use_emulator": true
+            }
+        }"#;
+
+        let config = FileProviderConfig::from_json(json).unwrap();
+        if let FileProviderConfig::AzureBlobStorage(config) = config {
+            assert_eq!(
+                config.get(&AzureBlobStorageConfigKeys::AccountName),
+                Some(&serde_json::Value::String("devstoreaccount1".to_string()))
+            );
+            assert_eq!(
+                config.get(&AzureBlobStorageConfigKeys::ContainerName),
+                Some(&serde_json::Value::String("music".to_string()))
+            );
+            assert_eq!(
+                config
+                    .get(&AzureBlobStorageConfigKeys::UseEmulator)
+                    .and_then(|v| v.as_bool()),
+                Some(true)
+            );
+        } else {
+            panic!("Expected AzureBlobStorage variant");
+        }
+    }
+
+    #[test]
+    fn test_from_json_http() {
+        let json = r#"{
+            "Http": {
+                "url": "https://music.example.com/library/",
+                "headers": {"Authorization": "Bearer token"}
+            }
+        }"#;
+
+        let config = FileProviderConfig::from_json(json).unwrap();
+        if let FileProviderConfig::Http(config) = config {
+            assert_eq!(config.url, "https://music.example.com/library/");
+            assert_eq!(config.manifest, None);
+            assert_eq!(
+                config.headers.get("Authorization").map(String::as_str),
+                Some("Bearer token")
+            );
+        } else {
+            panic!("Expected Http variant");
+        }
+    }
+
+    #[test]
+    fn test_from_json_webdav() {
+        let json = r#"{
+            "WebDav": {
+                "url": "https://cloud.example.com/remote.php/dav/files/user/",
+                "username": "user",
+                "password": "app-password"
+            }
+        }"#;
+
+        let config = FileProviderConfig::from_json(json).unwrap();
+        assert_eq!(
+            config,
+            FileProviderConfig::WebDav(WebDavConfig {
+                url: "https://cloud.example.com/remote.php/dav/files/user/".to_string(),
+                username: Some("user".to_string()),
+                password: Some("app-password".to_string()),
+            })
+        );
+    }
+
     #[test]
     fn test_from_json_invalid() {
         let json = r#"{"InvalidType":null}"#;
@@ -0,0 +1,12 @@
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, schemars::JsonSchema, Clone, PartialEq)]
+pub struct WebDavConfig {
+    /// URL of the root folder of the share, the paths of the tracks are relative to it
+    pub url: String,
+    /// username of the basic or digest authentication, the requests are anonymous if None
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+}
@@ -0,0 +1,27 @@
+fn default_segment_duration() -> f64 {
+    6.0
+}
+
+fn default_segments() -> usize {
+    6
+}
+
+/// Configuration of the segments of an `Hls` output.
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema, Clone, PartialEq)]
+pub struct HlsConfig {
+    /// target duration of a segment in seconds, default to `6`
+    #[serde(default = "default_segment_duration")]
+    pub segment_duration: f64,
+    /// number of segments listed in the media playlist, default to `6`
+    #[serde(default = "default_segments")]
+    pub segments: usize,
+}
+
+impl Default for HlsConfig {
+    fn default() -> Self {
+        Self {
+            segment_duration: default_segment_duration(),
+            segments: default_segments(),
+        }
+    }
+}
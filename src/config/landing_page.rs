@@ -0,0 +1,27 @@
+fn default_landing_page_enabled() -> bool {
+    true
+}
+
+fn default_landing_page_title() -> String {
+    "RustCast".to_string()
+}
+
+/// Configuration of the page listing the mounts of a server at `/`.
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema, Clone, PartialEq, Eq)]
+pub struct LandingPageConfig {
+    /// whether the page is served, default to `true`
+    #[serde(default = "default_landing_page_enabled")]
+    pub enabled: bool,
+    /// title of the page, default to `RustCast`
+    #[serde(default = "default_landing_page_title")]
+    pub title: String,
+}
+
+impl Default for LandingPageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_landing_page_enabled(),
+            title: default_landing_page_title(),
+        }
+    }
+}
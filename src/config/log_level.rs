@@ -1,6 +1,6 @@
 use clap::ValueEnum;
 
-#[derive(Debug, Clone, Copy, serde::Deserialize, ValueEnum, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, ValueEnum, Default)]
 pub enum LogLevel {
     /// A level lower than all log levels, intended to disable logging.
     #[serde(alias = "off")]
@@ -36,6 +36,23 @@ impl From<LogLevel> for log::LevelFilter {
     }
 }
 
+/// The schema lists the lowercase aliases with the names of the variants.
+impl schemars::JsonSchema for LogLevel {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "LogLevel".into()
+    }
+
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "description": "Log level of the application, default is `info`.",
+            "enum": [
+                "Off", "off", "Error", "error", "Warn", "warn",
+                "Info", "info", "Debug", "debug", "Trace", "trace"
+            ]
+        })
+    }
+}
+
 #[cfg(not(debug_assertions))]
 pub async fn set_log_output(level: Option<LogLevel>, output: &Vec<String>) -> anyhow::Result<()> {
     let level = level.unwrap_or_default();
@@ -1,57 +1,127 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
+mod admin_config;
+mod cache_config;
 mod clap_args;
+mod config_format;
+mod env;
 mod file_provider_config;
+mod hls_config;
+mod landing_page;
 mod log_level;
+mod output_auth;
+mod output_type;
 mod playlist_config;
+mod shutdown_config;
 
-pub use clap_args::ClapArgs;
-pub use file_provider_config::FileProviderConfig;
-use log_level::LogLevel;
-pub use playlist_config::{PlaylistChildConfig, PlaylistConfig};
+pub use admin_config::AdminConfig;
+pub use cache_config::CacheConfig;
+pub use clap_args::{ClapArgs, Command};
+pub use config_format::ConfigFormat;
+pub use file_provider_config::{FileProviderConfig, HttpConfig, WebDavConfig};
+pub use hls_config::HlsConfig;
+pub use landing_page::LandingPageConfig;
+pub use log_level::LogLevel;
+pub use output_auth::OutputAuthConfig;
+pub use output_type::OutputType;
+pub use playlist_config::{FailOverConfig, LiveSourceConfig, PlaylistChildConfig, PlaylistConfig};
+pub use shutdown_config::ShutdownConfig;
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct GlobalConfig {
+    /// the playlists by id, referenced by the `playlist` of the outputs
     pub playlists: HashMap<String, PlaylistConfig>,
+    /// the file providers by name, referenced by the `remote_client` of the playlists
     #[serde(default)]
     pub file_provider: HashMap<String, FileProviderConfig>,
     pub outputs: Vec<ShoutCastOutput>,
     #[serde(default)]
     pub log_level: Option<LogLevel>,
+    /// log files, `stdout` logs to the terminal
     #[serde(default)]
     pub log_file: Vec<String>,
+    /// the folder of the files downloaded by the file providers
     #[serde(default)]
     pub cache_dir: Option<Arc<String>>,
+    /// the size and the age limits of the cache folder
+    #[serde(default)]
+    pub cache: CacheConfig,
+    /// the admin listener serving `/metrics` and the admin API, disabled if not set
+    #[serde(default)]
+    pub admin: Option<AdminConfig>,
+    /// the page listing the mounts of each server at `/`
+    #[serde(default)]
+    pub landing_page: LandingPageConfig,
+    /// the deadline and the goodbye clip of the graceful shutdown
+    #[serde(default)]
+    pub shutdown: ShutdownConfig,
 }
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct ShoutCastOutput {
     pub host: String,
     pub port: u16,
+    /// the path of the mount on the server
     pub path: String,
+    /// the id of the playlist streamed by the mount
     pub playlist: String,
-    // TODO: Add authentication
+    #[serde(default)]
+    pub output_type: OutputType,
+    /// access control of the listeners, every listener is accepted if not set
+    #[serde(default)]
+    pub auth: Option<OutputAuthConfig>,
+    /// the segments of an `Hls` output, ignored by the other output types
+    #[serde(default)]
+    pub hls: HlsConfig,
 }
 
 impl GlobalConfig {
+    #[cfg(test)]
     fn from_json(json: &str) -> anyhow::Result<Self> {
-        let config: GlobalConfig = serde_json::from_str(json)?;
-        Ok(config)
+        Self::from_str(json, ConfigFormat::Json)
+    }
+
+    fn from_str(content: &str, format: ConfigFormat) -> anyhow::Result<Self> {
+        let mut config = format.parse_value(content)?;
+        env::apply_env(&mut config, &std::env::vars().collect())?;
+        match serde_json::from_value(config) {
+            Ok(config) => Ok(config),
+            Err(e) => {
+                // the positions are lost in the JSON value, the error is
+                //     located in the document unless the environment caused it
+                format.deserialize::<GlobalConfig>(content)?;
+                Err(anyhow::anyhow!(
+                    "{e}, after applying the environment variables"
+                ))
+            }
+        }
     }
 
-    async fn from_path(path: &str) -> anyhow::Result<Self> {
-        let json = tokio::fs::read_to_string(path).await?;
-        Self::from_json(&json)
+    /// Read the configuration file, its format is `format` or guessed from its extension.
+    pub async fn from_path(path: &str, format: Option<ConfigFormat>) -> anyhow::Result<Self> {
+        let content = tokio::fs::read_to_string(path).await?;
+        let format = format.unwrap_or_else(|| ConfigFormat::from_path(path));
+        Self::from_str(&content, format).map_err(|e| anyhow::anyhow!("{path}: {e}"))
+    }
+
+    /// Get the JSON Schema of the configuration file.
+    pub fn json_schema() -> schemars::Schema {
+        let mut schema = schemars::schema_for!(GlobalConfig);
+        schema.insert("title".to_string(), "RustCast configuration".into());
+        schema
     }
 
     pub async fn from_clap_args(clap_args: ClapArgs) -> anyhow::Result<Self> {
         // Initialize logging before parsing the log level from the configuration file
         let ClapArgs {
             config,
+            format,
             log_level,
             log_file,
+            ..
         } = clap_args;
-        let mut config = GlobalConfig::from_path(&config).await?;
+        let config = config.ok_or_else(|| anyhow::anyhow!("no configuration file given"))?;
+        let mut config = GlobalConfig::from_path(&config, format).await?;
         if let Some(log_level) = log_level {
             config.log_level = Some(log_level);
         }
@@ -65,6 +135,14 @@ impl GlobalConfig {
         Ok(config)
     }
 }
+
+/// Convert a number of seconds of the configuration,
+///     the numbers out of the range of a `Duration` are rejected.
+pub fn duration_from_secs(seconds: f64) -> anyhow::Result<Duration> {
+    Duration::try_from_secs_f64(seconds)
+        .map_err(|e| anyhow::anyhow!("invalid duration of {seconds:e} seconds: {e}"))
+}
+
 #[cfg(test)]
 mod tests {
     use file_provider_config::AwsS3ConfigKeys;
@@ -192,6 +270,233 @@ mod tests {
         assert_eq!(config.outputs[0].port, 8000);
         assert_eq!(config.outputs[0].path, "/stream");
         assert_eq!(config.outputs[0].playlist, "main");
+        assert_eq!(config.outputs[0].output_type, OutputType::ShoutCast);
+        assert_eq!(config.admin, None);
+        assert_eq!(config.landing_page, LandingPageConfig::default());
+        assert_eq!(config.shutdown, ShutdownConfig::default());
+    }
+
+    #[test]
+    fn test_from_yaml_and_toml() {
+        let yaml = r#"
+# comments are allowed
+playlists:
+  main:
+    name: Test Playlist
+    child:
+      LocalFolder:
+        folder: /path/to/folder
+        fail_over: Silent
+outputs:
+  - host: 127.0.0.1
+    port: 8000
+    path: /stream
+    playlist: main
+"#;
+        let toml = r#"
+# comments are allowed
+[playlists.main]
+name = "Test Playlist"
+
+[playlists.main.child.LocalFolder]
+folder = "/path/to/folder"
+fail_over = "Silent"
+
+[[outputs]]
+host = "127.0.0.1"
+port = 8000
+path = "/stream"
+playlist = "main"
+"#;
+        for config in [
+            GlobalConfig::from_str(yaml, ConfigFormat::Yaml).unwrap(),
+            GlobalConfig::from_str(toml, ConfigFormat::Toml).unwrap(),
+        ] {
+            assert_eq!(config.playlists["main"].name, "Test Playlist");
+            assert_eq!(config.outputs.len(), 1);
+            assert_eq!(config.outputs[0].port, 8000);
+        }
+    }
+
+    #[test]
+    fn test_error_position() {
+        let yaml = "playlists: {}\noutputs:\n  - host: 127.0.0.1\n    port: not-a-port\n";
+        let e = GlobalConfig::from_str(yaml, ConfigFormat::Yaml).unwrap_err();
+        assert!(e.to_string().contains("line 4"), "{e}");
+
+        let toml = "outputs = []\n[playlists.main]\nname = 1\n";
+        let e = GlobalConfig::from_str(toml, ConfigFormat::Toml).unwrap_err();
+        assert!(e.to_string().contains("line 3"), "{e}");
+
+        let json = "{\n  \"playlists\": {},\n  \"outputs\": [}\n";
+        let e = GlobalConfig::from_str(json, ConfigFormat::Json).unwrap_err();
+        assert!(e.to_string().contains("line 3"), "{e}");
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(ConfigFormat::from_path("config.yaml"), ConfigFormat::Yaml);
+        assert_eq!(
+            ConfigFormat::from_path("/etc/rustcast.YML"),
+            ConfigFormat::Yaml
+        );
+        assert_eq!(ConfigFormat::from_path("config.toml"), ConfigFormat::Toml);
+        assert_eq!(ConfigFormat::from_path("config.json"), ConfigFormat::Json);
+        assert_eq!(ConfigFormat::from_path("config"), ConfigFormat::Json);
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let schema = serde_json::to_value(GlobalConfig::json_schema()).unwrap();
+        assert_eq!(
+            schema["required"],
+            serde_json::json!(["playlists", "outputs"])
+        );
+
+        let defs = &schema["$defs"];
+        let children = defs["PlaylistChildConfig"]["oneOf"].as_array().unwrap();
+        assert!(children.iter().any(|c| c["const"] == "Silent"));
+        let remote_folder = children
+            .iter()
+            .find_map(|c| c["properties"].get("RemoteFolder"))
+            .unwrap();
+        assert_eq!(
+            remote_folder["required"],
+            serde_json::json!(["folder", "remote_client"])
+        );
+
+        let providers = defs["FileProviderConfig"]["oneOf"].as_array().unwrap();
+        let aws = providers
+            .iter()
+            .find_map(|p| p["properties"].get("AwsS3"))
+            .unwrap();
+        assert_eq!(aws["properties"]["bucket"], true);
+        assert_eq!(aws["additionalProperties"], false);
+        assert!(
+            defs["LogLevel"]["enum"]
+                .as_array()
+                .unwrap()
+                .contains(&"info".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_from_json_icecast_output() {
+        let json = r#"
+        {
+            "playlists": {},
+            "outputs": [
+                {
+                    "host": "127.0.0.1",
+                    "port": 8000,
+                    "path": "/stream",
+                    "playlist": "main",
+                    "output_type": "Icecast"
+                },
+                {
+                    "host": "127.0.0.1",
+                    "port": 8000,
+                    "path": "/legacy",
+                    "playlist": "main",
+                    "output_type": "shoutcast"
+                },
+                {
+                    "host": "127.0.0.1",
+                    "port": 8000,
+                    "path": "/hls",
+                    "playlist": "main",
+                    "output_type": "Hls",
+                    "hls": { "segment_duration": 4 }
+                }
+            ]
+        }
+        "#;
+
+        let config = GlobalConfig::from_json(json).unwrap();
+        assert_eq!(config.outputs[0].output_type, OutputType::Icecast);
+        assert_eq!(config.outputs[1].output_type, OutputType::ShoutCast);
+        assert_eq!(config.outputs[1].hls, HlsConfig::default());
+        assert_eq!(config.outputs[2].output_type, OutputType::Hls);
+        assert_eq!(config.outputs[2].hls.segment_duration, 4.0);
+        assert_eq!(config.outputs[2].hls.segments, 6);
+    }
+
+    #[tokio::test]
+    async fn test_from_json_output_auth() {
+        let json = r#"
+        {
+            "playlists": {},
+            "outputs": [
+                {
+                    "host": "127.0.0.1",
+                    "port": 8000,
+                    "path": "/stream",
+                    "playlist": "main",
+                    "auth": {
+                        "users": { "alice": "secret" },
+                        "tokens": ["abc"],
+                        "allow": ["10.0.0.0/8", "192.168.1.2"],
+                        "deny": ["10.0.0.1"]
+                    }
+                }
+            ]
+        }
+        "#;
+
+        let config = GlobalConfig::from_json(json).unwrap();
+        let auth = config.outputs[0].auth.clone().unwrap();
+        assert_eq!(auth.users["alice"], "secret");
+        assert_eq!(auth.htpasswd, None);
+        assert_eq!(auth.tokens, vec!["abc".to_string()]);
+        assert_eq!(
+            auth.allow,
+            vec![
+                "10.0.0.0/8".parse().unwrap(),
+                "192.168.1.2/32".parse().unwrap()
+            ]
+        );
+        assert_eq!(auth.deny, vec!["10.0.0.1/32".parse().unwrap()]);
+
+        let json = r#"
+        {
+            "playlists": {},
+            "outputs": [
+                {
+                    "host": "127.0.0.1",
+                    "port": 8000,
+                    "path": "/stream",
+                    "playlist": "main",
+                    "auth": { "allow": ["not a network"] }
+                }
+            ]
+        }
+        "#;
+        assert!(GlobalConfig::from_json(json).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_from_json_admin() {
+        let json = r#"
+        {
+            "playlists": {},
+            "outputs": [],
+            "admin": {
+                "port": 9000,
+                "password": "hackme"
+            }
+        }
+        "#;
+
+        let config = GlobalConfig::from_json(json).unwrap();
+        assert_eq!(
+            config.admin,
+            Some(AdminConfig {
+                host: "127.0.0.1".to_string(),
+                port: 9000,
+                user: "admin".to_string(),
+                password: Some("hackme".to_string()),
+            })
+        );
     }
 
     #[tokio::test]
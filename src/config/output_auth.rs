@@ -0,0 +1,46 @@
+use std::{collections::HashMap, net::IpAddr};
+
+use ipnet::IpNet;
+use serde::Deserialize;
+
+/// Access control of the listeners of an output.
+/// The IP lists are checked first,
+/// then a listener must give a valid token or user if any is configured.
+#[derive(Debug, Deserialize, schemars::JsonSchema, Clone, PartialEq, Eq, Default)]
+pub struct OutputAuthConfig {
+    /// user names and passwords of the listeners, checked with HTTP Basic authentication
+    #[serde(default)]
+    pub users: HashMap<String, String>,
+    /// path of an htpasswd file with more users,
+    /// with bcrypt, SHA1, MD5 (apr1) or plain text passwords
+    #[serde(default)]
+    pub htpasswd: Option<String>,
+    /// tokens accepted in the `token` query parameter
+    #[serde(default)]
+    pub tokens: Vec<String>,
+    /// networks of the listeners allowed to connect, every listener is allowed if empty
+    #[serde(default, deserialize_with = "deserialize_networks")]
+    #[schemars(with = "Vec<String>")]
+    pub allow: Vec<IpNet>,
+    /// networks of the listeners denied, checked before `allow`
+    #[serde(default, deserialize_with = "deserialize_networks")]
+    #[schemars(with = "Vec<String>")]
+    pub deny: Vec<IpNet>,
+}
+
+/// Deserialize a list of networks in the CIDR notation, a single IP address is also accepted.
+fn deserialize_networks<'de, D>(deserializer: D) -> Result<Vec<IpNet>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let networks = Vec::<String>::deserialize(deserializer)?;
+    networks
+        .iter()
+        .map(|network| {
+            network
+                .parse::<IpNet>()
+                .or_else(|_| network.parse::<IpAddr>().map(IpNet::from))
+                .map_err(|_| serde::de::Error::custom(format!("invalid network: {network}")))
+        })
+        .collect()
+}
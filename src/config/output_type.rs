@@ -0,0 +1,28 @@
+/// The protocol flavour used to serve an output.
+#[derive(Debug, Clone, Copy, serde::Deserialize, PartialEq, Eq, Default)]
+pub enum OutputType {
+    /// Shoutcast v1 (ICY) compatible output.
+    #[serde(alias = "shoutcast")]
+    #[default]
+    ShoutCast,
+    /// Icecast2 compatible output, with HTTP/1.1 responses and `/status-json.xsl`.
+    #[serde(alias = "icecast")]
+    Icecast,
+    /// HTTP Live Streaming output, with a rolling media playlist of in memory segments.
+    #[serde(alias = "hls", alias = "HLS")]
+    Hls,
+}
+
+/// The schema lists the aliases with the names of the variants.
+impl schemars::JsonSchema for OutputType {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "OutputType".into()
+    }
+
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "description": "The protocol flavour used to serve an output, default is `ShoutCast`.",
+            "enum": ["ShoutCast", "shoutcast", "Icecast", "icecast", "Hls", "hls", "HLS"]
+        })
+    }
+}
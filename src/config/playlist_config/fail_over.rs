@@ -0,0 +1,17 @@
+/// When the `fail_over` child of a playlist child takes over,
+///     the settings are flattened into the playlist child.
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema, Clone, PartialEq, Default)]
+pub struct FailOverConfig {
+    /// number of consecutive errors of the child before switching to the fail over child,
+    ///     default to `3`
+    #[serde(default)]
+    pub fail_over_max_errors: Option<usize>,
+    /// seconds to wait for a frame of the child before switching to the fail over child,
+    ///     default to `15`
+    #[serde(default)]
+    pub fail_over_timeout: Option<f64>,
+    /// seconds between two tries of the child while the fail over child is playing,
+    ///     default to `30`
+    #[serde(default)]
+    pub fail_over_retry_interval: Option<f64>,
+}
@@ -0,0 +1,22 @@
+fn default_live_source_user() -> String {
+    "source".to_string()
+}
+
+/// Configuration of the live source of a playlist.
+/// A connected live source takes over the playlist,
+/// until it disconnects.
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema, Clone, PartialEq, Eq)]
+pub struct LiveSourceConfig {
+    /// user name of the source client, default to `source`
+    #[serde(default = "default_live_source_user")]
+    pub user: String,
+    /// password of the source client
+    pub password: String,
+    /// host of the Shoutcast v1 source listener, default to `0.0.0.0`
+    #[serde(default)]
+    pub shoutcast_v1_host: Option<String>,
+    /// port of the Shoutcast v1 source listener,
+    /// the listener is only started if the port is set
+    #[serde(default)]
+    pub shoutcast_v1_port: Option<u16>,
+}
@@ -1,11 +1,17 @@
+pub use fail_over::FailOverConfig;
+pub use live_source::LiveSourceConfig;
 pub use playlist_child::PlaylistChildConfig;
 
+mod fail_over;
+mod live_source;
 mod playlist_child;
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema, Clone, PartialEq)]
 pub struct PlaylistConfig {
     pub child: PlaylistChildConfig,
     pub name: String,
+    #[serde(default)]
+    pub live: Option<LiveSourceConfig>,
 }
 
 #[cfg(test)]
@@ -43,6 +49,25 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_from_json_live_source() {
+        let json = r#"{
+            "child": "Silent",
+            "name": "Test Playlist",
+            "live": {
+                "password": "hackme",
+                "shoutcast_v1_port": 8001
+            }
+        }"#;
+
+        let config = PlaylistConfig::from_json(json).await.unwrap();
+        let live = config.live.unwrap();
+        assert_eq!(live.user, "source");
+        assert_eq!(live.password, "hackme");
+        assert_eq!(live.shoutcast_v1_host, None);
+        assert_eq!(live.shoutcast_v1_port, Some(8001));
+    }
+
     #[tokio::test]
     async fn test_from_json_invalid() {
         let json = r#"{
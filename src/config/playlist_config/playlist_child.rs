@@ -1,8 +1,12 @@
 use std::sync::Arc;
 
-#[derive(Debug, serde::Deserialize, Clone, PartialEq, Eq)]
+use super::FailOverConfig;
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema, Clone, PartialEq)]
 pub enum PlaylistChildConfig {
+    /// silent frames, useful as the last fail over
     Silent,
+    /// the audio files of a local folder
     LocalFolder {
         folder: Arc<String>,
         #[serde(default)]
@@ -13,7 +17,10 @@ pub enum PlaylistChildConfig {
         recursive: Option<bool>,
         #[serde(default)]
         fail_over: Option<Arc<PlaylistChildConfig>>,
+        #[serde(flatten)]
+        fail_over_settings: FailOverConfig,
     },
+    /// a list of local audio files
     LocalFiles {
         files: Arc<Vec<Arc<String>>>,
         #[serde(default)]
@@ -22,7 +29,10 @@ pub enum PlaylistChildConfig {
         shuffle: Option<bool>,
         #[serde(default)]
         fail_over: Option<Arc<PlaylistChildConfig>>,
+        #[serde(flatten)]
+        fail_over_settings: FailOverConfig,
     },
+    /// the audio files of a folder of the file provider `remote_client`
     RemoteFolder {
         folder: Arc<String>,
         remote_client: Arc<String>,
@@ -32,9 +42,15 @@ pub enum PlaylistChildConfig {
         shuffle: Option<bool>,
         #[serde(default)]
         recursive: Option<bool>,
+        /// the number of upcoming tracks downloaded in the background, default to 2
+        #[serde(default)]
+        prefetch: Option<usize>,
         #[serde(default)]
         fail_over: Option<Arc<PlaylistChildConfig>>,
+        #[serde(flatten)]
+        fail_over_settings: FailOverConfig,
     },
+    /// a list of audio files of the file provider `remote_client`
     RemoteFiles {
         files: Arc<Vec<Arc<String>>>,
         remote_client: String,
@@ -42,9 +58,15 @@ pub enum PlaylistChildConfig {
         repeat: Option<bool>,
         #[serde(default)]
         shuffle: Option<bool>,
+        /// the number of upcoming tracks downloaded in the background, default to 2
+        #[serde(default)]
+        prefetch: Option<usize>,
         #[serde(default)]
         fail_over: Option<Arc<PlaylistChildConfig>>,
+        #[serde(flatten)]
+        fail_over_settings: FailOverConfig,
     },
+    /// the children played one after the other
     Playlists {
         children: Arc<Vec<Arc<PlaylistChildConfig>>>,
         #[serde(default)]
@@ -53,6 +75,8 @@ pub enum PlaylistChildConfig {
         shuffle: Option<bool>,
         #[serde(default)]
         fail_over: Option<Arc<PlaylistChildConfig>>,
+        #[serde(flatten)]
+        fail_over_settings: FailOverConfig,
     },
 }
 #[cfg(test)]
@@ -84,6 +108,22 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_playlist_child_fail_over_settings() {
+        let json = r#"{"LocalFolder":{"folder":"/app/music","fail_over":"Silent","fail_over_max_errors":5,"fail_over_timeout":2.5}}"#;
+        let config = PlaylistChildConfig::from_json(json).await.unwrap();
+        match config {
+            PlaylistChildConfig::LocalFolder {
+                fail_over_settings, ..
+            } => {
+                assert_eq!(fail_over_settings.fail_over_max_errors, Some(5));
+                assert_eq!(fail_over_settings.fail_over_timeout, Some(2.5));
+                assert_eq!(fail_over_settings.fail_over_retry_interval, None);
+            }
+            _ => panic!("Expected LocalFolder variant"),
+        }
+    }
+
     #[tokio::test]
     async fn test_playlist_child_fail_over_folder() {
         let json = r#"{"LocalFolder":{"folder":"/app/music","fail_over":{"LocalFolder":{"folder":"/app/music","fail_over":"Silent"}}}}"#;
@@ -96,6 +136,7 @@ mod tests {
                     shuffle: None,
                     recursive: None,
                     fail_over: Some(Arc::new(PlaylistChildConfig::Silent)),
+                    fail_over_settings: FailOverConfig::default(),
                 };
                 assert_eq!(*fail_over.unwrap(), target)
             }
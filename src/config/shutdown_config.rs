@@ -0,0 +1,34 @@
+use std::time::Duration;
+
+use super::duration_from_secs;
+
+fn default_shutdown_timeout() -> f64 {
+    10.0
+}
+
+/// Configuration of the graceful shutdown on `SIGINT` and `SIGTERM`.
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema, Clone, PartialEq)]
+pub struct ShutdownConfig {
+    /// seconds given to the listeners and the downloads to finish, default to `10`
+    #[serde(default = "default_shutdown_timeout")]
+    pub timeout: f64,
+    /// path of a local audio file played to the listeners before they are disconnected
+    #[serde(default)]
+    pub goodbye: Option<String>,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            timeout: default_shutdown_timeout(),
+            goodbye: None,
+        }
+    }
+}
+
+impl ShutdownConfig {
+    /// the time given to the listeners and the downloads, a negative timeout is zero
+    pub fn timeout(&self) -> anyhow::Result<Duration> {
+        duration_from_secs(self.timeout.max(0.0))
+    }
+}
@@ -1,17 +1,40 @@
+use once_cell::sync::OnceCell;
 use tokio::sync::Mutex;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+use crate::playlist::FrameWithMeta;
 
 struct ContextInner {
     id: usize,
 }
 
-pub struct Context(Mutex<ContextInner>);
+pub struct Context {
+    inner: Mutex<ContextInner>,
+    /// the time the server started
+    pub start_time: chrono::DateTime<chrono::Local>,
+    /// cancelled when the server shuts down, the servers stop accepting connections
+    ///     and the listeners are disconnected after the goodbye clip
+    pub shutdown: CancellationToken,
+    /// cancelled when the shutdown deadline is reached, the downloads in flight are aborted
+    pub abort: CancellationToken,
+    /// the connections and the downloads the shutdown waits for
+    pub tasks: TaskTracker,
+    /// the frames of the clip played to the listeners on shutdown
+    pub goodbye: OnceCell<Vec<FrameWithMeta>>,
+}
 
-pub static CONTEXT: once_cell::sync::Lazy<Context> =
-    once_cell::sync::Lazy::new(|| Context(Mutex::new(ContextInner { id: 1 })));
+pub static CONTEXT: once_cell::sync::Lazy<Context> = once_cell::sync::Lazy::new(|| Context {
+    inner: Mutex::new(ContextInner { id: 1 }),
+    start_time: chrono::Local::now(),
+    shutdown: CancellationToken::new(),
+    abort: CancellationToken::new(),
+    tasks: TaskTracker::new(),
+    goodbye: OnceCell::new(),
+});
 
 impl Context {
     pub async fn get_id(&self) -> usize {
-        let mut inner = self.0.lock().await;
+        let mut inner = self.inner.lock().await;
         let id = inner.id;
         inner.id += 1;
         id
@@ -3,7 +3,10 @@ use std::{collections::HashMap, sync::Arc};
 use anyhow::Ok;
 use object_store::aws::AmazonS3Builder;
 
-use super::{FileProvider, aws::AwsS3FileProvider, gcp::GoogleCouldStorageFileProvider};
+use super::{
+    FileProvider, aws::AwsS3FileProvider, azure::AzureBlobStorageFileProvider,
+    gcp::GoogleCouldStorageFileProvider, http::HttpFileProvider, webdav::WebDavFileProvider,
+};
 
 // TODO add cache dir functionality
 
@@ -42,6 +45,21 @@ pub async fn build_file_provider(
 
                 Arc::new(GoogleCouldStorageFileProvider::new(cache_dir.clone(), gcp_builder).await?)
             }
+            crate::config::FileProviderConfig::AzureBlobStorage(keys) => {
+                let mut azure_builder = object_store::azure::MicrosoftAzureBuilder::new();
+                for (key, value) in keys {
+                    azure_builder =
+                        azure_builder.with_config(key.into(), serde_json_value_to_string(value)?)
+                }
+
+                Arc::new(AzureBlobStorageFileProvider::new(cache_dir.clone(), azure_builder).await?)
+            }
+            crate::config::FileProviderConfig::Http(config) => {
+                Arc::new(HttpFileProvider::new(cache_dir.clone(), config).await?)
+            }
+            crate::config::FileProviderConfig::WebDav(config) => {
+                Arc::new(WebDavFileProvider::new(cache_dir.clone(), config).await?)
+            }
         };
         res.insert(name, provider);
     }
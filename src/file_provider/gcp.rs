@@ -1,13 +1,14 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{ops::Range, path::PathBuf, sync::Arc};
 
 use crate::cache;
 use async_trait::async_trait;
+use bytes::Bytes;
 use cache::GcpDownloader;
 use futures::{Stream, StreamExt};
 use log::debug;
 use object_store::ObjectStore;
 
-use super::FileProvider;
+use super::{FileProvider, listing::list_with_snapshot};
 
 pub struct GoogleCouldStorageFileProvider {
     cache: Arc<cache::Cache>,
@@ -33,6 +34,39 @@ impl GoogleCouldStorageFileProvider {
             object_store,
         })
     }
+
+    /// List the files of a folder on the remote.
+    async fn list_remote_files<'s, 'p>(
+        &'s self,
+        path: Option<&'p str>,
+        recursive: bool,
+    ) -> anyhow::Result<std::pin::Pin<Box<dyn Stream<Item = anyhow::Result<String>> + Send + 'p>>>
+    where
+        's: 'p,
+    {
+        debug!("list files in {:?}", path);
+        let p = match path {
+            Some(p) => Some(object_store::path::Path::parse(p)?),
+            None => None,
+        };
+        let s = if recursive {
+            // the list method is recursive
+            self.object_store.list(p.as_ref())
+        } else {
+            // the list_with_delimiter method is not recursive
+            let s = self
+                .object_store
+                .list_with_delimiter(p.as_ref())
+                .await?
+                .objects;
+            let s = futures::stream::iter(s).map(Ok);
+            Box::pin(s)
+        };
+
+        let s = s.map(|m| m.map(|r| r.location.to_string()).map_err(|e| e.into()));
+
+        Ok(Box::pin(s))
+    }
 }
 
 #[async_trait]
@@ -47,6 +81,30 @@ impl FileProvider for GoogleCouldStorageFileProvider {
         }
     }
 
+    /// Get a file from the file provider, served while it is downloaded to the cache.
+    async fn get_file(
+        &self,
+        path: &str,
+    ) -> anyhow::Result<Option<Box<dyn tokio::io::AsyncRead + Send + Sync + Unpin>>> {
+        match self.cache.open(path).await {
+            Ok(file) => Ok(Some(file)),
+            Err(cache::Error::ResourceNotFound(_)) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn read_range(&self, path: &str, range: Range<u64>) -> anyhow::Result<Option<Bytes>> {
+        match self.cache.read_range(path, range).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(cache::Error::ResourceNotFound(_)) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn prefetch(&self, path: &str) {
+        self.cache.prefetch(path);
+    }
+
     async fn get_meta(&self, path: &str) -> anyhow::Result<Option<cache::FileMetadata>> {
         let meta = match self.cache.get_file_meta(path).await {
             Ok(m) => m,
@@ -56,6 +114,7 @@ impl FileProvider for GoogleCouldStorageFileProvider {
         Ok(Some(meta))
     }
 
+    /// List the files of a folder, the last complete listing is used if the remote fails.
     async fn list_files<'s, 'p>(
         &'s self,
         path: Option<&'p str>,
@@ -64,27 +123,11 @@ impl FileProvider for GoogleCouldStorageFileProvider {
     where
         's: 'p,
     {
-        debug!("list files in {:?}", path);
-        let p = match path {
-            Some(p) => Some(object_store::path::Path::parse(p)?),
-            None => None,
-        };
-        let s = if recursive {
-            // the list method is recursive
-            self.object_store.list(p.as_ref())
-        } else {
-            // the list_with_delimiter method is not recursive
-            let s = self
-                .object_store
-                .list_with_delimiter(p.as_ref())
-                .await?
-                .objects;
-            let s = futures::stream::iter(s).map(Ok);
-            Box::pin(s)
-        };
-
-        let s = s.map(|m| m.map(|r| r.location.to_string()).map_err(|e| e.into()));
+        let listing = self.list_remote_files(path, recursive).await;
+        list_with_snapshot(&self.cache, path, recursive, listing).await
+    }
 
-        Ok(Box::pin(s))
+    fn cache_metrics(&self) -> Option<Arc<crate::metrics::CacheMetrics>> {
+        Some(self.cache.metrics())
     }
 }
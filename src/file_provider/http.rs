@@ -0,0 +1,340 @@
+use std::{collections::HashSet, ops::Range, path::PathBuf, sync::Arc};
+
+use crate::{cache, config::HttpConfig};
+use async_stream::stream;
+use async_trait::async_trait;
+use bytes::Bytes;
+use cache::{HttpDownloader, file_url, folder_url, relative_path};
+use futures::Stream;
+use log::debug;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use url::Url;
+
+use super::{FileProvider, listing::list_with_snapshot};
+
+/// Fetches the files relative to a base URL.
+///
+/// The files are listed from a JSON or M3U manifest if set,
+///     otherwise from the autoindex HTML pages of the folders.
+pub struct HttpFileProvider {
+    cache: Arc<cache::Cache>,
+    client: reqwest::Client,
+    base: Url,
+    manifest: Option<Url>,
+}
+
+impl HttpFileProvider {
+    pub async fn new(cache_dir: Option<Arc<String>>, config: HttpConfig) -> anyhow::Result<Self> {
+        let base = folder_url(&config.url)?;
+        let manifest = match &config.manifest {
+            Some(manifest) => Some(base.join(manifest)?),
+            None => None,
+        };
+        let mut headers = HeaderMap::with_capacity(config.headers.len());
+        for (name, value) in config.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| anyhow::anyhow!("invalid header name {name}: {e}"))?;
+            let value = HeaderValue::from_str(&value)
+                .map_err(|e| anyhow::anyhow!("invalid value of header {name}: {e}"))?;
+            headers.insert(name, value);
+        }
+
+        let http_downloader = HttpDownloader::new(base.clone(), headers)?;
+        let client = http_downloader.get_client();
+        let mut cache = cache::Cache::builder().file_downloader(Box::new(http_downloader));
+        if let Some(dir) = cache_dir {
+            let path = PathBuf::from(dir.as_str());
+            cache = cache.dir(path);
+        };
+
+        let cache = cache.build().await?;
+        Ok(Self {
+            cache: cache.into(),
+            client,
+            base,
+            manifest,
+        })
+    }
+
+    async fn get_text(&self, url: Url) -> anyhow::Result<String> {
+        debug!("GET {url}");
+        let text = self
+            .client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        Ok(text)
+    }
+
+    /// List the files of the manifest in the folder `path`.
+    async fn list_manifest(
+        &self,
+        manifest: &Url,
+        path: &str,
+        recursive: bool,
+    ) -> anyhow::Result<Vec<String>> {
+        let text = self.get_text(manifest.clone()).await?;
+        let mut files = Vec::new();
+        for entry in parse_manifest(&text)? {
+            // the entries are relative to the manifest
+            let Some(file) = manifest
+                .join(&entry)
+                .ok()
+                .and_then(|url| relative_path(&self.base, &url))
+            else {
+                debug!("{entry} of the manifest is not under {}", self.base);
+                continue;
+            };
+            let name = if path.is_empty() {
+                Some(file.as_str())
+            } else {
+                file.strip_prefix(path).and_then(|f| f.strip_prefix('/'))
+            };
+            if name.is_some_and(|name| recursive || !name.contains('/')) {
+                files.push(file);
+            }
+        }
+        Ok(files)
+    }
+
+    /// List the files of a folder on the remote.
+    async fn list_remote_files<'s, 'p>(
+        &'s self,
+        path: Option<&'p str>,
+        recursive: bool,
+    ) -> anyhow::Result<std::pin::Pin<Box<dyn Stream<Item = anyhow::Result<String>> + Send + 'p>>>
+    where
+        's: 'p,
+    {
+        debug!("list files in {:?}", path);
+        let path = path.unwrap_or_default().trim_matches('/');
+        if let Some(manifest) = &self.manifest {
+            let files = self.list_manifest(manifest, path, recursive).await?;
+            return Ok(Box::pin(futures::stream::iter(files.into_iter().map(Ok))));
+        }
+
+        let folder = if path.is_empty() {
+            self.base.clone()
+        } else {
+            file_url(&self.base, &format!("{path}/"))?
+        };
+        let s = stream! {
+            let mut visited = HashSet::new();
+            let mut folders = vec![folder];
+            while let Some(folder) = folders.pop() {
+                if !visited.insert(folder.clone()) {
+                    continue;
+                }
+                let html = match self.get_text(folder.clone()).await {
+                    Ok(html) => html,
+                    Err(e) => {
+                        yield Err(e);
+                        continue;
+                    }
+                };
+                let mut files = HashSet::new();
+                for link in parse_links(&html) {
+                    // the sorting links, the anchors and the parent folder are skipped
+                    if link.starts_with('?') || link.starts_with('#') {
+                        continue;
+                    }
+                    let Ok(mut url) = folder.join(&link) else {
+                        continue;
+                    };
+                    url.set_query(None);
+                    url.set_fragment(None);
+                    if relative_path(&folder, &url).is_none() {
+                        continue;
+                    }
+                    if url.path().ends_with('/') {
+                        if recursive {
+                            folders.push(url);
+                        }
+                    } else if let Some(file) = relative_path(&self.base, &url)
+                        && files.insert(file.clone())
+                    {
+                        yield Ok(file);
+                    }
+                }
+            }
+        };
+
+        Ok(Box::pin(s))
+    }
+}
+
+/// Read the entries of a manifest, either a JSON array of paths, a JSON object
+///     with the paths in a `files` array, or an M3U playlist.
+fn parse_manifest(text: &str) -> anyhow::Result<Vec<String>> {
+    let trimmed = text.trim_start();
+    if trimmed.starts_with('[') || trimmed.starts_with('{') {
+        let value: serde_json::Value = serde_json::from_str(trimmed)?;
+        let files = match &value {
+            serde_json::Value::Array(files) => files,
+            serde_json::Value::Object(o) => match o.get("files") {
+                Some(serde_json::Value::Array(files)) => files,
+                _ => return Err(anyhow::anyhow!("the manifest has no `files` array")),
+            },
+            _ => unreachable!(),
+        };
+        return files
+            .iter()
+            .map(|f| match f {
+                serde_json::Value::String(f) => Ok(f.clone()),
+                _ => Err(anyhow::anyhow!("invalid entry in the manifest: {f}")),
+            })
+            .collect();
+    }
+
+    // M3U, the lines starting with `#` are comments or directives
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Get the targets of the links of an HTML page.
+fn parse_links(html: &str) -> Vec<String> {
+    let lower = html.to_ascii_lowercase();
+    let mut links = Vec::new();
+    let mut rest = 0;
+    while let Some(i) = lower[rest..].find("href") {
+        let mut start = rest + i + "href".len();
+        rest = start;
+        let value = html[start..].trim_start();
+        let Some(value) = value.strip_prefix('=') else {
+            continue;
+        };
+        let value = value.trim_start();
+        start = html.len() - value.len();
+        let (link, len) = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => match value[1..].find(quote) {
+                Some(end) => (&value[1..end + 1], end + 2),
+                None => break,
+            },
+            _ => {
+                let end = value
+                    .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                    .unwrap_or(value.len());
+                (&value[..end], end)
+            }
+        };
+        rest = start + len;
+        links.push(link.replace("&amp;", "&"));
+    }
+    links
+}
+
+#[async_trait]
+impl FileProvider for HttpFileProvider {
+    /// Get a file local cache path from the file provider.
+    /// return the local cache path if the file exists, otherwise None.
+    async fn get_local_cache_path(&self, path: &str) -> anyhow::Result<Option<PathBuf>> {
+        match self.cache.cached_path(path).await {
+            Ok(p) => Ok(Some(p)),
+            Err(cache::Error::ResourceNotFound(_)) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Get a file from the file provider, served while it is downloaded to the cache.
+    async fn get_file(
+        &self,
+        path: &str,
+    ) -> anyhow::Result<Option<Box<dyn tokio::io::AsyncRead + Send + Sync + Unpin>>> {
+        match self.cache.open(path).await {
+            Ok(file) => Ok(Some(file)),
+            Err(cache::Error::ResourceNotFound(_)) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn read_range(&self, path: &str, range: Range<u64>) -> anyhow::Result<Option<Bytes>> {
+        match self.cache.read_range(path, range).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(cache::Error::ResourceNotFound(_)) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn prefetch(&self, path: &str) {
+        self.cache.prefetch(path);
+    }
+
+    async fn get_meta(&self, path: &str) -> anyhow::Result<Option<cache::FileMetadata>> {
+        let meta = match self.cache.get_file_meta(path).await {
+            Ok(m) => m,
+            Err(cache::Error::ResourceNotFound(_)) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Some(meta))
+    }
+
+    /// List the files of a folder, the last complete listing is used if the remote fails.
+    async fn list_files<'s, 'p>(
+        &'s self,
+        path: Option<&'p str>,
+        recursive: bool,
+    ) -> anyhow::Result<std::pin::Pin<Box<dyn Stream<Item = anyhow::Result<String>> + Send + 'p>>>
+    where
+        's: 'p,
+    {
+        let listing = self.list_remote_files(path, recursive).await;
+        list_with_snapshot(&self.cache, path, recursive, listing).await
+    }
+
+    fn cache_metrics(&self) -> Option<Arc<crate::metrics::CacheMetrics>> {
+        Some(self.cache.metrics())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_links() {
+        let html = r#"<html><body><h1>Index of /music/</h1>
+            <a href="?C=N;O=D">Name</a>
+            <a href="../">Parent Directory</a>
+            <A HREF="Song%20One.mp3">Song One.mp3</A>
+            <a href = 'album/'>album/</a>
+            <a href=track.flac>track.flac</a>
+            <a href="a.mp3?x=1&amp;y=2">a.mp3</a>
+            </body></html>"#;
+        assert_eq!(
+            parse_links(html),
+            vec![
+                "?C=N;O=D",
+                "../",
+                "Song%20One.mp3",
+                "album/",
+                "track.flac",
+                "a.mp3?x=1&y=2",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_manifest() {
+        assert_eq!(
+            parse_manifest(r#"["a.mp3", "album/b.mp3"]"#).unwrap(),
+            vec!["a.mp3", "album/b.mp3"]
+        );
+        assert_eq!(
+            parse_manifest(r#"{"files": ["a.mp3"]}"#).unwrap(),
+            vec!["a.mp3"]
+        );
+        assert!(parse_manifest(r#"{"tracks": ["a.mp3"]}"#).is_err());
+        assert_eq!(
+            parse_manifest("#EXTM3U\n#EXTINF:123,Artist - Title\na.mp3\n\nhttp://host/b.mp3\r\n")
+                .unwrap(),
+            vec!["a.mp3", "http://host/b.mp3"]
+        );
+    }
+}
@@ -0,0 +1,119 @@
+use std::{collections::HashSet, pin::Pin};
+
+use async_stream::stream;
+use futures::{Stream, StreamExt};
+use log::warn;
+
+use crate::cache::Cache;
+
+/// The files of a folder, listed one by one.
+type FileStream<'p> = Pin<Box<dyn Stream<Item = anyhow::Result<String>> + Send + 'p>>;
+
+/// List the files of a folder with `listing`, the listing of the remote.
+///
+/// Each complete listing is saved in the cache. If the remote fails, the files of the last
+///     complete listing are listed instead, so the folder is played through the outages.
+pub(crate) async fn list_with_snapshot<'p>(
+    cache: &'p Cache,
+    folder: Option<&'p str>,
+    recursive: bool,
+    listing: anyhow::Result<FileStream<'p>>,
+) -> anyhow::Result<FileStream<'p>> {
+    let folder = folder.unwrap_or_default();
+    let mut listing = match listing {
+        Ok(listing) => listing,
+        Err(e) => match cache.listing(folder, recursive).await {
+            Some(files) => {
+                warn!("failed to list {folder:?}, using its last listing: {e:#}");
+                return Ok(Box::pin(futures::stream::iter(files.into_iter().map(Ok))));
+            }
+            None => return Err(e),
+        },
+    };
+
+    let s = stream! {
+        let mut files = Vec::new();
+        let mut complete = true;
+        while let Some(file) = listing.next().await {
+            match file {
+                Ok(file) => {
+                    files.push(file.clone());
+                    yield Ok(file);
+                }
+                Err(e) => {
+                    complete = false;
+                    let Some(snapshot) = cache.listing(folder, recursive).await else {
+                        yield Err(e);
+                        continue;
+                    };
+                    warn!("failed to list {folder:?}, using its last listing: {e:#}");
+                    // the files already listed are not listed twice
+                    let listed: HashSet<_> = files.drain(..).collect();
+                    for file in snapshot.into_iter().filter(|file| !listed.contains(file)) {
+                        yield Ok(file);
+                    }
+                    break;
+                }
+            }
+        }
+        if complete && let Err(e) = cache.save_listing(folder, recursive, &files).await {
+            warn!("failed to save the listing of {folder:?}: {e}");
+        }
+    };
+
+    Ok(Box::pin(s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listing(files: Vec<anyhow::Result<&'static str>>) -> anyhow::Result<FileStream<'static>> {
+        let files = files.into_iter().map(|file| file.map(str::to_string));
+        Ok(Box::pin(futures::stream::iter(files)))
+    }
+
+    fn unreachable<T>() -> anyhow::Result<T> {
+        Err(anyhow::anyhow!("unreachable"))
+    }
+
+    async fn collect(listing: anyhow::Result<FileStream<'_>>) -> Vec<String> {
+        listing
+            .unwrap()
+            .map(|file| file.unwrap_or_else(|e| e.to_string()))
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_list_with_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::builder()
+            .dir(dir.path().to_path_buf())
+            .build()
+            .await
+            .unwrap();
+
+        // never listed
+        let res = list_with_snapshot(&cache, Some("music"), false, unreachable()).await;
+        assert!(res.is_err());
+        let failed = listing(vec![Ok("music/a.mp3"), unreachable()]);
+        let files = collect(list_with_snapshot(&cache, Some("music"), false, failed).await).await;
+        assert_eq!(files, vec!["music/a.mp3", "unreachable"]);
+
+        // the last complete listing is used if the remote fails
+        let complete = listing(vec![Ok("music/a.mp3"), Ok("music/b.mp3")]);
+        let files = collect(list_with_snapshot(&cache, Some("music"), false, complete).await).await;
+        assert_eq!(files, vec!["music/a.mp3", "music/b.mp3"]);
+        let res = list_with_snapshot(&cache, Some("music"), false, unreachable()).await;
+        assert_eq!(collect(res).await, vec!["music/a.mp3", "music/b.mp3"]);
+        let failed = listing(vec![Ok("music/b.mp3"), unreachable()]);
+        let files = collect(list_with_snapshot(&cache, Some("music"), false, failed).await).await;
+        assert_eq!(files, vec!["music/b.mp3", "music/a.mp3"]);
+        assert!(
+            list_with_snapshot(&cache, Some("music"), true, unreachable())
+                .await
+                .is_err()
+        );
+    }
+}
@@ -1,15 +1,22 @@
-use std::path::PathBuf;
+use std::{ops::Range, path::PathBuf};
 
 use async_trait::async_trait;
+use bytes::Bytes;
 use futures::Stream;
 
 mod aws;
+mod azure;
 mod from_config;
 mod gcp;
+mod http;
+mod listing;
 mod local;
+mod range_reader;
+mod webdav;
 
 pub use from_config::build_file_provider;
 pub use local::LocalFileProvider;
+pub use range_reader::RangeReader;
 
 #[async_trait]
 /// A trait for file providers.
@@ -39,6 +46,21 @@ pub trait FileProvider: Send + Sync {
         Ok(Some(Box::new(file)))
     }
 
+    /// Read the bytes `range` of a file without fetching the whole file,
+    ///     fewer bytes if the file ends before the end of the range.
+    /// Returns None if the file does not exist.
+    async fn read_range(&self, path: &str, range: Range<u64>) -> anyhow::Result<Option<Bytes>> {
+        let path = match self.get_local_cache_path(path).await? {
+            Some(p) => p,
+            None => return Ok(None),
+        };
+        Ok(Some(crate::cache::read_file_range(&path, range).await?))
+    }
+
+    /// Start fetching a file in the background, before it is read.
+    /// Does nothing for the file providers without a local cache.
+    fn prefetch(&self, _path: &str) {}
+
     /// Get meta of a file from the file provider.
     /// Returns the meta if the file exists, otherwise None.
     async fn get_meta(&self, path: &str) -> anyhow::Result<Option<crate::cache::FileMetadata>>;
@@ -52,4 +74,10 @@ pub trait FileProvider: Send + Sync {
     ) -> anyhow::Result<std::pin::Pin<Box<dyn Stream<Item = anyhow::Result<String>> + Send + 'p>>>
     where
         's: 'p;
+
+    /// Get the metrics of the local cache of the file provider.
+    /// Returns None if the file provider does not have a local cache.
+    fn cache_metrics(&self) -> Option<std::sync::Arc<crate::metrics::CacheMetrics>> {
+        None
+    }
 }
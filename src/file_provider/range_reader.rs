@@ -0,0 +1,181 @@
+use std::{
+    collections::BTreeMap,
+    io::SeekFrom,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, ready},
+};
+
+use bytes::Bytes;
+use futures::{FutureExt, future::BoxFuture};
+use tokio::io::{AsyncRead, AsyncSeek, ReadBuf};
+
+use super::FileProvider;
+
+/// size of the blocks fetched at a new position
+const MIN_BLOCK_SIZE: u64 = 65536;
+
+/// the size of the blocks doubles while the file is read sequentially, up to this size
+const MAX_BLOCK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// A fetch of the block starting at an offset.
+type PendingBlock = (u64, BoxFuture<'static, anyhow::Result<Option<Bytes>>>);
+
+/// Reads a file of a file provider with ranged reads, only the parts read are fetched.
+///
+/// The blocks fetched are kept, reading them again fetches nothing.
+pub struct RangeReader {
+    file_provider: Arc<dyn FileProvider>,
+    path: Arc<String>,
+    size: u64,
+    pos: u64,
+    /// the blocks fetched, by offset
+    blocks: BTreeMap<u64, Bytes>,
+    block_size: u64,
+    pending: Option<PendingBlock>,
+}
+
+impl RangeReader {
+    /// `size` is the size of the file in bytes.
+    pub fn new(file_provider: Arc<dyn FileProvider>, path: Arc<String>, size: u64) -> Self {
+        Self {
+            file_provider,
+            path,
+            size,
+            pos: 0,
+            blocks: BTreeMap::new(),
+            block_size: MIN_BLOCK_SIZE,
+            pending: None,
+        }
+    }
+
+    /// Get the number of bytes fetched.
+    pub fn fetched(&self) -> u64 {
+        self.blocks.values().map(|b| b.len() as u64).sum()
+    }
+
+    /// Get the bytes fetched from `pos` to the end of its block.
+    fn block(&self, pos: u64) -> Option<&[u8]> {
+        let (start, block) = self.blocks.range(..=pos).next_back()?;
+        let offset = (pos - start) as usize;
+        (offset < block.len()).then(|| &block[offset..])
+    }
+
+    /// Start the fetch of the block at the current position.
+    fn fetch(&mut self) -> PendingBlock {
+        self.block_size = if self.pos > 0 && self.block(self.pos - 1).is_some() {
+            (self.block_size * 2).min(MAX_BLOCK_SIZE)
+        } else {
+            MIN_BLOCK_SIZE
+        };
+        // up to the next block fetched
+        let end = self
+            .blocks
+            .range(self.pos..)
+            .next()
+            .map_or(self.size, |(start, _)| *start)
+            .min(self.pos + self.block_size);
+        let range = self.pos..end;
+        let (file_provider, path) = (self.file_provider.clone(), self.path.clone());
+        let fetch = async move { file_provider.read_range(&path, range).await };
+        (self.pos, fetch.boxed())
+    }
+}
+
+impl AsyncRead for RangeReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if this.pos >= this.size || buf.remaining() == 0 {
+                return Poll::Ready(Ok(()));
+            }
+            if let Some(block) = this.block(this.pos) {
+                let len = block.len().min(buf.remaining());
+                buf.put_slice(&block[..len]);
+                this.pos += len as u64;
+                return Poll::Ready(Ok(()));
+            }
+
+            if this.pending.is_none() {
+                this.pending = Some(this.fetch());
+            }
+            let (start, fetch) = this.pending.as_mut().unwrap();
+            let res = ready!(fetch.poll_unpin(cx));
+            let start = *start;
+            this.pending = None;
+            match res {
+                Ok(Some(bytes)) if !bytes.is_empty() => {
+                    this.blocks.insert(start, bytes);
+                }
+                // the file is shorter than its size
+                Ok(Some(_)) => {
+                    return Poll::Ready(Err(std::io::ErrorKind::UnexpectedEof.into()));
+                }
+                Ok(None) => return Poll::Ready(Err(std::io::ErrorKind::NotFound.into())),
+                Err(e) => return Poll::Ready(Err(std::io::Error::other(e))),
+            }
+        }
+    }
+}
+
+impl AsyncSeek for RangeReader {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> std::io::Result<()> {
+        let this = self.get_mut();
+        let pos = match position {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(offset) => this.size.checked_add_signed(offset),
+            SeekFrom::Current(offset) => this.pos.checked_add_signed(offset),
+        };
+        this.pos = pos.ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "invalid seek to a negative position",
+            )
+        })?;
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<u64>> {
+        Poll::Ready(Ok(self.pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LocalFileProvider;
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+    #[tokio::test]
+    async fn test_range_reader() {
+        let data: Vec<u8> = (0..1_000_000u32).map(|i| i as u8).collect();
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), &data).unwrap();
+        let path = Arc::new(file.path().to_str().unwrap().to_string());
+        let mut reader =
+            RangeReader::new(Arc::new(LocalFileProvider::new()), path, data.len() as u64);
+
+        // the head and the tail only
+        let mut head = vec![0; 100_000];
+        reader.read_exact(&mut head).await.unwrap();
+        assert_eq!(head, data[..100_000]);
+        reader.seek(SeekFrom::End(-1000)).await.unwrap();
+        let mut tail = Vec::new();
+        reader.read_to_end(&mut tail).await.unwrap();
+        assert_eq!(tail, data[data.len() - 1000..]);
+        let fetched = reader.fetched();
+        assert!(fetched < 300_000, "{fetched} bytes fetched");
+
+        // the blocks fetched are read again
+        reader.seek(SeekFrom::Start(10)).await.unwrap();
+        let mut again = vec![0; 1000];
+        reader.read_exact(&mut again).await.unwrap();
+        assert_eq!(again, data[10..1010]);
+        assert_eq!(reader.fetched(), fetched);
+        assert!(reader.seek(SeekFrom::Current(-2000)).await.is_err());
+    }
+}
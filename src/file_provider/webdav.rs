@@ -0,0 +1,152 @@
+use std::{ops::Range, path::PathBuf, sync::Arc};
+
+use crate::{cache, config::WebDavConfig};
+use async_trait::async_trait;
+use bytes::Bytes;
+use cache::{WebDavClient, WebDavDownloader, folder_url};
+use futures::Stream;
+use log::{debug, info};
+use reqwest::StatusCode;
+
+use super::{FileProvider, listing::list_with_snapshot};
+
+pub struct WebDavFileProvider {
+    cache: Arc<cache::Cache>,
+    client: Arc<WebDavClient>,
+}
+
+impl WebDavFileProvider {
+    pub async fn new(cache_dir: Option<Arc<String>>, config: WebDavConfig) -> anyhow::Result<Self> {
+        let base = folder_url(&config.url)?;
+        let credentials = config
+            .username
+            .map(|username| (username, config.password.unwrap_or_default()));
+        let webdav_downloader = WebDavDownloader::new(base, credentials)?;
+        let client = webdav_downloader.get_client();
+        let mut cache = cache::Cache::builder().file_downloader(Box::new(webdav_downloader));
+        if let Some(dir) = cache_dir {
+            let path = PathBuf::from(dir.as_str());
+            cache = cache.dir(path);
+        };
+
+        let cache = cache.build().await?;
+        Ok(Self {
+            cache: cache.into(),
+            client,
+        })
+    }
+
+    /// List the files of the folder `path` and of its sub folders, one `PROPFIND` per folder.
+    async fn list_each_folder(&self, path: &str) -> anyhow::Result<Vec<String>> {
+        let mut files = Vec::new();
+        let mut folders = vec![path.to_string()];
+        while let Some(folder) = folders.pop() {
+            for entry in self.client.propfind(&format!("{folder}/"), "1").await? {
+                if entry.path == folder {
+                    continue;
+                }
+                if entry.collection {
+                    folders.push(entry.path);
+                } else {
+                    files.push(entry.path);
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    /// List the files of a folder on the remote.
+    async fn list_remote_files<'s, 'p>(
+        &'s self,
+        path: Option<&'p str>,
+        recursive: bool,
+    ) -> anyhow::Result<std::pin::Pin<Box<dyn Stream<Item = anyhow::Result<String>> + Send + 'p>>>
+    where
+        's: 'p,
+    {
+        debug!("list files in {:?}", path);
+        let path = path.unwrap_or_default().trim_matches('/');
+        let folder = format!("{path}/");
+        let depth = if recursive { "infinity" } else { "1" };
+        let files = match self.client.propfind(&folder, depth).await {
+            Ok(entries) => entries
+                .into_iter()
+                .filter(|entry| !entry.collection)
+                .map(|entry| entry.path)
+                .collect(),
+            // many servers refuse `Depth: infinity`, the folders are listed one by one
+            Err(cache::Error::Http(e))
+                if recursive && e.status() == Some(StatusCode::FORBIDDEN) =>
+            {
+                info!("Depth infinity refused by the server, listing {folder} folder by folder");
+                self.list_each_folder(path).await?
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Box::pin(futures::stream::iter(files.into_iter().map(Ok))))
+    }
+}
+
+#[async_trait]
+impl FileProvider for WebDavFileProvider {
+    /// Get a file local cache path from the file provider.
+    /// return the local cache path if the file exists, otherwise None.
+    async fn get_local_cache_path(&self, path: &str) -> anyhow::Result<Option<PathBuf>> {
+        match self.cache.cached_path(path).await {
+            Ok(p) => Ok(Some(p)),
+            Err(cache::Error::ResourceNotFound(_)) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Get a file from the file provider, served while it is downloaded to the cache.
+    async fn get_file(
+        &self,
+        path: &str,
+    ) -> anyhow::Result<Option<Box<dyn tokio::io::AsyncRead + Send + Sync + Unpin>>> {
+        match self.cache.open(path).await {
+            Ok(file) => Ok(Some(file)),
+            Err(cache::Error::ResourceNotFound(_)) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn read_range(&self, path: &str, range: Range<u64>) -> anyhow::Result<Option<Bytes>> {
+        match self.cache.read_range(path, range).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(cache::Error::ResourceNotFound(_)) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn prefetch(&self, path: &str) {
+        self.cache.prefetch(path);
+    }
+
+    async fn get_meta(&self, path: &str) -> anyhow::Result<Option<cache::FileMetadata>> {
+        let meta = match self.cache.get_file_meta(path).await {
+            Ok(m) => m,
+            Err(cache::Error::ResourceNotFound(_)) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Some(meta))
+    }
+
+    /// List the files of a folder, the last complete listing is used if the remote fails.
+    async fn list_files<'s, 'p>(
+        &'s self,
+        path: Option<&'p str>,
+        recursive: bool,
+    ) -> anyhow::Result<std::pin::Pin<Box<dyn Stream<Item = anyhow::Result<String>> + Send + 'p>>>
+    where
+        's: 'p,
+    {
+        let listing = self.list_remote_files(path, recursive).await;
+        list_with_snapshot(&self.cache, path, recursive, listing).await
+    }
+
+    fn cache_metrics(&self) -> Option<Arc<crate::metrics::CacheMetrics>> {
+        Some(self.cache.metrics())
+    }
+}
@@ -1,18 +1,23 @@
-use std::{collections::HashMap, sync::Arc};
+use std::sync::Arc;
 
 use clap::Parser;
 
+mod admin;
+mod audio;
 mod cache;
+mod check;
 pub mod config;
 mod context;
 mod file_provider;
+mod metrics;
 mod playlist;
 mod shoutcast;
+mod shutdown;
+mod station;
 
-use config::ShoutCastOutput;
 pub use context::CONTEXT;
 pub use file_provider::*;
-use playlist::{Playlist, build_playlist_from_config};
+use station::Station;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -38,63 +43,47 @@ async fn main() -> anyhow::Result<()> {
             .unwrap();
     }
 
-    let config = config::ClapArgs::parse();
-    let config::GlobalConfig {
-        file_provider,
-        playlists,
-        outputs,
-        cache_dir,
-        ..
-    } = config::GlobalConfig::from_clap_args(config).await?;
-
-    let file_provider =
-        Arc::new(file_provider::build_file_provider(cache_dir, file_provider).await?);
-    let playlists = build_playlist_from_config(playlists, file_provider).await?;
-
-    let mut outputs_map = HashMap::new();
-    for shoutcast_config in outputs {
-        let ShoutCastOutput {
-            host,
-            port,
-            path,
-            playlist,
-        } = shoutcast_config;
-        let path = path.trim_matches('/').to_string();
-
-        let playlist = match playlists.get(&playlist) {
-            Some(playlist) => playlist.clone(),
-            None => {
-                log::error!("playlist not found: {}", playlist);
-                return Err(anyhow::anyhow!("playlist not found: {}", playlist));
-            }
-        };
-        let fut: Option<&mut HashMap<String, Arc<Playlist>>> =
-            outputs_map.get_mut(&(host.clone(), port));
-        if let Some(fut) = fut {
-            let res = fut.insert(path.clone(), playlist);
-            if res.is_some() {
-                let msg = format!(
-                    "You have configured the same URL path({}) twice on the same server({}:{}). This creates a conflict because the system doesn't know which configuration to use when a request comes in for that path.",
-                    path, host, port
-                );
-                log::error!("{}", msg);
-                return Err(anyhow::anyhow!("{}", msg));
-            }
-        } else {
-            let mut fut = HashMap::new();
-            fut.insert(path, playlist);
-            outputs_map.insert((host, port), fut);
+    let mut clap_args = config::ClapArgs::parse();
+    match clap_args.command.take() {
+        Some(config::Command::Check { config, format }) => {
+            std::process::exit(check::run(&config, format).await);
         }
+        Some(config::Command::Schema) => {
+            let schema = config::GlobalConfig::json_schema();
+            println!("{}", serde_json::to_string_pretty(&schema)?);
+            return Ok(());
+        }
+        None => {}
     }
+    let config_path = clap_args.config.clone().unwrap_or_default();
+    let config_format = clap_args.format;
+    let config = config::GlobalConfig::from_clap_args(clap_args).await?;
+    let admin = config.admin.clone();
+    let shutdown_config = config.shutdown.clone();
+    shutdown::load_goodbye(&shutdown_config).await?;
 
-    let mut output_fut = Vec::with_capacity(outputs_map.len());
+    let station = Station::start(config_path, config_format, config).await?;
 
-    for ((host, port), mut playlists) in outputs_map {
-        playlists.shrink_to_fit();
-        output_fut.push(shoutcast::listen(host, port, Arc::new(playlists)));
+    if let Some(config::AdminConfig {
+        host,
+        port,
+        user,
+        password,
+    }) = admin
+    {
+        let state = admin::AdminState {
+            station: station.clone(),
+            credentials: password.map(|password| (user, password)),
+        };
+        tokio::spawn(admin::listen(host, port, Arc::new(state)));
     }
 
-    futures::future::join_all(output_fut).await;
+    // the servers run in their own tasks, the configuration is reloaded on SIGHUP
+    tokio::select! {
+        res = station.reload_on_hangup() => res?,
+        res = shutdown::wait_for_signal() => res?,
+    }
+    shutdown::shutdown(&shutdown_config).await;
 
     Ok(())
 }
@@ -0,0 +1,221 @@
+use std::{
+    fmt::Write,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+/// upper bounds in seconds of the buckets of the download latency histogram
+const DOWNLOAD_SECONDS_BUCKETS: [f64; 9] = [0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+/// A histogram of durations with fixed buckets, in the Prometheus layout.
+pub struct Histogram {
+    bounds: &'static [f64],
+    /// non cumulative count of each bucket, the last one is `+Inf`
+    buckets: Vec<AtomicU64>,
+    /// sum of the observed durations in microseconds
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    pub fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            buckets: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+
+    pub fn observe(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        let i = self
+            .bounds
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(self.bounds.len());
+        self.buckets[i].fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+}
+
+/// Metrics of the cache of a file provider.
+pub struct CacheMetrics {
+    /// resources served from the cache without downloading
+    pub hits: AtomicU64,
+    /// resources downloaded to the cache
+    pub misses: AtomicU64,
+    /// bytes written to the cache by the downloads
+    pub download_bytes: AtomicU64,
+    /// duration of the downloads
+    pub download_seconds: Histogram,
+}
+
+impl Default for CacheMetrics {
+    fn default() -> Self {
+        Self {
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            download_bytes: AtomicU64::new(0),
+            download_seconds: Histogram::new(&DOWNLOAD_SECONDS_BUCKETS),
+        }
+    }
+}
+
+/// Metrics of a playlist producer.
+#[derive(Default)]
+pub struct PlaylistMetrics {
+    /// frames prepared by `Playlist::prepare_frame`
+    pub frames_prepared: AtomicU64,
+    /// times `prepare_frame` found the producer channel empty and waited for the producer
+    pub producer_stalls: AtomicU64,
+    /// time spent waiting for the producer in microseconds
+    pub producer_stall_micros: AtomicU64,
+    /// times the title or the artist of the prepared frames changed
+    pub track_changes: AtomicU64,
+}
+
+/// Metrics of a mount of an output.
+#[derive(Default)]
+pub struct MountMetrics {
+    /// bytes written to the listeners, the stream headers and the meta data included
+    pub bytes_sent: AtomicU64,
+    /// listeners currently connected to the mount
+    pub listeners: AtomicU64,
+}
+
+impl MountMetrics {
+    /// count a listener connected to the mount, until the guard is dropped
+    pub fn connect_listener(self: &Arc<Self>) -> MountListenerGuard {
+        self.listeners.fetch_add(1, Ordering::Relaxed);
+        MountListenerGuard {
+            metrics: self.clone(),
+        }
+    }
+}
+
+/// MountListenerGuard counts a listener of a mount, until it is dropped.
+pub struct MountListenerGuard {
+    metrics: Arc<MountMetrics>,
+}
+
+impl Drop for MountListenerGuard {
+    fn drop(&mut self) {
+        self.metrics.listeners.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Writer of the Prometheus text exposition format.
+#[derive(Default)]
+pub struct TextEncoder {
+    buf: String,
+}
+
+impl TextEncoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// write the `HELP` and `TYPE` lines of a metric family
+    pub fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.buf, "# HELP {name} {help}");
+        let _ = writeln!(self.buf, "# TYPE {name} {kind}");
+    }
+
+    /// write a sample of a metric family
+    pub fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.buf.push_str(name);
+        if !labels.is_empty() {
+            self.buf.push('{');
+            for (i, (key, value)) in labels.iter().enumerate() {
+                if i > 0 {
+                    self.buf.push(',');
+                }
+                let _ = write!(self.buf, "{key}=\"{}\"", escape_label_value(value));
+            }
+            self.buf.push('}');
+        }
+        let _ = writeln!(self.buf, " {value}");
+    }
+
+    /// write the buckets, the sum and the count of a histogram
+    pub fn histogram(&mut self, name: &str, labels: &[(&str, &str)], histogram: &Histogram) {
+        let mut count = 0;
+        for (i, bucket) in histogram.buckets.iter().enumerate() {
+            count += bucket.load(Ordering::Relaxed);
+            let le = match histogram.bounds.get(i) {
+                Some(bound) => bound.to_string(),
+                None => "+Inf".to_string(),
+            };
+            let mut bucket_labels = labels.to_vec();
+            bucket_labels.push(("le", &le));
+            self.sample(&format!("{name}_bucket"), &bucket_labels, count as f64);
+        }
+        let sum = histogram.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        self.sample(&format!("{name}_sum"), labels, sum);
+        self.sample(&format!("{name}_count"), labels, count as f64);
+    }
+
+    pub fn finish(self) -> String {
+        self.buf
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_encoder() {
+        let mut encoder = TextEncoder::new();
+        encoder.family("rustcast_listeners", "gauge", "Listeners connected");
+        encoder.sample("rustcast_listeners", &[("mount", "/a\"b\\c\n")], 3.0);
+        encoder.sample("rustcast_up", &[], 1.0);
+        assert_eq!(
+            encoder.finish(),
+            "# HELP rustcast_listeners Listeners connected\n\
+            # TYPE rustcast_listeners gauge\n\
+            rustcast_listeners{mount=\"/a\\\"b\\\\c\\n\"} 3\n\
+            rustcast_up 1\n"
+        );
+    }
+
+    #[test]
+    fn test_histogram() {
+        let histogram = Histogram::new(&[0.5, 1.0]);
+        histogram.observe(Duration::from_millis(100));
+        histogram.observe(Duration::from_millis(700));
+        histogram.observe(Duration::from_secs(3));
+
+        let mut encoder = TextEncoder::new();
+        encoder.histogram("d", &[("p", "s3")], &histogram);
+        assert_eq!(
+            encoder.finish(),
+            "d_bucket{p=\"s3\",le=\"0.5\"} 1\n\
+            d_bucket{p=\"s3\",le=\"1\"} 2\n\
+            d_bucket{p=\"s3\",le=\"+Inf\"} 3\n\
+            d_sum{p=\"s3\"} 3.8\n\
+            d_count{p=\"s3\"} 3\n"
+        );
+    }
+
+    #[test]
+    fn test_mount_listeners() {
+        let metrics = Arc::new(MountMetrics::default());
+        let a = metrics.connect_listener();
+        let b = metrics.connect_listener();
+        assert_eq!(metrics.listeners.load(Ordering::Relaxed), 2);
+        drop(a);
+        drop(b);
+        assert_eq!(metrics.listeners.load(Ordering::Relaxed), 0);
+    }
+}
@@ -1,116 +1,239 @@
-use std::{collections::HashMap, pin::Pin, sync::Arc};
+use std::{collections::HashMap, pin::Pin, sync::Arc, time::Duration};
 
 use async_stream::stream;
 use futures::Stream;
 
 use crate::{
     FileProvider, LocalFileProvider,
-    config::{PlaylistChildConfig, PlaylistConfig},
+    config::{FailOverConfig, LiveSourceConfig, PlaylistChildConfig, PlaylistConfig},
 };
 
-use super::{Playlist, PlaylistChild};
+use super::{FailOverSettings, LiveSource, Playlist, PlaylistChild};
 
-pub async fn build_playlist_from_config(
-    playlist: HashMap<String, PlaylistConfig>,
-    file_provider: Arc<HashMap<String, Arc<dyn FileProvider>>>,
-) -> anyhow::Result<HashMap<String, Arc<Playlist>>> {
-    let mut res = HashMap::new();
-    for (key, playlist) in playlist {
-        let PlaylistConfig { child, name } = playlist;
-        let child = build_playlist_child_from_config(child, file_provider.clone()).await?;
-        let playlist = Playlist::new(name, child).await;
-        res.insert(key, Arc::new(playlist));
-    }
+/// the number of upcoming remote tracks prefetched by default
+const DEFAULT_PREFETCH: usize = 2;
 
-    Ok(res)
+/// Build a playlist and start its producer.
+pub async fn build_playlist(
+    playlist: PlaylistConfig,
+    file_provider: Arc<HashMap<String, Arc<dyn FileProvider>>>,
+) -> anyhow::Result<Playlist> {
+    let PlaylistConfig { child, name, live } = playlist;
+    let child = build_playlist_child_from_config(child, file_provider).await?;
+    let live_source = live.map(|live| {
+        let LiveSourceConfig {
+            user,
+            password,
+            shoutcast_v1_host,
+            shoutcast_v1_port,
+        } = live;
+        let shoutcast_v1 = shoutcast_v1_port.map(|port| {
+            (
+                shoutcast_v1_host.unwrap_or_else(|| "0.0.0.0".to_string()),
+                port,
+            )
+        });
+        Arc::new(LiveSource::new(user, password, shoutcast_v1))
+    });
+    Ok(Playlist::new(name, child, live_source).await)
 }
 
-async fn build_playlist_child_from_config(
+pub async fn build_playlist_child_from_config(
     playlist: PlaylistChildConfig,
     file_provider: Arc<HashMap<String, Arc<dyn FileProvider>>>,
 ) -> anyhow::Result<Box<dyn PlaylistChild>> {
-    let child: Box<dyn PlaylistChild> = match playlist {
-        PlaylistChildConfig::Silent => Box::new(crate::playlist::Silent::new()?),
-         PlaylistChildConfig::LocalFolder {
-             folder,
-             repeat,
-             shuffle,
-             recursive,
-             // TODO add fail_over functionality
-             ..
-         } => {
-             let file_provider = Arc::new(LocalFileProvider::new());
-             Box::new(
-                 crate::playlist::LocalFolder::new(folder, repeat, shuffle, recursive, file_provider)?,
-             )
-         }
-
-         PlaylistChildConfig::LocalFiles { files, repeat, shuffle, ..
-             // TODO add fail_over functionality
-         } => {
-             let file_provider: Arc<dyn FileProvider> = Arc::new(LocalFileProvider::new());
-             Box::new(
-                 crate::playlist::LocalFileTrackList::new(files, repeat, shuffle, file_provider)?,
-             )
-         },
-         PlaylistChildConfig::RemoteFolder { folder, remote_client, repeat, shuffle, recursive, ..
-             // TODO add fail_over functionality
-         } => {
-             let file_provider = match file_provider.get(remote_client.as_str()){
-                 Some(provider) => provider.clone(),
-                 None => return Err(anyhow::anyhow!("No file provider found for {}", remote_client)),
-             };
-             Box::new(
-                 crate::playlist::LocalFolder::new(folder, repeat, shuffle,recursive, file_provider)?,
-             )
-         },
-         PlaylistChildConfig::RemoteFiles { files, remote_client, repeat, shuffle, ..
-        // TODO add fail_over functionality
-         } => {
-             let file_provider = match file_provider.get(&remote_client){
-                 Some(provider) => provider.clone(),
-                 None => return Err(anyhow::anyhow!("No file provider found for {}", remote_client)),
-             };
-             Box::new(
-                 crate::playlist::LocalFileTrackList::new(files, repeat, shuffle, file_provider)?,
-             )
-         },
-
-         PlaylistChildConfig::Playlists { children, repeat, shuffle, ..
-        // TODO add fail_over functionality
-         } => {
-             type ReturnStream = Pin<Box<dyn Stream<Item = anyhow::Result<Box<dyn PlaylistChild>>> + Send>>;
+    let (child, fail_over, fail_over_settings): (Box<dyn PlaylistChild>, _, _) = match playlist {
+        PlaylistChildConfig::Silent => (
+            Box::new(crate::playlist::Silent::new()?),
+            None,
+            FailOverConfig::default(),
+        ),
+        PlaylistChildConfig::LocalFolder {
+            folder,
+            repeat,
+            shuffle,
+            recursive,
+            fail_over,
+            fail_over_settings,
+        } => {
+            let file_provider = Arc::new(LocalFileProvider::new());
+            (
+                Box::new(crate::playlist::LocalFolder::new(
+                    folder,
+                    repeat,
+                    shuffle,
+                    recursive,
+                    None,
+                    file_provider,
+                )?),
+                fail_over,
+                fail_over_settings,
+            )
+        }
+        PlaylistChildConfig::LocalFiles {
+            files,
+            repeat,
+            shuffle,
+            fail_over,
+            fail_over_settings,
+        } => {
+            let file_provider: Arc<dyn FileProvider> = Arc::new(LocalFileProvider::new());
+            (
+                Box::new(crate::playlist::LocalFileTrackList::new(
+                    files,
+                    repeat,
+                    shuffle,
+                    0,
+                    file_provider,
+                )?),
+                fail_over,
+                fail_over_settings,
+            )
+        }
+        PlaylistChildConfig::RemoteFolder {
+            folder,
+            remote_client,
+            repeat,
+            shuffle,
+            recursive,
+            prefetch,
+            fail_over,
+            fail_over_settings,
+        } => {
+            let file_provider = match file_provider.get(remote_client.as_str()) {
+                Some(provider) => provider.clone(),
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "No file provider found for {}",
+                        remote_client
+                    ));
+                }
+            };
+            (
+                Box::new(crate::playlist::LocalFolder::new(
+                    folder,
+                    repeat,
+                    shuffle,
+                    recursive,
+                    Some(prefetch.unwrap_or(DEFAULT_PREFETCH)),
+                    file_provider,
+                )?),
+                fail_over,
+                fail_over_settings,
+            )
+        }
+        PlaylistChildConfig::RemoteFiles {
+            files,
+            remote_client,
+            repeat,
+            shuffle,
+            prefetch,
+            fail_over,
+            fail_over_settings,
+        } => {
+            let file_provider = match file_provider.get(&remote_client) {
+                Some(provider) => provider.clone(),
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "No file provider found for {}",
+                        remote_client
+                    ));
+                }
+            };
+            (
+                Box::new(crate::playlist::LocalFileTrackList::new(
+                    files,
+                    repeat,
+                    shuffle,
+                    prefetch.unwrap_or(DEFAULT_PREFETCH),
+                    file_provider,
+                )?),
+                fail_over,
+                fail_over_settings,
+            )
+        }
+        PlaylistChildConfig::Playlists {
+            children,
+            repeat,
+            shuffle,
+            fail_over,
+            fail_over_settings,
+        } => {
+            type ReturnStream =
+                Pin<Box<dyn Stream<Item = anyhow::Result<Box<dyn PlaylistChild>>> + Send>>;
 
-             fn init_fn(
-                 p: Arc<Vec<Arc<PlaylistChildConfig>>>,
-                 fp: Arc<HashMap<String, Arc<dyn FileProvider >>>,
-             ) -> Pin<
-                 Box<
-                     dyn Future<Output = anyhow::Result<ReturnStream>>
-                         + Send,
-                 >,
-             > {
-                 let s = stream! {
-                        for i in p.iter() {
-                            let c = build_playlist_child_from_config((**i).clone(), fp.clone()).await;
-                            yield c;
-                        }
-                 };
+            fn init_fn(
+                p: Arc<Vec<Arc<PlaylistChildConfig>>>,
+                fp: Arc<HashMap<String, Arc<dyn FileProvider>>>,
+            ) -> Pin<Box<dyn Future<Output = anyhow::Result<ReturnStream>> + Send>> {
+                let s = stream! {
+                    for i in p.iter() {
+                        let c = build_playlist_child_from_config((**i).clone(), fp.clone()).await;
+                        yield c;
+                    }
+                };
 
-                     let s: ReturnStream = Box::pin(s);
+                let s: ReturnStream = Box::pin(s);
 
-                         Box::pin(async { Ok(s) })
-             }
+                Box::pin(async { Ok(s) })
+            }
 
-             Box::new(
-                 crate::playlist::PlaylistChildList::<Vec<Arc<PlaylistChildConfig>>, Arc<HashMap<String, Arc<dyn FileProvider >>>>::
-                     new(children,
-                     repeat,
-                     shuffle, init_fn, file_provider,)?,
-             )
-         },
+            (
+                Box::new(crate::playlist::PlaylistChildList::<
+                    Vec<Arc<PlaylistChildConfig>>,
+                    Arc<HashMap<String, Arc<dyn FileProvider>>>,
+                >::new(
+                    children,
+                    0,
+                    repeat,
+                    shuffle,
+                    init_fn,
+                    file_provider.clone(),
+                )?),
+                fail_over,
+                fail_over_settings,
+            )
+        }
+    };
 
+    // wrap the child with the fail over child if there is one
+    let child: Box<dyn PlaylistChild> = match fail_over {
+        Some(fail_over) => {
+            let fail_over = Box::pin(build_playlist_child_from_config(
+                (*fail_over).clone(),
+                file_provider,
+            ))
+            .await?;
+            let settings = fail_over_settings_from_config(fail_over_settings)?;
+            Box::new(crate::playlist::FailOver::new(child, fail_over, settings)?)
+        }
+        None => child,
     };
 
     Ok(child)
 }
+
+/// Fill the fail over settings not set in the configuration with their defaults.
+fn fail_over_settings_from_config(config: FailOverConfig) -> anyhow::Result<FailOverSettings> {
+    let default = FailOverSettings::default();
+    let seconds = |name: &str, value: Option<f64>, default: Duration| match value {
+        Some(value) => Duration::try_from_secs_f64(value)
+            .map_err(|e| anyhow::anyhow!("invalid {name} {value}: {e}")),
+        None => Ok(default),
+    };
+    Ok(FailOverSettings {
+        max_consecutive_errors: config
+            .fail_over_max_errors
+            .unwrap_or(default.max_consecutive_errors),
+        primary_frame_timeout: seconds(
+            "fail_over_timeout",
+            config.fail_over_timeout,
+            default.primary_frame_timeout,
+        )?,
+        primary_retry_interval: seconds(
+            "fail_over_retry_interval",
+            config.fail_over_retry_interval,
+            default.primary_retry_interval,
+        )?,
+    })
+}
@@ -1,4 +1,10 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
 
 use moka::notification::ListenerFuture;
 use tokio::sync::Mutex;
@@ -14,6 +20,21 @@ pub struct ListenerFrameData {
     listener_id_2_frame: moka::future::Cache<usize, PreparedFrame>,
     listener_id_frame_group: moka::future::Cache<(usize, usize), ()>,
     current_frame: Arc<Mutex<PreparedFrame>>,
+    /// number of listeners currently connected
+    listeners: Arc<AtomicUsize>,
+    /// maximum number of listeners connected at the same time
+    listener_peak: Arc<AtomicUsize>,
+}
+
+/// ListenerGuard counts a connected listener, until it is dropped.
+pub struct ListenerGuard {
+    listeners: Arc<AtomicUsize>,
+}
+
+impl Drop for ListenerGuard {
+    fn drop(&mut self) {
+        self.listeners.fetch_sub(1, Ordering::Relaxed);
+    }
 }
 
 impl ListenerFrameData {
@@ -59,9 +80,30 @@ impl ListenerFrameData {
             listener_id_2_frame,
             listener_id_frame_group,
             current_frame,
+            listeners: Arc::new(AtomicUsize::new(0)),
+            listener_peak: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// count a new connected listener, the listener is counted until the guard is dropped
+    pub fn connect_listener(&self) -> ListenerGuard {
+        let listeners = self.listeners.fetch_add(1, Ordering::Relaxed) + 1;
+        self.listener_peak.fetch_max(listeners, Ordering::Relaxed);
+        ListenerGuard {
+            listeners: self.listeners.clone(),
         }
     }
 
+    /// get the number of listeners currently connected
+    pub fn get_listeners(&self) -> usize {
+        self.listeners.load(Ordering::Relaxed)
+    }
+
+    /// get the maximum number of listeners connected at the same time
+    pub fn get_listener_peak(&self) -> usize {
+        self.listener_peak.load(Ordering::Relaxed)
+    }
+
     pub async fn get_current_frame(&self) -> PreparedFrame {
         let current_frame = self.current_frame.lock().await;
         current_frame.clone()
@@ -3,15 +3,20 @@ mod listener_frame_data;
 mod playlist_child;
 mod playlist_frame_stream;
 mod playlist_struct;
+mod producer;
 
 // re-export the modules
-pub use from_config::build_playlist_from_config;
+pub use from_config::{build_playlist, build_playlist_child_from_config};
 pub use playlist_child::*;
 pub use playlist_frame_stream::PlaylistFrameStream;
 pub use playlist_struct::{Playlist, PreparedFrame};
+pub use producer::PlaylistControl;
 
 /// default_frame_size: 32768 bytes
 const DEFAULT_FRAME_SIZE: usize = 2097152;
 
+/// target duration of a chunk of audio frames in milliseconds
+const DEFAULT_CHUNK_DURATION: f64 = 1000.0;
+
 /// maximum write ahead duration in milliseconds for the playlist
 const MAX_WRITE_AHEAD_DURATION: u128 = 120000;
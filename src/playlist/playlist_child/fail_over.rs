@@ -0,0 +1,366 @@
+use std::{pin::Pin, time::Duration};
+
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use log::{info, warn};
+use tokio::{sync::Mutex, time::Instant};
+
+use super::{FrameWithMeta, PlaylistChild};
+
+/// When the fail over child takes over the primary child, and when the primary child is tried again.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FailOverSettings {
+    /// number of consecutive errors of the primary child before switching to the fail over child
+    pub max_consecutive_errors: usize,
+    /// maximum time to wait for a frame of the primary child before switching to the fail over child
+    pub primary_frame_timeout: Duration,
+    /// time to wait before trying the primary child again while playing the fail over child
+    pub primary_retry_interval: Duration,
+}
+
+impl Default for FailOverSettings {
+    fn default() -> Self {
+        Self {
+            max_consecutive_errors: 3,
+            primary_frame_timeout: Duration::from_secs(15),
+            primary_retry_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+enum ChildEvent {
+    Frame(anyhow::Result<FrameWithMeta>),
+    /// the stream of the child ended, `finished` is the result of `is_finished`
+    End {
+        finished: bool,
+    },
+}
+
+type ChildStream = Pin<Box<dyn Stream<Item = ChildEvent> + Send>>;
+
+/// Take the ownership of the child and restart its stream every time it ends,
+///     until the child is finished.
+fn child_stream(mut child: Box<dyn PlaylistChild>) -> ChildStream {
+    let s = stream! {
+        loop {
+            match child.stream_frame_with_meta().await {
+                Ok(mut s) => {
+                    while let Some(frame) = s.next().await {
+                        yield ChildEvent::Frame(frame);
+                    }
+                }
+                Err(e) => yield ChildEvent::Frame(Err(e)),
+            }
+
+            let finished = match child.is_finished().await {
+                Ok(finished) => finished,
+                Err(e) => {
+                    warn!("failed to check if child is finished: {}", e);
+                    false
+                }
+            };
+            yield ChildEvent::End { finished };
+            if finished {
+                break;
+            }
+        }
+    };
+
+    Box::pin(s)
+}
+
+enum NextEvent {
+    Primary(Option<ChildEvent>),
+    FailOver(Option<ChildEvent>),
+    PrimaryTimeout,
+    Retry,
+}
+
+/// FailOver plays the `primary` child, and switches to the `fail_over` child when the primary
+///     errors `max_consecutive_errors` times in a row,
+///     yields nothing for `primary_frame_timeout`,
+///     or finishes.
+/// While the fail over child is playing, the primary child is polled again
+///     every `primary_retry_interval`, and it takes over as soon as it yields a frame.
+pub struct FailOver {
+    /// wrapped in a `Mutex` to make `FailOver` `Sync`, it is only accessed with `get_mut`
+    primary: Mutex<ChildStream>,
+    fail_over: Mutex<ChildStream>,
+    settings: FailOverSettings,
+    primary_finished: bool,
+    fail_over_finished: bool,
+}
+
+impl FailOver {
+    pub fn new(
+        primary: Box<dyn PlaylistChild>,
+        fail_over: Box<dyn PlaylistChild>,
+        settings: FailOverSettings,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            primary: Mutex::new(child_stream(primary)),
+            fail_over: Mutex::new(child_stream(fail_over)),
+            settings,
+            primary_finished: false,
+            fail_over_finished: false,
+        })
+    }
+}
+
+#[async_trait]
+impl PlaylistChild for FailOver {
+    /// the fail over is finished only when both children are finished
+    async fn is_finished(&mut self) -> anyhow::Result<bool> {
+        Ok(self.primary_finished && self.fail_over_finished)
+    }
+
+    async fn stream_frame_with_meta(
+        &'_ mut self,
+    ) -> anyhow::Result<
+        std::pin::Pin<Box<dyn futures::Stream<Item = anyhow::Result<FrameWithMeta>> + Send + '_>>,
+    > {
+        let s = stream! {
+            let Self {
+                primary,
+                fail_over,
+                settings,
+                primary_finished,
+                fail_over_finished,
+            } = self;
+            let primary = primary.get_mut();
+            let fail_over = fail_over.get_mut();
+
+            let mut on_primary = !*primary_finished;
+            let mut consecutive_errors = 0;
+            let mut retry_at = Instant::now();
+
+            loop {
+                if *primary_finished && *fail_over_finished {
+                    break;
+                }
+                if *primary_finished {
+                    on_primary = false;
+                } else if *fail_over_finished {
+                    // nothing to switch to, keep on the primary child
+                    on_primary = true;
+                }
+
+                let next = if on_primary && *fail_over_finished {
+                    NextEvent::Primary(primary.next().await)
+                } else if on_primary {
+                    match tokio::time::timeout(settings.primary_frame_timeout, primary.next()).await {
+                        Ok(event) => NextEvent::Primary(event),
+                        Err(_) => NextEvent::PrimaryTimeout,
+                    }
+                } else if *primary_finished {
+                    NextEvent::FailOver(fail_over.next().await)
+                } else if Instant::now() >= retry_at {
+                    tokio::select! {
+                        biased;
+                        event = primary.next() => NextEvent::Primary(event),
+                        event = fail_over.next() => NextEvent::FailOver(event),
+                    }
+                } else {
+                    tokio::select! {
+                        _ = tokio::time::sleep_until(retry_at) => NextEvent::Retry,
+                        event = fail_over.next() => NextEvent::FailOver(event),
+                    }
+                };
+
+                // whether the primary child failed and the fail over child should take over
+                let mut primary_failed = false;
+                match next {
+                    NextEvent::Primary(Some(ChildEvent::Frame(Ok(frame)))) => {
+                        if !on_primary {
+                            info!("primary child recovered, switching back from fail over");
+                            on_primary = true;
+                        }
+                        consecutive_errors = 0;
+                        yield Ok(frame);
+                    }
+                    NextEvent::Primary(Some(ChildEvent::Frame(Err(e)))) => {
+                        if *fail_over_finished {
+                            yield Err(e);
+                        } else if on_primary {
+                            consecutive_errors += 1;
+                            warn!(
+                                "primary child failed ({}/{}): {}",
+                                consecutive_errors, settings.max_consecutive_errors, e
+                            );
+                            primary_failed = consecutive_errors >= settings.max_consecutive_errors;
+                        } else {
+                            warn!("primary child is still failing: {}", e);
+                            primary_failed = true;
+                        }
+                    }
+                    NextEvent::Primary(Some(ChildEvent::End { finished })) => {
+                        if finished {
+                            info!("primary child finished");
+                            *primary_finished = true;
+                        }
+                        primary_failed = true;
+                    }
+                    NextEvent::Primary(None) => {
+                        *primary_finished = true;
+                    }
+                    NextEvent::PrimaryTimeout => {
+                        warn!(
+                            "primary child yielded nothing for {:?}",
+                            settings.primary_frame_timeout
+                        );
+                        primary_failed = true;
+                    }
+                    NextEvent::FailOver(Some(ChildEvent::Frame(frame))) => {
+                        yield frame;
+                    }
+                    NextEvent::FailOver(Some(ChildEvent::End { finished })) => {
+                        if finished {
+                            info!("fail over child finished");
+                            *fail_over_finished = true;
+                        }
+                    }
+                    NextEvent::FailOver(None) => {
+                        *fail_over_finished = true;
+                    }
+                    NextEvent::Retry => {}
+                }
+
+                if primary_failed && !*fail_over_finished {
+                    if on_primary {
+                        warn!("switching to fail over child");
+                    }
+                    on_primary = false;
+                    consecutive_errors = 0;
+                    retry_at = Instant::now() + settings.primary_retry_interval;
+                }
+            }
+        };
+
+        Ok(Box::pin(s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    /// MockChild yields the given frames once, an `Err` is yielded for each `None`
+    struct MockChild {
+        frames: Vec<Option<&'static str>>,
+        played: bool,
+    }
+
+    impl MockChild {
+        fn boxed(frames: Vec<Option<&'static str>>) -> Box<dyn PlaylistChild> {
+            Box::new(Self {
+                frames,
+                played: false,
+            })
+        }
+    }
+
+    #[async_trait]
+    impl PlaylistChild for MockChild {
+        async fn is_finished(&mut self) -> anyhow::Result<bool> {
+            Ok(self.played)
+        }
+
+        async fn stream_frame_with_meta(
+            &'_ mut self,
+        ) -> anyhow::Result<
+            std::pin::Pin<
+                Box<dyn futures::Stream<Item = anyhow::Result<FrameWithMeta>> + Send + '_>,
+            >,
+        > {
+            let s = stream! {
+                for frame in self.frames.clone() {
+                    match frame {
+                        Some(title) => yield Ok(FrameWithMeta {
+                            frame: bytes::Bytes::new(),
+                            header: None,
+                            title: Arc::new(title.to_string()),
+                            artist: Arc::new("".to_string()),
+                            content_type: Arc::new("audio/mpeg".to_string()),
+                            duration: 0.0,
+                        }),
+                        None => yield Err(anyhow::anyhow!("mock error")),
+                    }
+                }
+                self.played = true;
+            };
+
+            Ok(Box::pin(s))
+        }
+    }
+
+    async fn collect_titles(child: &mut FailOver) -> Vec<String> {
+        let mut titles = Vec::new();
+        let mut s = child.stream_frame_with_meta().await.unwrap();
+        while let Some(frame) = s.next().await {
+            titles.push(frame.unwrap().title.to_string());
+        }
+        titles
+    }
+
+    #[tokio::test]
+    async fn test_fail_over_when_primary_finished() {
+        let mut child = FailOver::new(
+            MockChild::boxed(vec![Some("p1"), Some("p2")]),
+            MockChild::boxed(vec![Some("f1")]),
+            FailOverSettings::default(),
+        )
+        .unwrap();
+
+        assert_eq!(collect_titles(&mut child).await, vec!["p1", "p2", "f1"]);
+        assert!(child.is_finished().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_fail_over_after_consecutive_errors() {
+        let mut child = FailOver::new(
+            MockChild::boxed(vec![Some("p1"), None, Some("p2"), None, None, None]),
+            MockChild::boxed(vec![Some("f1"), Some("f2")]),
+            FailOverSettings::default(),
+        )
+        .unwrap();
+
+        // a single error is tolerated, the errors are not passed to the listeners
+        assert_eq!(
+            collect_titles(&mut child).await,
+            vec!["p1", "p2", "f1", "f2"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_primary_resumed_when_fail_over_finished() {
+        let mut child = FailOver::new(
+            MockChild::boxed(vec![None, None, None, Some("p1")]),
+            MockChild::boxed(vec![Some("f1")]),
+            FailOverSettings::default(),
+        )
+        .unwrap();
+
+        assert_eq!(collect_titles(&mut child).await, vec!["f1", "p1"]);
+        assert!(child.is_finished().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_fail_over_settings() {
+        let settings = FailOverSettings {
+            max_consecutive_errors: 1,
+            ..Default::default()
+        };
+        let mut child = FailOver::new(
+            MockChild::boxed(vec![Some("p1"), None, Some("p2")]),
+            MockChild::boxed(vec![Some("f1")]),
+            settings,
+        )
+        .unwrap();
+
+        // the primary child is tried again once the fail over child is finished
+        assert_eq!(collect_titles(&mut child).await, vec!["p1", "f1", "p2"]);
+    }
+}
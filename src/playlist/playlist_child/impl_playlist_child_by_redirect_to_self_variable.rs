@@ -15,6 +15,10 @@ macro_rules! impl_playlist_child_by_redirect_to_self_variable {
             async fn is_finished(&mut self) -> anyhow::Result<bool> {
                 self.$e.is_finished().await
             }
+
+            async fn prefetch(&mut self) -> anyhow::Result<()> {
+                self.$e.prefetch().await
+            }
         }
     };
 }
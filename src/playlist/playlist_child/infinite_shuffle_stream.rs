@@ -137,21 +137,14 @@ mod tests {
 
     struct MockOriginalData;
 
+    type MockStream = Pin<Box<dyn Stream<Item = anyhow::Result<i32>> + Send>>;
+
     fn mock_data_stream(
         _original_data: Arc<MockOriginalData>,
         _fp: MockFileProvider,
-    ) -> Pin<
-        Box<
-            dyn Future<
-                    Output = anyhow::Result<
-                        Pin<Box<dyn Stream<Item = anyhow::Result<i32>> + Send>>,
-                    >,
-                > + Send,
-        >,
-    > {
+    ) -> Pin<Box<dyn Future<Output = anyhow::Result<MockStream>> + Send>> {
         let values = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
-        let stream: Pin<Box<dyn Stream<Item = anyhow::Result<i32>> + Send>> =
-            Box::pin(futures::stream::iter(values.into_iter().map(Ok)));
+        let stream: MockStream = Box::pin(futures::stream::iter(values.into_iter().map(Ok)));
         let stream = async move || Ok(stream);
         let stream = (stream)();
         Box::pin(stream)
@@ -0,0 +1,297 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+use async_stream::stream;
+use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use log::info;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt},
+    sync::{
+        Mutex,
+        mpsc::{self, Receiver, Sender, UnboundedReceiver, UnboundedSender},
+    },
+};
+
+use crate::{audio::ChunkReader, shoutcast::constant_time_eq};
+
+use super::{FrameWithMeta, PlaylistChild};
+
+/// number of frames buffered between the source client and the playlist,
+///     the source client is not read while the buffer is full
+const LIVE_FRAME_BUFFER_SIZE: usize = 32;
+
+/// size of the buffer used to read the data of a source client
+///     when its content type can not be cut into frames
+const LIVE_READ_BUFFER_SIZE: usize = 16384;
+
+/// duration of each frame of the live source in milliseconds
+const LIVE_FRAME_DURATION: f64 = 500.0;
+
+/// bitrate used when the source client does not send its bitrate, in kbit/s
+pub const DEFAULT_LIVE_BITRATE: usize = 128;
+
+/// LiveSource is the entry point of the source clients of a playlist.
+/// Only one source client can be connected at the same time,
+///     each connection is a `LiveSourceSession`,
+///     and the frames of the session are streamed by the `LiveSourceChild` of the playlist.
+pub struct LiveSource {
+    user: String,
+    password: String,
+    /// host and port of the Shoutcast v1 source listener
+    pub shoutcast_v1: Option<(String, u16)>,
+    connected: Arc<AtomicBool>,
+    session_sender: UnboundedSender<Receiver<FrameWithMeta>>,
+    session_recv: Mutex<UnboundedReceiver<Receiver<FrameWithMeta>>>,
+}
+
+impl LiveSource {
+    pub fn new(user: String, password: String, shoutcast_v1: Option<(String, u16)>) -> Self {
+        let (session_sender, session_recv) = mpsc::unbounded_channel();
+        Self {
+            user,
+            password,
+            shoutcast_v1,
+            connected: Arc::new(AtomicBool::new(false)),
+            session_sender,
+            session_recv: Mutex::new(session_recv),
+        }
+    }
+
+    /// check the credentials of a source client in constant time,
+    ///     the user is not checked if it is None (Shoutcast v1 only sends a password)
+    pub fn authenticate(&self, user: Option<&str>, password: &str) -> bool {
+        let user = user.is_none_or(|user| constant_time_eq(user.as_bytes(), self.user.as_bytes()));
+        let password = constant_time_eq(password.as_bytes(), self.password.as_bytes());
+        user & password
+    }
+
+    /// whether a source client is connected
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    /// Start a new session for a connected source client.
+    /// Returns an error if another source client is already connected.
+    pub fn connect(
+        &self,
+        content_type: Arc<String>,
+        title: Arc<String>,
+        artist: Arc<String>,
+        bitrate: usize,
+    ) -> anyhow::Result<LiveSourceSession> {
+        if self
+            .connected
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Relaxed)
+            .is_err()
+        {
+            anyhow::bail!("a source client is already connected");
+        }
+
+        let (sender, recv) = mpsc::channel(LIVE_FRAME_BUFFER_SIZE);
+        if self.session_sender.send(recv).is_err() {
+            self.connected.store(false, Ordering::Release);
+            anyhow::bail!("the playlist is not accepting live sources anymore");
+        }
+        info!("live source connected: {}", title);
+
+        let byte_per_millisecond = bitrate.max(1) as f64 / 8.0;
+        Ok(LiveSourceSession {
+            sender,
+            connected: self.connected.clone(),
+            frame_size: (byte_per_millisecond * LIVE_FRAME_DURATION) as usize,
+            byte_per_millisecond,
+            content_type,
+            title,
+            artist,
+        })
+    }
+}
+
+/// LiveSourceSession cuts the data of a connected source client into frames,
+///     and sends them to the playlist.
+/// The source client is disconnected when the session is dropped.
+pub struct LiveSourceSession {
+    sender: Sender<FrameWithMeta>,
+    connected: Arc<AtomicBool>,
+    frame_size: usize,
+    byte_per_millisecond: f64,
+    content_type: Arc<String>,
+    title: Arc<String>,
+    artist: Arc<String>,
+}
+
+impl LiveSourceSession {
+    /// Stream the data of the source client to the playlist until the client disconnects.
+    /// The data is cut on the frame or page boundaries of the supported content types,
+    ///     and by the bitrate of the source client otherwise.
+    /// Returns an error if the playlist stopped receiving frames.
+    pub async fn stream(&mut self, reader: impl AsyncRead + Unpin) -> anyhow::Result<()> {
+        let mut reader = match ChunkReader::new(&self.content_type, reader) {
+            Ok(mut reader) => {
+                while let Some(chunk) = reader.next_chunk(LIVE_FRAME_DURATION).await? {
+                    self.send(chunk.data, chunk.header, chunk.duration).await?;
+                }
+                return Ok(());
+            }
+            Err(reader) => reader,
+        };
+
+        let mut buf = BytesMut::with_capacity(LIVE_READ_BUFFER_SIZE);
+        loop {
+            buf.reserve(LIVE_READ_BUFFER_SIZE);
+            let read = reader.read_buf(&mut buf).await?;
+            // the last frame of the source client may be shorter
+            while buf.len() >= self.frame_size || (read == 0 && !buf.is_empty()) {
+                let frame = buf.split_to(self.frame_size.min(buf.len())).freeze();
+                let duration = frame.len() as f64 / self.byte_per_millisecond;
+                self.send(frame, None, duration).await?;
+            }
+            if read == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    /// send a frame to the playlist, waiting while its buffer is full
+    async fn send(&self, frame: Bytes, header: Option<Bytes>, duration: f64) -> anyhow::Result<()> {
+        let frame_with_meta = FrameWithMeta {
+            duration,
+            frame,
+            header,
+            title: self.title.clone(),
+            artist: self.artist.clone(),
+            content_type: self.content_type.clone(),
+        };
+        if self.sender.send(frame_with_meta).await.is_err() {
+            anyhow::bail!("the playlist stopped receiving live frames");
+        }
+        Ok(())
+    }
+}
+
+impl Drop for LiveSourceSession {
+    fn drop(&mut self) {
+        info!("live source disconnected: {}", self.title);
+        self.connected.store(false, Ordering::Release);
+    }
+}
+
+/// LiveSourceChild streams the frames of the next session of the live source,
+///     the stream waits until a source client connects,
+///     and ends when the source client disconnects.
+pub struct LiveSourceChild {
+    source: Arc<LiveSource>,
+}
+
+impl LiveSourceChild {
+    pub fn new(source: Arc<LiveSource>) -> Self {
+        Self { source }
+    }
+}
+
+#[async_trait]
+impl PlaylistChild for LiveSourceChild {
+    /// a live source is never finished, the next source client can connect at any time
+    async fn is_finished(&mut self) -> anyhow::Result<bool> {
+        Ok(false)
+    }
+
+    async fn stream_frame_with_meta(
+        &'_ mut self,
+    ) -> anyhow::Result<
+        std::pin::Pin<Box<dyn futures::Stream<Item = anyhow::Result<FrameWithMeta>> + Send + '_>>,
+    > {
+        let s = stream! {
+            let mut session_recv = self.source.session_recv.lock().await;
+            if let Some(mut session) = session_recv.recv().await {
+                while let Some(frame) = session.recv().await {
+                    yield Ok(frame);
+                }
+            }
+        };
+
+        Ok(Box::pin(s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+
+    fn connect(source: &LiveSource, content_type: &str) -> anyhow::Result<LiveSourceSession> {
+        source.connect(
+            Arc::new(content_type.to_string()),
+            Arc::new("Live Show".to_string()),
+            Arc::new("Live".to_string()),
+            DEFAULT_LIVE_BITRATE,
+        )
+    }
+
+    /// stream the data of a source client and collect the frames of the playlist
+    async fn stream_frames(content_type: &str, data: Vec<u8>) -> Vec<FrameWithMeta> {
+        let source = Arc::new(LiveSource::new(
+            "source".to_string(),
+            "hackme".to_string(),
+            None,
+        ));
+        let mut child = LiveSourceChild::new(source.clone());
+        let s = child.stream_frame_with_meta().await.unwrap();
+
+        let mut session = connect(&source, content_type).unwrap();
+        let streaming = tokio::spawn(async move { session.stream(data.as_slice()).await });
+        let frames = s.map(|f| f.unwrap()).collect::<Vec<_>>().await;
+        streaming.await.unwrap().unwrap();
+        frames
+    }
+
+    #[test]
+    fn test_authenticate() {
+        let source = LiveSource::new("source".to_string(), "hackme".to_string(), None);
+        assert!(source.authenticate(Some("source"), "hackme"));
+        assert!(source.authenticate(None, "hackme"));
+        assert!(!source.authenticate(Some("admin"), "hackme"));
+        assert!(!source.authenticate(Some("source"), "wrong"));
+    }
+
+    #[tokio::test]
+    async fn test_single_source_client() {
+        let source = LiveSource::new("source".to_string(), "hackme".to_string(), None);
+        let session = connect(&source, "audio/mpeg").unwrap();
+        assert!(source.is_connected());
+        assert!(connect(&source, "audio/mpeg").is_err());
+
+        drop(session);
+        assert!(!source.is_connected());
+        assert!(connect(&source, "audio/mpeg").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_stream_session_frames() {
+        // 128 kbit/s 44.1 kHz MPEG 1 Layer III frames of 417 bytes and 26.12 ms,
+        //     a frame of 500 ms has 20 of them
+        let mut data = Vec::new();
+        for _ in 0..30 {
+            data.extend([0xFF, 0xFB, 0x90, 0x64]);
+            data.resize(data.len() + 413, 0x55);
+        }
+        // the source client starts in the middle of a frame
+        let frames = stream_frames("audio/mpeg", data[100..].to_vec()).await;
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].frame.len(), 20 * 417);
+        assert!(frames[0].duration >= 500.0);
+        assert_eq!(frames[1].frame.len(), 9 * 417);
+        assert_eq!(*frames[1].title, "Live Show");
+
+        // 128 kbit/s is 16 bytes per millisecond, a frame of 500 ms is 8000 bytes
+        let frames = stream_frames("audio/aac", vec![0; 12000]).await;
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].frame.len(), 8000);
+        assert_eq!(frames[0].duration, 500.0);
+        assert_eq!(frames[1].duration, 250.0);
+    }
+}
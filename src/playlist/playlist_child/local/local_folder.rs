@@ -17,6 +17,7 @@ use tokio_stream::StreamExt;
 #[custom_input_type(additional_input(name = "repeat", input_type = "bool", default = "false"))]
 #[custom_input_type(additional_input(name = "shuffle", input_type = "bool", default = "false"))]
 #[custom_input_type(additional_input(name = "recursive", input_type = "bool", default = "false"))]
+#[custom_input_type(additional_input(name = "prefetch", input_type = "usize", default = "0"))]
 #[custom_input_type(additional_input(
     name = "file_provider",
     input_type = "Arc<dyn FileProvider>",
@@ -74,12 +75,14 @@ impl LocalFolderInner {
         repeat: bool,
         shuffle: bool,
         recursive: bool,
+        prefetch: usize,
         file_provider: Arc<dyn FileProvider>,
     ) -> anyhow::Result<Self> {
         let tracks = Arc::new((tracks, recursive));
         Ok(Self {
             tracks: PlaylistChildList::new(
                 tracks,
+                prefetch,
                 Some(repeat),
                 Some(shuffle),
                 original_data2_stream_default,
@@ -6,13 +6,17 @@ use derive_lazy_playlist_child::LazyPlaylistChild;
 use id3::TagLike;
 use log::debug;
 use once_cell::sync::Lazy;
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, io::SeekFrom, sync::Arc};
 
-use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt};
 
 use crate::{
-    FileProvider,
-    playlist::{DEFAULT_FRAME_SIZE, PlaylistChild},
+    FileProvider, RangeReader,
+    audio::{
+        ChunkReader, FLAC_CONTENT_TYPE, MP3_CONTENT_TYPE, OGG_CONTENT_TYPE, read_flac_info,
+        read_mp3_info, read_ogg_info,
+    },
+    playlist::{DEFAULT_CHUNK_DURATION, DEFAULT_FRAME_SIZE, PlaylistChild},
 };
 
 /// use Arc to share the same content between threads
@@ -55,51 +59,90 @@ struct MetaData {
     byte_per_millisecond: f64,
 }
 
+/// Probe the tags and the headers of a file, only the parts read are fetched from remote providers:
+///     the tags and the first frame or the metadata blocks at the start, the last Ogg page at the end.
 async fn get_meta_data_from_file(
-    path: &str,
+    path: &Arc<String>,
     file_provider: Arc<dyn FileProvider>,
 ) -> anyhow::Result<MetaData> {
-    let cache_path = match file_provider.get_local_cache_path(path).await? {
-        Some(cache_path) => cache_path,
-        None => return Err(anyhow::anyhow!("file not found")),
-    };
     let content_type = match get_content_type_from_path(path) {
         Some(content_type) => content_type,
         None => return Err(anyhow::anyhow!("unsupported file type")),
     };
 
+    // get size of the file
+    let meta = match file_provider.get_meta(path).await? {
+        Some(meta) => meta,
+        None => return Err(anyhow::anyhow!("file not found")),
+    };
+    let mut size = meta.size;
+    let mut reader = RangeReader::new(file_provider.clone(), path.clone(), size as u64);
+
     let mut title = None;
     let mut artist = None;
     let mut duration = Option::None;
 
-    let tag = id3::Tag::read_from_path(&cache_path);
+    let tag = id3::Tag::async_read_from(&mut reader).await;
     let tag = id3::partial_tag_ok(tag);
     if let Ok(tag) = tag {
         title = tag.title().map(|t| t.to_string());
         artist = tag.artist().map(|t| t.to_string());
-        duration = tag.duration();
+        duration = tag.duration().map(|d| d as f64);
+    }
+    reader.seek(SeekFrom::Start(0)).await?;
+
+    match content_type.as_str() {
+        MP3_CONTENT_TYPE => {
+            // the Xing, Info or VBRI header gives the exact duration and size of the audio,
+            //     without the tags, without scanning the whole file
+            if let Some(info) = read_mp3_info(&mut reader, size).await? {
+                debug!(
+                    "got duration: {} ms, bitrate: {} kbit/s from mp3 header",
+                    info.duration,
+                    info.bitrate()
+                );
+                duration = Some(info.duration);
+                size = info.audio_bytes;
+            }
+        }
+        FLAC_CONTENT_TYPE | OGG_CONTENT_TYPE => {
+            // the duration is in the STREAMINFO block or the granule position of the last page,
+            //     the title and the artist are in the Vorbis comment
+            let info = match content_type.as_str() {
+                FLAC_CONTENT_TYPE => read_flac_info(&mut reader).await?,
+                _ => read_ogg_info(&mut reader).await?,
+            };
+            if let Some(info) = info {
+                debug!("got duration: {} ms from {}", info.duration, content_type);
+                duration = Some(info.duration);
+                title = title.or(info.title);
+                artist = artist.or(info.artist);
+            }
+        }
+        _ => {}
     }
 
-    if duration.is_none() {
+    debug!(
+        "probed {path}: {} of {size} bytes fetched",
+        reader.fetched()
+    );
+
+    // the frames of the whole file are counted, it is fetched
+    if duration.is_none()
+        && let Some(cache_path) = file_provider.get_local_cache_path(path).await?
+    {
         duration = match mp3_duration::from_path(&cache_path) {
-            Ok(duration) => Some((duration.as_nanos() / 1_000_000) as u32),
+            Ok(duration) => Some(duration.as_nanos() as f64 / 1_000_000.0),
             Err(_) => None,
         };
     }
     let duration = match duration {
-        Some(duration) => duration,
-        None => return Err(anyhow::anyhow!("failed to get duration")),
+        Some(duration) if duration > 0.0 => duration,
+        _ => return Err(anyhow::anyhow!("failed to get duration")),
     };
 
-    // get size of the file
-    let meta = match file_provider.get_meta(path).await? {
-        Some(meta) => meta,
-        None => return Err(anyhow::anyhow!("file not found")),
-    };
-    let size = meta.size;
-
     // calculate the bitrate
-    let byte_per_millisecond = (size + 1) as f64 / duration as f64;
+    let byte_per_millisecond = (size + 1) as f64 / duration;
 
     Ok(MetaData {
         content_type,
@@ -198,28 +241,52 @@ impl PlaylistChild for LocalFileTrackInner {
     > {
         let s = stream! {
             loop {
-                let mut stream = self.new_stream().await?;
-                let mut start = true;
-                loop {
-                    let mut buf = vec![0; DEFAULT_FRAME_SIZE];
-                    let read = stream.read(&mut buf).await?;
-                    if read == 0 && start {
-                        Err(anyhow::anyhow!("file is empty"))?;
-                    } else if read == 0 {
-                        break;
+                let stream = self.new_stream().await?;
+                match ChunkReader::new(&self.content_type, stream) {
+                    Ok(mut reader) => {
+                        // split on the frame or page boundaries,
+                        //     the tags are not sent to the listeners
+                        let mut start = true;
+                        while let Some(chunk) = reader.next_chunk(DEFAULT_CHUNK_DURATION).await? {
+                            start = false;
+                            yield Ok(FrameWithMeta {
+                                frame: chunk.data,
+                                header: chunk.header,
+                                title: self.title.clone(),
+                                artist: self.artist.clone(),
+                                content_type: self.content_type.clone(),
+                                duration: chunk.duration,
+                            });
+                        }
+                        if start {
+                            Err(anyhow::anyhow!("no audio frame found in file"))?;
+                        }
                     }
+                    Err(mut stream) => {
+                        let mut start = true;
+                        loop {
+                            let mut buf = vec![0; DEFAULT_FRAME_SIZE];
+                            let read = stream.read(&mut buf).await?;
+                            if read == 0 && start {
+                                Err(anyhow::anyhow!("file is empty"))?;
+                            } else if read == 0 {
+                                break;
+                            }
+
+                            let frame = bytes::Bytes::from(buf).slice(0..read);
+                            let frame_with_meta = FrameWithMeta {
+                                duration: frame.len() as f64 / self.byte_per_millisecond,
+                                frame,
+                                header: None,
+                                title: self.title.clone(),
+                                artist: self.artist.clone(),
+                                content_type: self.content_type.clone(),
 
-                    let frame = bytes::Bytes::from(buf).slice(0..read);
-                    let frame_with_meta = FrameWithMeta {
-                        duration: frame.len() as f64 / self.byte_per_millisecond,
-                        frame,
-                        title: self.title.clone(),
-                        artist: self.artist.clone(),
-                        content_type: self.content_type.clone(),
-
-                    };
-                    start = false;
-                    yield Ok(frame_with_meta);
+                            };
+                            start = false;
+                            yield Ok(frame_with_meta);
+                        }
+                    }
                 }
                 if !self.repeat {
                     self.played = true;
@@ -230,4 +297,10 @@ impl PlaylistChild for LocalFileTrackInner {
 
         Ok(Box::pin(s))
     }
+
+    /// the tags were read by the initialisation, the file is fetched in the background
+    async fn prefetch(&mut self) -> anyhow::Result<()> {
+        self.file_provider.prefetch(&self.path);
+        Ok(())
+    }
 }
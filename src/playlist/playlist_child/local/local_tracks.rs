@@ -34,14 +34,17 @@ fn original_data2_stream(
 }
 
 impl LocalFileTrackList {
+    /// `prefetch` is the number of upcoming tracks prefetched while a track is played.
     pub fn new(
         tracks: Arc<Vec<Arc<String>>>,
         repeat: Option<bool>,
         shuffle: Option<bool>,
+        prefetch: usize,
         file_provider: Arc<dyn FileProvider>,
     ) -> anyhow::Result<Self> {
         let t = PlaylistChildList::<Vec<Arc<String>>, Arc<dyn FileProvider>>::new(
             tracks,
+            prefetch,
             repeat,
             shuffle,
             original_data2_stream,
@@ -5,12 +5,16 @@ use async_trait::async_trait;
 #[macro_use]
 mod impl_playlist_child_by_redirect_to_self_variable;
 
+mod fail_over;
 mod infinite_shuffle_stream;
+mod live_source;
 mod local;
 mod playlist_child_list;
 mod silent;
 
 use bytes::Bytes;
+pub use fail_over::{FailOver, FailOverSettings};
+pub use live_source::{DEFAULT_LIVE_BITRATE, LiveSource, LiveSourceChild, LiveSourceSession};
 // re-export the local module
 pub use local::*;
 pub use playlist_child_list::PlaylistChildList;
@@ -19,6 +23,9 @@ pub use silent::Silent;
 #[derive(Clone)]
 pub struct FrameWithMeta {
     pub frame: Bytes,
+    /// the stream header needed to decode the frame, e.g. the FLAC metadata or the Ogg header pages,
+    ///     sent to a listener before its first frame and whenever it changes
+    pub header: Option<Bytes>,
     pub title: Arc<String>,
     pub artist: Arc<String>,
     pub content_type: Arc<String>,
@@ -36,4 +43,10 @@ pub trait PlaylistChild: Sync + Send {
 
     /// check if the Playlist is finished
     async fn is_finished(&mut self) -> anyhow::Result<bool>;
+
+    /// prepare the child to be played soon, e.g. start the download of a remote track;
+    ///     does nothing by default
+    async fn prefetch(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
 }
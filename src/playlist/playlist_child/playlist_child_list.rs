@@ -2,7 +2,8 @@ use async_stream::stream;
 use async_trait::async_trait;
 use derive_lazy_playlist_child::LazyPlaylistChild;
 use futures::{StreamExt, pin_mut};
-use std::sync::Arc;
+use std::{collections::VecDeque, sync::Arc};
+use tokio::task::JoinHandle;
 
 use super::{
     FrameWithMeta, PlaylistChild,
@@ -13,6 +14,8 @@ use super::{
 /// playlist children, the current index, whether to repeat
 /// the playlist, whether to shuffle the playlist, and whether
 /// the playlist is played.
+///
+/// The next `prefetch` children are prefetched while a child is played.
 #[allow(clippy::duplicated_attributes)]
 #[derive(LazyPlaylistChild)]
 #[custom_input_type(input_type(name = "tracks", input_type = "Arc<O>"))]
@@ -36,10 +39,24 @@ where
     FP: Send + Sync + Unpin + Clone,
 {
     tracks: InfiniteShuffleStream<O, Box<dyn PlaylistChild>, FP>,
+    /// the number of upcoming children prefetched
+    prefetch: usize,
     /// whether the playlist is played
     played: bool,
 }
 
+/// A child being prefetched in the background.
+type Prefetching = JoinHandle<anyhow::Result<Box<dyn PlaylistChild>>>;
+
+/// Prefetch a child in the background, it is returned once prefetched.
+fn spawn_prefetch(child: anyhow::Result<Box<dyn PlaylistChild>>) -> Prefetching {
+    tokio::spawn(async move {
+        let mut child = child?;
+        child.prefetch().await?;
+        Ok(child)
+    })
+}
+
 impl<O, FP> PlaylistChildListInner<O, FP>
 where
     O: Send + Sync + Unpin,
@@ -47,6 +64,7 @@ where
 {
     async fn new(
         tracks: Arc<O>,
+        prefetch: usize,
         repeat: bool,
         shuffle: bool,
         original_data2_stream: OriginalData2Stream<O, Box<dyn PlaylistChild>, FP>,
@@ -62,6 +80,7 @@ where
 
         Ok(Self {
             tracks,
+            prefetch,
             played: false,
         })
     }
@@ -87,9 +106,14 @@ where
             let s = self.tracks.stream();
             let s = s.fuse();
             pin_mut!(s);
+            // the children after the one played, prefetched in the background
+            let mut upcoming: VecDeque<Prefetching> = VecDeque::new();
 
             loop {
-                let data = s.next().await;
+                let data = match upcoming.pop_front() {
+                    Some(handle) => Some(handle.await.unwrap_or_else(|e| Err(e.into()))),
+                    None => s.next().await,
+                };
                 if data.is_none() {
                     self.played = true;
                     break;
@@ -108,6 +132,7 @@ where
                     }
                 };
                 let mut data_s = data_s.fuse();
+                let mut started = false;
 
                 loop {
                     let frame = data_s.next().await;
@@ -117,6 +142,16 @@ where
                             yield f;
                         }
                     }
+                    // once the child plays, not to delay its start
+                    if !started {
+                        started = true;
+                        while upcoming.len() < self.prefetch {
+                            match s.next().await {
+                                Some(child) => upcoming.push_back(spawn_prefetch(child)),
+                                None => break,
+                            }
+                        }
+                    }
                 }
             }
         };
@@ -124,3 +159,90 @@ where
         Ok(Box::pin(s))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::pin::Pin;
+
+    use super::*;
+    use futures::Stream;
+
+    /// MockChild yields two frames titled by its name, followed by `+` once prefetched
+    struct MockChild {
+        name: &'static str,
+        /// whether its prefetch fails
+        failing: bool,
+        prefetched: bool,
+    }
+
+    #[async_trait]
+    impl PlaylistChild for MockChild {
+        async fn is_finished(&mut self) -> anyhow::Result<bool> {
+            Ok(false)
+        }
+
+        async fn stream_frame_with_meta(
+            &'_ mut self,
+        ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<FrameWithMeta>> + Send + '_>>>
+        {
+            let suffix = if self.prefetched { "+" } else { "" };
+            let title = Arc::new(format!("{}{suffix}", self.name));
+            let frame = FrameWithMeta {
+                frame: bytes::Bytes::new(),
+                header: None,
+                title,
+                artist: Arc::new("".to_string()),
+                content_type: Arc::new("audio/mpeg".to_string()),
+                duration: 0.0,
+            };
+            Ok(Box::pin(futures::stream::iter([
+                Ok(frame.clone()),
+                Ok(frame),
+            ])))
+        }
+
+        async fn prefetch(&mut self) -> anyhow::Result<()> {
+            if self.failing {
+                anyhow::bail!("mock error");
+            }
+            self.prefetched = true;
+            Ok(())
+        }
+    }
+
+    type ChildStream = Pin<Box<dyn Stream<Item = anyhow::Result<Box<dyn PlaylistChild>>> + Send>>;
+
+    fn mock_children(
+        children: Arc<Vec<(&'static str, bool)>>,
+        _fp: (),
+    ) -> Pin<Box<dyn Future<Output = anyhow::Result<ChildStream>> + Send>> {
+        let children: Vec<_> = children
+            .iter()
+            .map(|&(name, failing)| {
+                Ok(Box::new(MockChild {
+                    name,
+                    failing,
+                    prefetched: false,
+                }) as Box<dyn PlaylistChild>)
+            })
+            .collect();
+        let s: ChildStream = Box::pin(futures::stream::iter(children));
+        Box::pin(async { Ok(s) })
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_upcoming_children() {
+        let children = Arc::new(vec![("a", false), ("b", true), ("c", false)]);
+        let mut list = PlaylistChildList::new(children, 2, None, None, mock_children, ()).unwrap();
+
+        let mut titles = Vec::new();
+        let mut s = list.stream_frame_with_meta().await.unwrap();
+        while let Some(frame) = s.next().await {
+            titles.push(frame.map_or_else(|e| e.to_string(), |f| f.title.to_string()));
+        }
+        drop(s);
+        // the first child is played at once, the failed prefetch is yielded at its turn
+        assert_eq!(titles, vec!["a", "a", "mock error", "c+", "c+"]);
+        assert!(list.is_finished().await.unwrap());
+    }
+}
@@ -4,18 +4,24 @@ use async_trait::async_trait;
 use once_cell::sync::Lazy;
 use std::sync::Arc;
 
-use crate::playlist::PlaylistChild;
+use crate::{audio::split_chunks, playlist::PlaylistChild};
 
 const SILENT_FILE: &[u8; 37206] = include_bytes!("../../../1-second-of-silence.mp3");
 
-static SILENT_FILE_FRAME_WITH_META: once_cell::sync::Lazy<FrameWithMeta> =
-    Lazy::new(|| FrameWithMeta {
-        frame: bytes::Bytes::from_static(SILENT_FILE),
+/// the audio frames of `SILENT_FILE` without its tags
+static SILENT_FILE_FRAME_WITH_META: once_cell::sync::Lazy<FrameWithMeta> = Lazy::new(|| {
+    let chunk = split_chunks(SILENT_FILE, f64::INFINITY)
+        .pop()
+        .expect("the silent file has audio frames");
+    FrameWithMeta {
+        frame: chunk.data,
+        header: None,
         title: Arc::new("Silent".to_string()),
         artist: Arc::new("Silent".to_string()),
         content_type: Arc::new("audio/mpeg".to_string()),
-        duration: 1000.0,
-    });
+        duration: chunk.duration,
+    }
+});
 
 pub struct Silent {}
 
@@ -84,8 +84,16 @@ impl Stream for PlaylistFrameStream {
                     match data {
                         Ok(Some(frame)) => {
                             self.current_stream_frame = frame.clone();
+                            // sleep until the frames before this one have been played,
+                            //     the sleep is computed from the created time to avoid drifting
+                            let played_duration = self.write_ahead_duration;
                             self.write_ahead_duration += frame.frame_with_meta.duration;
-                            let sleep_dur = frame.frame_with_meta.duration.floor();
+                            let elapsed = std::time::SystemTime::now()
+                                .duration_since(std::time::UNIX_EPOCH)
+                                .unwrap()
+                                .as_millis()
+                                .saturating_sub(self.created_time);
+                            let sleep_dur = (played_duration - elapsed as f64).max(0.0);
                             self.waiting_pending_future = Some(Box::pin(tokio::time::sleep(
                                 std::time::Duration::from_millis(sleep_dur as u64),
                             )));
@@ -1,13 +1,28 @@
-use std::sync::Arc;
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Instant,
+};
 
 use bytes::Bytes;
 use log::debug;
-use tokio::sync::{Mutex, mpsc::Receiver};
-use tokio_stream::StreamExt;
+use tokio::sync::{
+    Mutex,
+    mpsc::{Receiver, Sender, error::TryRecvError},
+};
 
-use crate::{CONTEXT, shoutcast::ListenerID};
+use crate::{CONTEXT, audio::AudioFormat, metrics::PlaylistMetrics, shoutcast::ListenerID};
 
-use super::{FrameWithMeta, PlaylistChild, listener_frame_data::ListenerFrameData};
+use super::{
+    FrameWithMeta, LiveSource, LiveSourceChild, PlaylistChild,
+    listener_frame_data::{ListenerFrameData, ListenerGuard},
+    producer::{PlaylistControl, produce},
+};
+
+/// number of control commands waiting for the producer
+const CONTROL_CHANNEL_SIZE: usize = 16;
 
 /// We keep a linked list of `PreparedFrame` to stream to the client,
 ///     each `prepared_frame` is wrapped in an `Arc<Mutex<PreparedFrame>>`
@@ -36,16 +51,33 @@ pub struct Playlist {
     pub name: Arc<String>,
     finished: Mutex<bool>,
     child_recv: Mutex<Receiver<anyhow::Result<FrameWithMeta>>>,
+    /// the control commands applied by the producer of the frames
+    control: Sender<PlaylistControl>,
+    /// whether the playlist is paused by a control command
+    paused: AtomicBool,
     newest_prepared_frames: Mutex<PreparedFrame>,
     listener_frame_data_db: ListenerFrameData,
     content_type: Mutex<Arc<String>>,
+    /// the id, title and artist of the latest frame written to a listener
+    now_playing: Mutex<(usize, Arc<String>, Arc<String>)>,
+    /// the time the playlist started streaming
+    pub start_time: chrono::DateTime<chrono::Local>,
+    /// the live source that preempts the child while a source client is connected
+    live_source: Option<Arc<LiveSource>>,
+    /// frames prepared, producer stalls and track changes of the playlist
+    metrics: Arc<PlaylistMetrics>,
 }
 
 impl Playlist {
-    pub async fn new(name: String, child: Box<dyn PlaylistChild>) -> Self {
+    pub async fn new(
+        name: String,
+        child: Box<dyn PlaylistChild>,
+        live_source: Option<Arc<LiveSource>>,
+    ) -> Self {
         let frame = PreparedFrame {
             frame_with_meta: FrameWithMeta {
                 frame: Bytes::new(),
+                header: None,
                 duration: 0.0,
                 title: Arc::new("".to_string()),
                 artist: Arc::new("".to_string()),
@@ -56,41 +88,64 @@ impl Playlist {
         };
 
         let (sender, child_recv) = tokio::sync::mpsc::channel(1);
-        tokio::spawn(async move {
-            let mut child = child;
-            let stream = child.stream_frame_with_meta().await;
-            // if err, send the err to the receiver
-            if let Err(e) = stream {
-                let e = sender.send(Err(e)).await;
-                if let Err(e) = e {
-                    log::error!("failed to send error to child_recv: {}", e);
-                }
-                return;
-            }
-            let mut stream = stream.unwrap();
-
-            while let Some(frame_with_meta) = stream.next().await {
-                match sender.send(frame_with_meta).await {
-                    Ok(_) => {}
-                    Err(e) => {
-                        // the receiver is dropped, we should stop the stream
-                        log::error!("failed to send frame_with_meta: {}", e);
-                        return;
-                    }
-                }
-            }
-
-            // the stream is finished, the sender is dropped
-        });
+        let (control, control_recv) = tokio::sync::mpsc::channel(CONTROL_CHANNEL_SIZE);
+        let live_child = live_source.clone().map(LiveSourceChild::new);
+        tokio::spawn(produce(child, live_child, sender, control_recv));
 
         Self {
             name: name.into(),
             child_recv: (Mutex::new(child_recv)),
+            control,
+            paused: AtomicBool::new(false),
             finished: Mutex::new(false),
             newest_prepared_frames: Mutex::new(frame.clone()),
             listener_frame_data_db: ListenerFrameData::new(Arc::new(Mutex::new(frame.clone()))),
+            now_playing: Mutex::new((
+                frame.id,
+                frame.frame_with_meta.title.clone(),
+                frame.frame_with_meta.artist.clone(),
+            )),
             content_type: Mutex::new(frame.frame_with_meta.content_type),
+            start_time: chrono::Local::now(),
+            live_source,
+            metrics: Default::default(),
+        }
+    }
+
+    /// get the metrics of the playlist
+    pub fn metrics(&self) -> Arc<PlaylistMetrics> {
+        self.metrics.clone()
+    }
+
+    /// get the live source of the playlist, None if the playlist does not accept source clients
+    pub fn get_live_source(&self) -> Option<Arc<LiveSource>> {
+        self.live_source.clone()
+    }
+
+    /// send a control command to the producer of the playlist,
+    ///     the command is applied before the next frame is prepared
+    pub async fn control(&self, control: PlaylistControl) -> anyhow::Result<()> {
+        match control {
+            PlaylistControl::Pause => self.paused.store(true, Ordering::Relaxed),
+            PlaylistControl::Resume => self.paused.store(false, Ordering::Relaxed),
+            _ => {}
         }
+        self.control
+            .send(control)
+            .await
+            .map_err(|_| anyhow::anyhow!("the playlist is finished"))
+    }
+
+    /// check if the playlist is paused
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// check if a source client is streaming to the playlist
+    pub fn is_live(&self) -> bool {
+        self.live_source
+            .as_ref()
+            .is_some_and(|live_source| live_source.is_connected())
     }
 
     /// get the content type of the playlist
@@ -98,8 +153,51 @@ impl Playlist {
         self.content_type.lock().await.clone()
     }
 
+    /// get the bitrate, sample rate and channels of the newest prepared frame
+    pub async fn get_audio_format(&self) -> Option<AudioFormat> {
+        let newest = self.newest_prepared_frames.lock().await;
+        let frame = &newest.frame_with_meta;
+        AudioFormat::of_chunk(
+            &frame.content_type,
+            &frame.frame,
+            frame.header.as_deref(),
+            frame.duration,
+        )
+    }
+
+    /// get the title and artist of the latest frame written to a listener
+    pub async fn get_now_playing(&self) -> (Arc<String>, Arc<String>) {
+        let now_playing = self.now_playing.lock().await;
+        (now_playing.1.clone(), now_playing.2.clone())
+    }
+
+    /// count a new connected listener, the listener is counted until the guard is dropped
+    pub fn connect_listener(&self) -> ListenerGuard {
+        self.listener_frame_data_db.connect_listener()
+    }
+
+    /// get the number of listeners currently connected
+    pub fn get_listeners(&self) -> usize {
+        self.listener_frame_data_db.get_listeners()
+    }
+
+    /// get the maximum number of listeners connected at the same time
+    pub fn get_listener_peak(&self) -> usize {
+        self.listener_frame_data_db.get_listener_peak()
+    }
+
     /// log the listener current frame
     pub async fn log_current_frame(&self, listener_id: &ListenerID, frame: PreparedFrame) {
+        let mut now_playing = self.now_playing.lock().await;
+        if frame.id > now_playing.0 {
+            *now_playing = (
+                frame.id,
+                frame.frame_with_meta.title.clone(),
+                frame.frame_with_meta.artist.clone(),
+            );
+        }
+        drop(now_playing);
+
         self.listener_frame_data_db
             .log_current_frame(listener_id, frame)
             .await;
@@ -130,7 +228,23 @@ impl Playlist {
         }
         drop(newest_prepared_frames);
 
-        let frame_with_meta = match self.child_recv.lock().await.recv().await {
+        let mut child_recv = self.child_recv.lock().await;
+        let frame_with_meta = match child_recv.try_recv() {
+            Ok(frame) => Some(frame),
+            Err(TryRecvError::Disconnected) => None,
+            Err(TryRecvError::Empty) => {
+                // the producer is behind the listeners
+                let start = Instant::now();
+                let frame = child_recv.recv().await;
+                self.metrics.producer_stalls.fetch_add(1, Ordering::Relaxed);
+                self.metrics
+                    .producer_stall_micros
+                    .fetch_add(start.elapsed().as_micros() as u64, Ordering::Relaxed);
+                frame
+            }
+        };
+        drop(child_recv);
+        let frame_with_meta = match frame_with_meta {
             Some(frame) => frame?,
             None => {
                 // the child is finished
@@ -149,9 +263,16 @@ impl Playlist {
         };
 
         let mut newest_prepared_frames = self.newest_prepared_frames.lock().await;
+        let previous = &newest_prepared_frames.frame_with_meta;
+        if previous.title != prepared_frame.frame_with_meta.title
+            || previous.artist != prepared_frame.frame_with_meta.artist
+        {
+            self.metrics.track_changes.fetch_add(1, Ordering::Relaxed);
+        }
         newest_prepared_frames
             .set_next(prepared_frame.clone())
             .await;
+        self.metrics.frames_prepared.fetch_add(1, Ordering::Relaxed);
         *newest_prepared_frames = prepared_frame;
 
         Ok(())
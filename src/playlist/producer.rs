@@ -0,0 +1,355 @@
+use std::{collections::VecDeque, pin::Pin, sync::Arc};
+
+use async_stream::stream;
+use futures::{Stream, StreamExt};
+use log::{error, warn};
+use tokio::sync::mpsc::{Receiver, Sender};
+
+use super::{FrameWithMeta, LiveSourceChild, PlaylistChild, Silent};
+
+/// maximum duration of audio in milliseconds dropped to skip a track,
+///     bounds the skip of the children never changing their track, e.g. `Silent`
+const MAX_SKIP_DURATION: f64 = 1000.0 * 60.0 * 60.0;
+
+type FrameStream = Pin<Box<dyn Stream<Item = anyhow::Result<FrameWithMeta>> + Send>>;
+
+/// the title and the artist of a track,
+///     the frames of a track share the same `Arc`s
+type Track = (Arc<String>, Arc<String>);
+
+/// A command sent to the producer of a playlist.
+pub enum PlaylistControl {
+    /// skip the rest of the current track
+    Skip,
+    /// play a track after the current one, before the next track of the child
+    Queue(Box<dyn PlaylistChild>),
+    /// stream silence instead of the child, until resumed
+    Pause,
+    /// resume the child where it was paused
+    Resume,
+    /// replace the child at its next track boundary, the current track is played to its end
+    Replace(Box<dyn PlaylistChild>),
+}
+
+/// stream the frames of a child, the stream owns the child
+fn owned_stream(mut child: Box<dyn PlaylistChild>) -> FrameStream {
+    Box::pin(stream! {
+        let mut s = match child.stream_frame_with_meta().await {
+            Ok(s) => s,
+            Err(e) => {
+                yield Err(e);
+                return;
+            }
+        };
+        while let Some(frame) = s.next().await {
+            yield frame;
+        }
+    })
+}
+
+fn same_track(frame: &FrameWithMeta, track: &Option<Track>) -> bool {
+    match track {
+        Some((title, artist)) => {
+            Arc::ptr_eq(&frame.title, title) && Arc::ptr_eq(&frame.artist, artist)
+        }
+        None => false,
+    }
+}
+
+/// Producer pulls the frames of the child of a playlist, and applies the control commands.
+struct Producer {
+    child: FrameStream,
+    silent: FrameStream,
+    queue: VecDeque<FrameStream>,
+    /// the queued track being played
+    queued: Option<FrameStream>,
+    /// the first frame of the next track of the child, held back while the queued tracks are played
+    pending: Option<anyhow::Result<FrameWithMeta>>,
+    paused: bool,
+    /// the track of the child being skipped
+    skipping: Option<Track>,
+    /// the track of the last frame of the child
+    current: Option<Track>,
+    /// the child taking over at the next track boundary
+    replacement: Option<FrameStream>,
+}
+
+impl Producer {
+    fn new(child: Box<dyn PlaylistChild>) -> Self {
+        Self {
+            child: owned_stream(child),
+            silent: owned_stream(Box::new(Silent {})),
+            queue: VecDeque::new(),
+            queued: None,
+            pending: None,
+            paused: false,
+            skipping: None,
+            current: None,
+            replacement: None,
+        }
+    }
+
+    fn control(&mut self, control: PlaylistControl) {
+        match control {
+            PlaylistControl::Skip => {
+                if self.queued.take().is_none() {
+                    self.skipping = self.current.clone();
+                }
+            }
+            PlaylistControl::Queue(child) => self.queue.push_back(owned_stream(child)),
+            PlaylistControl::Pause => self.paused = true,
+            PlaylistControl::Resume => self.paused = false,
+            PlaylistControl::Replace(child) => self.replacement = Some(owned_stream(child)),
+        }
+    }
+
+    /// Get the next frame to send, None if the child is finished.
+    async fn next_frame(&mut self) -> Option<anyhow::Result<FrameWithMeta>> {
+        if self.paused {
+            return self.silent.next().await;
+        }
+
+        let mut skipped = 0.0;
+        loop {
+            if let Some(queued) = self.queued.as_mut() {
+                match queued.next().await {
+                    Some(Ok(frame)) => return Some(Ok(frame)),
+                    Some(Err(e)) => {
+                        // a broken queued track must not end the playlist
+                        error!("failed to play the queued track: {}", e);
+                        self.queued = None;
+                    }
+                    None => self.queued = None,
+                }
+                continue;
+            }
+
+            let frame = match self.pending.take() {
+                Some(frame) => frame,
+                None => match self.child.next().await {
+                    Some(frame) => frame,
+                    // a finished child is replaced right away
+                    None => {
+                        self.child = self.replacement.take()?;
+                        continue;
+                    }
+                },
+            };
+            let Ok(f) = &frame else {
+                return Some(frame);
+            };
+
+            if self.skipping.is_some() {
+                if same_track(f, &self.skipping) && skipped < MAX_SKIP_DURATION {
+                    skipped += f.duration;
+                    continue;
+                }
+                self.skipping = None;
+            }
+
+            if !same_track(f, &self.current) {
+                // the first frame of the next track of the replaced child is dropped
+                if let Some(replacement) = self.replacement.take() {
+                    self.child = replacement;
+                    continue;
+                }
+                // the queued tracks are played between two tracks of the child
+                if let Some(queued) = self.queue.pop_front() {
+                    self.pending = Some(frame);
+                    self.queued = Some(queued);
+                    continue;
+                }
+                self.current = Some((f.title.clone(), f.artist.clone()));
+            }
+            return Some(frame);
+        }
+    }
+}
+
+enum Next {
+    Control(PlaylistControl),
+    Live(Option<anyhow::Result<FrameWithMeta>>),
+    Child(Option<anyhow::Result<FrameWithMeta>>),
+}
+
+/// Send the frames of the child to the playlist, until the child is finished
+///     or the playlist is dropped.
+/// A connected live source preempts the child, the control commands are applied between two frames.
+pub async fn produce(
+    child: Box<dyn PlaylistChild>,
+    mut live_child: Option<LiveSourceChild>,
+    sender: Sender<anyhow::Result<FrameWithMeta>>,
+    mut control_recv: Receiver<PlaylistControl>,
+) {
+    let mut producer = Producer::new(child);
+
+    loop {
+        // the live stream waits for the next source client,
+        //     and ends when the source client disconnects
+        let mut live_stream = match live_child.as_mut() {
+            Some(live_child) => match live_child.stream_frame_with_meta().await {
+                Ok(s) => Some(s),
+                Err(e) => {
+                    error!("failed to stream live source: {}", e);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        // once the source client starts streaming, the live stream preempts the child
+        //     until the source client disconnects
+        let mut on_live = false;
+        loop {
+            let next = match live_stream.as_mut() {
+                Some(live) => tokio::select! {
+                    biased;
+                    Some(control) = control_recv.recv() => Next::Control(control),
+                    frame = live.next() => Next::Live(frame),
+                    frame = producer.next_frame(), if !on_live => Next::Child(frame),
+                },
+                None => tokio::select! {
+                    biased;
+                    Some(control) = control_recv.recv() => Next::Control(control),
+                    frame = producer.next_frame() => Next::Child(frame),
+                },
+            };
+            let frame_with_meta = match next {
+                Next::Control(PlaylistControl::Skip) if on_live => {
+                    warn!("the live source can not be skipped");
+                    continue;
+                }
+                Next::Control(control) => {
+                    producer.control(control);
+                    continue;
+                }
+                Next::Live(Some(frame)) => {
+                    on_live = true;
+                    frame
+                }
+                Next::Live(None) => break,
+                Next::Child(Some(frame)) => frame,
+                // the stream is finished, the sender is dropped
+                Next::Child(None) => return,
+            };
+            if let Err(e) = sender.send(frame_with_meta).await {
+                // the receiver is dropped, we should stop the stream
+                error!("failed to send frame_with_meta: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+
+    use super::*;
+
+    /// MockTracks yields the given number of frames for each track,
+    ///     the frames of a track share their title
+    struct MockTracks {
+        tracks: Vec<(&'static str, usize)>,
+    }
+
+    impl MockTracks {
+        fn boxed(tracks: Vec<(&'static str, usize)>) -> Box<dyn PlaylistChild> {
+            Box::new(Self { tracks })
+        }
+    }
+
+    #[async_trait]
+    impl PlaylistChild for MockTracks {
+        async fn is_finished(&mut self) -> anyhow::Result<bool> {
+            Ok(false)
+        }
+
+        async fn stream_frame_with_meta(
+            &'_ mut self,
+        ) -> anyhow::Result<
+            std::pin::Pin<
+                Box<dyn futures::Stream<Item = anyhow::Result<FrameWithMeta>> + Send + '_>,
+            >,
+        > {
+            let s = stream! {
+                for (title, frames) in self.tracks.clone() {
+                    let title = Arc::new(title.to_string());
+                    let artist = Arc::new("".to_string());
+                    for _ in 0..frames {
+                        yield Ok(FrameWithMeta {
+                            frame: bytes::Bytes::new(),
+                            header: None,
+                            title: title.clone(),
+                            artist: artist.clone(),
+                            content_type: Arc::new("audio/mpeg".to_string()),
+                            duration: 1000.0,
+                        });
+                    }
+                }
+            };
+
+            Ok(Box::pin(s))
+        }
+    }
+
+    async fn next_title(producer: &mut Producer) -> Option<String> {
+        let frame = producer.next_frame().await?;
+        Some(frame.unwrap().title.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_skip() {
+        let mut producer = Producer::new(MockTracks::boxed(vec![("a", 3), ("b", 2)]));
+        assert_eq!(next_title(&mut producer).await.as_deref(), Some("a"));
+
+        producer.control(PlaylistControl::Skip);
+        assert_eq!(next_title(&mut producer).await.as_deref(), Some("b"));
+        assert_eq!(next_title(&mut producer).await.as_deref(), Some("b"));
+        assert_eq!(next_title(&mut producer).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_queue_after_current_track() {
+        let mut producer = Producer::new(MockTracks::boxed(vec![("a", 2), ("b", 1)]));
+        assert_eq!(next_title(&mut producer).await.as_deref(), Some("a"));
+
+        producer.control(PlaylistControl::Queue(MockTracks::boxed(vec![("q", 3)])));
+        assert_eq!(next_title(&mut producer).await.as_deref(), Some("a"));
+        assert_eq!(next_title(&mut producer).await.as_deref(), Some("q"));
+
+        // skipping the queued track goes back to the child
+        producer.control(PlaylistControl::Skip);
+        assert_eq!(next_title(&mut producer).await.as_deref(), Some("b"));
+        assert_eq!(next_title(&mut producer).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_replace_at_track_boundary() {
+        let mut producer = Producer::new(MockTracks::boxed(vec![("a", 2), ("b", 1)]));
+        assert_eq!(next_title(&mut producer).await.as_deref(), Some("a"));
+
+        producer.control(PlaylistControl::Replace(MockTracks::boxed(vec![("c", 1)])));
+        assert_eq!(next_title(&mut producer).await.as_deref(), Some("a"));
+        assert_eq!(next_title(&mut producer).await.as_deref(), Some("c"));
+        assert_eq!(next_title(&mut producer).await, None);
+
+        // a finished child is replaced right away
+        producer.control(PlaylistControl::Replace(MockTracks::boxed(vec![("d", 1)])));
+        assert_eq!(next_title(&mut producer).await.as_deref(), Some("d"));
+    }
+
+    #[tokio::test]
+    async fn test_pause_and_resume() {
+        let mut producer = Producer::new(MockTracks::boxed(vec![("a", 2)]));
+        assert_eq!(next_title(&mut producer).await.as_deref(), Some("a"));
+
+        producer.control(PlaylistControl::Pause);
+        assert_eq!(next_title(&mut producer).await.as_deref(), Some("Silent"));
+        assert_eq!(next_title(&mut producer).await.as_deref(), Some("Silent"));
+
+        producer.control(PlaylistControl::Resume);
+        assert_eq!(next_title(&mut producer).await.as_deref(), Some("a"));
+        assert_eq!(next_title(&mut producer).await, None);
+    }
+}
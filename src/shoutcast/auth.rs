@@ -0,0 +1,370 @@
+use std::{collections::HashMap, net::IpAddr};
+
+use base64::Engine;
+use http::Request;
+use ipnet::IpNet;
+use md5::{Digest, Md5};
+use sha1::Sha1;
+use subtle::ConstantTimeEq;
+
+use crate::config::{OutputAuthConfig, OutputType};
+
+use super::{basic_auth, response::text_response};
+
+/// name of the query parameter carrying the token of a listener
+const TOKEN_QUERY_PARAMETER: &str = "token";
+
+/// realm of the `WWW-Authenticate` header sent to the listeners
+const LISTENER_REALM: &str = "RustCast";
+
+/// the alphabet of the crypt base64 encoding
+const CRYPT_BASE64: &[u8; 64] = b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// A password of an htpasswd file or of the configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Password {
+    Plain(String),
+    /// `$2a$`, `$2b$` or `$2y$`
+    Bcrypt(String),
+    /// `{SHA}` followed by the base64 SHA1 digest
+    Sha1(Vec<u8>),
+    /// `$apr1$salt$hash`
+    Apr1 {
+        salt: String,
+        hash: String,
+    },
+}
+
+impl Password {
+    /// Parse a password of an htpasswd file,
+    ///     a password without a known prefix is a plain text password.
+    fn parse_htpasswd(password: &str) -> Self {
+        if password.starts_with("$2a$")
+            || password.starts_with("$2b$")
+            || password.starts_with("$2y$")
+        {
+            return Self::Bcrypt(password.to_string());
+        }
+        if let Some(digest) = password.strip_prefix("{SHA}")
+            && let Ok(digest) = base64::engine::general_purpose::STANDARD.decode(digest)
+        {
+            return Self::Sha1(digest);
+        }
+        if let Some(rest) = password.strip_prefix("$apr1$")
+            && let Some((salt, hash)) = rest.split_once('$')
+        {
+            return Self::Apr1 {
+                salt: salt.to_string(),
+                hash: hash.to_string(),
+            };
+        }
+        Self::Plain(password.to_string())
+    }
+
+    /// Check a password, the comparisons take the same time whatever the differences.
+    fn verify(&self, password: &str) -> bool {
+        match self {
+            Self::Plain(expected) => constant_time_eq(expected.as_bytes(), password.as_bytes()),
+            Self::Bcrypt(hash) => bcrypt::verify(password, hash).unwrap_or(false),
+            Self::Sha1(digest) => constant_time_eq(&Sha1::digest(password.as_bytes()), digest),
+            Self::Apr1 { salt, hash } => constant_time_eq(
+                apr1(password.as_bytes(), salt.as_bytes()).as_bytes(),
+                hash.as_bytes(),
+            ),
+        }
+    }
+
+    /// Whether the verification takes milliseconds of CPU, bcrypt and apr1 are slow on purpose.
+    fn is_slow(&self) -> bool {
+        matches!(self, Self::Bcrypt(_) | Self::Apr1 { .. })
+    }
+}
+
+/// Compare two secrets in a time independent of their content.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+/// The hash of the Apache MD5 crypt (`$apr1$`), without the magic and the salt.
+fn apr1(password: &[u8], salt: &[u8]) -> String {
+    const MAGIC: &[u8] = b"$apr1$";
+    let salt = &salt[..salt.len().min(8)];
+
+    let alternate = Md5::new()
+        .chain_update(password)
+        .chain_update(salt)
+        .chain_update(password)
+        .finalize();
+
+    let mut ctx = Md5::new()
+        .chain_update(password)
+        .chain_update(MAGIC)
+        .chain_update(salt);
+    for chunk in (0..password.len()).step_by(16) {
+        ctx.update(&alternate[..(password.len() - chunk).min(16)]);
+    }
+    let mut i = password.len();
+    while i > 0 {
+        if i & 1 == 1 {
+            ctx.update([0]);
+        } else {
+            ctx.update(&password[..1]);
+        }
+        i >>= 1;
+    }
+    let mut digest = ctx.finalize();
+
+    for round in 0..1000 {
+        let mut ctx = Md5::new();
+        if round & 1 == 1 {
+            ctx.update(password);
+        } else {
+            ctx.update(digest);
+        }
+        if round % 3 != 0 {
+            ctx.update(salt);
+        }
+        if round % 7 != 0 {
+            ctx.update(password);
+        }
+        if round & 1 == 1 {
+            ctx.update(digest);
+        } else {
+            ctx.update(password);
+        }
+        digest = ctx.finalize();
+    }
+
+    let mut res = String::with_capacity(22);
+    let mut push = |mut value: u32, n: usize| {
+        for _ in 0..n {
+            res.push(CRYPT_BASE64[(value & 0x3f) as usize] as char);
+            value >>= 6;
+        }
+    };
+    for (a, b, c) in [(0, 6, 12), (1, 7, 13), (2, 8, 14), (3, 9, 15), (4, 10, 5)] {
+        push(
+            (digest[a] as u32) << 16 | (digest[b] as u32) << 8 | digest[c] as u32,
+            4,
+        );
+    }
+    push(digest[11] as u32, 2);
+    res
+}
+
+/// Parse the lines `user:password` of an htpasswd file.
+fn parse_htpasswd(content: &str) -> HashMap<String, Password> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once(':'))
+        .map(|(user, password)| (user.to_string(), Password::parse_htpasswd(password)))
+        .collect()
+}
+
+/// get the value of a query parameter of the request
+fn query_parameter(request: &Request<()>, name: &str) -> Option<String> {
+    request.uri().query()?.split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        if key != name {
+            return None;
+        }
+        percent_encoding::percent_decode_str(&value.replace('+', " "))
+            .decode_utf8()
+            .ok()
+            .map(|value| value.into_owned())
+    })
+}
+
+/// The result of the access control of a listener.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthResult {
+    Allowed,
+    /// the listener did not give valid credentials, `401 Unauthorized`
+    Unauthorized,
+    /// the IP of the listener is not allowed, `403 Forbidden`
+    Forbidden,
+}
+
+/// OutputAuth checks the listeners of an output.
+#[derive(Debug)]
+pub struct OutputAuth {
+    users: HashMap<String, Password>,
+    tokens: Vec<String>,
+    allow: Vec<IpNet>,
+    deny: Vec<IpNet>,
+}
+
+impl OutputAuth {
+    /// Build the access control of an output, the htpasswd file is read once.
+    /// The users of the configuration take precedence over the users of the htpasswd file.
+    pub async fn from_config(config: OutputAuthConfig) -> anyhow::Result<Self> {
+        let OutputAuthConfig {
+            users,
+            htpasswd,
+            tokens,
+            allow,
+            deny,
+        } = config;
+        let mut all_users = HashMap::new();
+        if let Some(htpasswd) = htpasswd {
+            let content = tokio::fs::read_to_string(&htpasswd)
+                .await
+                .map_err(|e| anyhow::anyhow!("failed to read htpasswd file {htpasswd}: {e}"))?;
+            all_users = parse_htpasswd(&content);
+        }
+        all_users.extend(
+            users
+                .into_iter()
+                .map(|(user, password)| (user, Password::Plain(password))),
+        );
+
+        Ok(Self {
+            users: all_users,
+            tokens,
+            allow,
+            deny,
+        })
+    }
+
+    /// Check a listener by its IP, then by its token or its user.
+    ///
+    /// The slow password hashes are verified on a blocking thread,
+    ///     not to delay the frames of the other listeners.
+    pub async fn check(&self, ip: IpAddr, request: &Request<()>) -> AuthResult {
+        // an IPv4 listener of a dual stack listener is seen as an IPv4-mapped IPv6 address
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            IpAddr::V4(_) => ip,
+        };
+        if self.deny.iter().any(|network| network.contains(&ip)) {
+            return AuthResult::Forbidden;
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|network| network.contains(&ip)) {
+            return AuthResult::Forbidden;
+        }
+
+        if self.users.is_empty() && self.tokens.is_empty() {
+            return AuthResult::Allowed;
+        }
+        if let Some(token) = query_parameter(request, TOKEN_QUERY_PARAMETER)
+            && self
+                .tokens
+                .iter()
+                .any(|expected| constant_time_eq(expected.as_bytes(), token.as_bytes()))
+        {
+            return AuthResult::Allowed;
+        }
+        if let Some((user, password)) = basic_auth(request)
+            && let Some(expected) = self.users.get(&user)
+        {
+            let verified = if expected.is_slow() {
+                let expected = expected.clone();
+                tokio::task::spawn_blocking(move || expected.verify(&password))
+                    .await
+                    .unwrap_or(false)
+            } else {
+                expected.verify(&password)
+            };
+            if verified {
+                return AuthResult::Allowed;
+            }
+        }
+        AuthResult::Unauthorized
+    }
+}
+
+/// The response sent to a listener refused by the access control.
+pub fn refused_response(result: AuthResult, output_type: OutputType, head: bool) -> String {
+    match result {
+        AuthResult::Unauthorized => text_response(
+            output_type,
+            "401 Unauthorized",
+            &format!("WWW-Authenticate: Basic realm=\"{LISTENER_REALM}\"\r\n"),
+            "Authentication required",
+            head,
+        ),
+        _ => text_response(output_type, "403 Forbidden", "", "", head),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(uri: &str, auth: Option<&str>) -> Request<()> {
+        let mut request = Request::builder().uri(uri);
+        if let Some(auth) = auth {
+            let encoded = base64::engine::general_purpose::STANDARD.encode(auth);
+            request = request.header(http::header::AUTHORIZATION, format!("Basic {encoded}"));
+        }
+        request.body(()).unwrap()
+    }
+
+    #[test]
+    fn test_htpasswd_passwords() {
+        let users = parse_htpasswd(
+            "# comment\n\
+            apr:$apr1$abcdefgh$h9FWgUz3n9YxylKLlR5SQ/\n\
+            sha:{SHA}5en6G6MezRroT3XKqkdPOmY/BfQ=\n\
+            bcrypt:$2b$04$abcdefghijklmnopqrstuu2r9OfJnfCsdneAXAGHnS4UpFFP8WIrW\n\
+            plain:secret\n",
+        );
+        assert_eq!(users.len(), 4);
+        for password in users.values() {
+            assert!(password.verify("secret"), "{password:?}");
+            assert!(!password.verify("wrong"), "{password:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check() {
+        let config = OutputAuthConfig {
+            users: HashMap::from([("alice".to_string(), "secret".to_string())]),
+            tokens: vec!["a b".to_string()],
+            allow: vec!["10.0.0.0/8".parse().unwrap()],
+            deny: vec!["10.0.0.1/32".parse().unwrap()],
+            ..Default::default()
+        };
+        let auth = OutputAuth::from_config(config).await.unwrap();
+        let ip: IpAddr = "10.1.2.3".parse().unwrap();
+
+        assert_eq!(
+            auth.check(ip, &request("/stream", Some("alice:secret")))
+                .await,
+            AuthResult::Allowed
+        );
+        assert_eq!(
+            auth.check(ip, &request("/stream?x=1&token=a%20b", None))
+                .await,
+            AuthResult::Allowed
+        );
+        assert_eq!(
+            auth.check(ip, &request("/stream?token=bad", Some("alice:bad")))
+                .await,
+            AuthResult::Unauthorized
+        );
+        assert_eq!(
+            auth.check(
+                "10.0.0.1".parse().unwrap(),
+                &request("/", Some("alice:secret"))
+            )
+            .await,
+            AuthResult::Forbidden
+        );
+        assert_eq!(
+            auth.check("::ffff:192.168.0.1".parse().unwrap(), &request("/", None))
+                .await,
+            AuthResult::Forbidden
+        );
+        assert_eq!(
+            auth.check(
+                "::ffff:10.1.2.3".parse().unwrap(),
+                &request("/?token=a+b", None)
+            )
+            .await,
+            AuthResult::Allowed
+        );
+    }
+}
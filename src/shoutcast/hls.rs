@@ -0,0 +1,405 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use bytes::{BufMut, Bytes, BytesMut};
+use log::{error, info, warn};
+use tokio_stream::StreamExt;
+
+use crate::{
+    CONTEXT,
+    audio::MP3_CONTENT_TYPE,
+    config::{HlsConfig, OutputType},
+    playlist::{FrameWithMeta, Playlist, PlaylistFrameStream},
+};
+
+use super::{
+    ListenerID,
+    response::{response_head, text_response},
+};
+
+/// name of the media playlist in the mount path
+const MEDIA_PLAYLIST: &str = "index.m3u8";
+
+const MEDIA_PLAYLIST_CONTENT_TYPE: &str = "application/vnd.apple.mpegurl";
+
+/// extension of the segments, only MPEG audio can be packed in raw segments
+const SEGMENT_EXTENSION: &str = "mp3";
+
+/// segments kept after they leave the media playlist,
+///     for the clients still downloading them
+const EXTRA_SEGMENTS: usize = 2;
+
+/// owner identifier of the ID3 `PRIV` frame carrying the timestamp of a packed audio segment
+const TIMESTAMP_OWNER: &[u8] = b"com.apple.streaming.transportStreamTimestamp\0";
+
+/// A segment of audio frames, starting with an ID3 tag.
+struct Segment {
+    sequence: u64,
+    /// duration in milliseconds
+    duration: f64,
+    title: Arc<String>,
+    artist: Arc<String>,
+    data: Bytes,
+}
+
+#[derive(Default)]
+struct HlsState {
+    segments: VecDeque<Segment>,
+    /// the playlist is finished, no segment will be added
+    ended: bool,
+}
+
+/// A segment being cut from the frames of the playlist.
+struct SegmentBuilder {
+    data: BytesMut,
+    duration: f64,
+    title: Arc<String>,
+    artist: Arc<String>,
+}
+
+impl SegmentBuilder {
+    /// start a segment at `position` milliseconds of the stream with its first frame
+    fn new(position: f64, frame: &FrameWithMeta) -> Self {
+        let mut data = BytesMut::new();
+        data.put_slice(&id3_tag(position, &frame.title, &frame.artist));
+        Self {
+            data,
+            duration: 0.0,
+            title: frame.title.clone(),
+            artist: frame.artist.clone(),
+        }
+    }
+
+    /// whether the frame belongs to the segment,
+    ///     a segment ends before the target duration is exceeded and at a track change
+    fn accepts(&self, frame: &FrameWithMeta, target_duration: f64) -> bool {
+        // the duration of a segment rounded to the nearest second must not exceed the target
+        self.duration + frame.duration < target_duration + 500.0
+            && self.title == frame.title
+            && self.artist == frame.artist
+    }
+
+    fn push(&mut self, frame: &FrameWithMeta) {
+        self.data.put_slice(&frame.frame);
+        self.duration += frame.duration;
+    }
+}
+
+/// Hls cuts the frames of a playlist into segments kept in memory,
+///     and serves the rolling media playlist and the segments of a mount.
+pub struct Hls {
+    /// target duration of a segment in milliseconds
+    target_duration: f64,
+    /// number of segments listed in the media playlist
+    window: usize,
+    state: RwLock<HlsState>,
+    /// the mount is removed, the segmenter stops after the current frame
+    retired: AtomicBool,
+}
+
+impl Hls {
+    pub fn new(config: HlsConfig) -> Self {
+        Self {
+            target_duration: config.segment_duration.max(1.0) * 1000.0,
+            window: config.segments.max(1),
+            state: Default::default(),
+            retired: AtomicBool::new(false),
+        }
+    }
+
+    /// stop cutting segments, the media playlist is ended
+    pub fn retire(&self) {
+        self.retired.store(true, Ordering::Relaxed);
+    }
+
+    /// Cut the frames of the playlist into segments until the playlist is finished,
+    ///     the frames are streamed in real time as for a listener.
+    pub async fn run(self: Arc<Self>, playlist: Arc<Playlist>) {
+        let id = ListenerID {
+            listener_id: CONTEXT.get_id().await,
+            session_id: None,
+        };
+        let mut frames = PlaylistFrameStream::new(playlist.clone(), &id).await;
+        // the position of the next segment in the stream, in milliseconds
+        let mut position = 0.0;
+        let mut sequence = 0;
+        let mut builder: Option<SegmentBuilder> = None;
+        let mut unsupported = false;
+
+        // the media playlist is ended when the server shuts down
+        while !self.retired.load(Ordering::Relaxed) && !CONTEXT.shutdown.is_cancelled() {
+            let frame = match frames.next().await {
+                Some(Ok(frame)) => frame,
+                Some(Err(e)) => {
+                    error!("failed to segment the playlist {}: {}", playlist.name, e);
+                    break;
+                }
+                None => break,
+            };
+            playlist.log_current_frame(&id, frame.clone()).await;
+            let frame = frame.frame_with_meta;
+
+            if frame.content_type.as_str() != MP3_CONTENT_TYPE {
+                if !unsupported {
+                    warn!(
+                        "HLS only supports {MP3_CONTENT_TYPE}, the {} frames of the playlist {} are dropped",
+                        frame.content_type, playlist.name
+                    );
+                    unsupported = true;
+                }
+                continue;
+            }
+            unsupported = false;
+
+            if let Some(segment) = builder.take_if(|b| !b.accepts(&frame, self.target_duration)) {
+                position += segment.duration;
+                self.publish(sequence, segment);
+                sequence += 1;
+            }
+            builder
+                .get_or_insert_with(|| SegmentBuilder::new(position, &frame))
+                .push(&frame);
+        }
+
+        if let Some(segment) = builder {
+            self.publish(sequence, segment);
+        }
+        self.state.write().unwrap().ended = true;
+        info!("HLS segmenter of playlist {} finished", playlist.name);
+    }
+
+    fn publish(&self, sequence: u64, segment: SegmentBuilder) {
+        let mut state = self.state.write().unwrap();
+        state.segments.push_back(Segment {
+            sequence,
+            duration: segment.duration,
+            title: segment.title,
+            artist: segment.artist,
+            data: segment.data.freeze(),
+        });
+        while state.segments.len() > self.window + EXTRA_SEGMENTS {
+            state.segments.pop_front();
+        }
+    }
+
+    /// Build the media playlist, None if no segment is cut yet.
+    /// `prefix` is the path of the segments, `query` is appended to their URI.
+    fn media_playlist(&self, prefix: &str, query: Option<&str>) -> Option<String> {
+        let state = self.state.read().unwrap();
+        let skip = state.segments.len().saturating_sub(self.window);
+        let first = state.segments.get(skip)?;
+
+        let mut res = format!(
+            "#EXTM3U\n\
+            #EXT-X-VERSION:3\n\
+            #EXT-X-TARGETDURATION:{}\n\
+            #EXT-X-MEDIA-SEQUENCE:{}\n",
+            (self.target_duration / 1000.0).ceil() as u64,
+            first.sequence,
+        );
+        let query = query.map(|query| format!("?{query}")).unwrap_or_default();
+        for segment in state.segments.iter().skip(skip) {
+            let title =
+                format!("{} - {}", segment.artist, segment.title).replace(['\r', '\n'], " ");
+            res.push_str(&format!(
+                "#EXTINF:{:.3},{title}\n{prefix}{}.{SEGMENT_EXTENSION}{query}\n",
+                segment.duration / 1000.0,
+                segment.sequence,
+            ));
+        }
+        if state.ended {
+            res.push_str("#EXT-X-ENDLIST\n");
+        }
+        Some(res)
+    }
+
+    fn segment(&self, sequence: u64) -> Option<Bytes> {
+        let state = self.state.read().unwrap();
+        let first = state.segments.front()?.sequence;
+        let i = sequence.checked_sub(first)?;
+        state
+            .segments
+            .get(i as usize)
+            .map(|segment| segment.data.clone())
+    }
+
+    /// The response to a request of the mount at `mount_path`,
+    ///     `file` is the media playlist or a segment, the body is empty for `HEAD` requests.
+    pub fn response(
+        &self,
+        mount_path: &str,
+        file: &str,
+        query: Option<&str>,
+        head: bool,
+    ) -> (String, Bytes) {
+        let (content_type, body) = if file.is_empty() || file == MEDIA_PLAYLIST {
+            let prefix = if mount_path.is_empty() {
+                "/".to_string()
+            } else {
+                format!("/{mount_path}/")
+            };
+            match self.media_playlist(&prefix, query) {
+                Some(playlist) => (MEDIA_PLAYLIST_CONTENT_TYPE, Bytes::from(playlist)),
+                None => {
+                    let response = text_response(
+                        OutputType::Hls,
+                        "503 Service Unavailable",
+                        "Retry-After: 1\r\n",
+                        "The stream is not available yet",
+                        head,
+                    );
+                    return (response, Bytes::new());
+                }
+            }
+        } else {
+            let segment = file
+                .strip_suffix(SEGMENT_EXTENSION)
+                .and_then(|file| file.strip_suffix('.'))
+                .and_then(|sequence| sequence.parse().ok())
+                .and_then(|sequence| self.segment(sequence));
+            match segment {
+                Some(segment) => (MP3_CONTENT_TYPE, segment),
+                None => {
+                    let response = text_response(
+                        OutputType::Hls,
+                        "404 Not Found",
+                        "",
+                        &format!("Segment not found: /{mount_path}/{file}"),
+                        head,
+                    );
+                    return (response, Bytes::new());
+                }
+            }
+        };
+
+        let response = response_head(OutputType::Hls, "200 OK", "", content_type, body.len());
+        (response, if head { Bytes::new() } else { body })
+    }
+}
+
+fn syncsafe(size: usize) -> [u8; 4] {
+    [
+        (size >> 21 & 0x7f) as u8,
+        (size >> 14 & 0x7f) as u8,
+        (size >> 7 & 0x7f) as u8,
+        (size & 0x7f) as u8,
+    ]
+}
+
+fn id3_frame(tag: &mut Vec<u8>, id: &[u8; 4], content: &[u8]) {
+    tag.extend_from_slice(id);
+    tag.extend_from_slice(&syncsafe(content.len()));
+    tag.extend_from_slice(&[0, 0]);
+    tag.extend_from_slice(content);
+}
+
+/// The ID3v2.4 tag starting a packed audio segment, with the timestamp of the segment
+///     and the title and the artist of its track as timed metadata.
+/// `position` is the position of the segment in the stream in milliseconds.
+fn id3_tag(position: f64, title: &str, artist: &str) -> Vec<u8> {
+    let mut frames = Vec::new();
+
+    // the 33 bits MPEG-2 timestamp with a 90 kHz clock, in 8 big endian bytes
+    let timestamp = (position * 90.0) as u64 & 0x1_ffff_ffff;
+    let mut content = TIMESTAMP_OWNER.to_vec();
+    content.extend_from_slice(&timestamp.to_be_bytes());
+    id3_frame(&mut frames, b"PRIV", &content);
+
+    for (id, text) in [(b"TIT2", title), (b"TPE1", artist)] {
+        if !text.is_empty() {
+            // 3 is the UTF-8 encoding
+            let mut content = vec![3];
+            content.extend_from_slice(text.as_bytes());
+            id3_frame(&mut frames, id, &content);
+        }
+    }
+
+    let mut tag = b"ID3\x04\x00\x00".to_vec();
+    tag.extend_from_slice(&syncsafe(frames.len()));
+    tag.extend_from_slice(&frames);
+    tag
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(title: &str, duration: f64) -> FrameWithMeta {
+        FrameWithMeta {
+            frame: Bytes::from_static(b"\xff\xfb"),
+            header: None,
+            title: Arc::new(title.to_string()),
+            artist: Arc::new("Artist".to_string()),
+            content_type: Arc::new(MP3_CONTENT_TYPE.to_string()),
+            duration,
+        }
+    }
+
+    #[test]
+    fn test_id3_tag() {
+        let tag = id3_tag(1000.0, "Song", "");
+        assert_eq!(&tag[..6], b"ID3\x04\x00\x00");
+        assert_eq!(tag[6..10], syncsafe(tag.len() - 10));
+        assert_eq!(&tag[10..14], b"PRIV");
+        let timestamp_end = 20 + TIMESTAMP_OWNER.len() + 8;
+        assert_eq!(
+            &tag[timestamp_end - 8..timestamp_end],
+            &90000u64.to_be_bytes()
+        );
+        assert_eq!(&tag[timestamp_end..timestamp_end + 4], b"TIT2");
+        assert_eq!(&tag[timestamp_end + 10..], b"\x03Song");
+
+        assert_eq!(syncsafe(0x3fff), [0, 0, 0x7f, 0x7f]);
+    }
+
+    #[test]
+    fn test_media_playlist() {
+        let hls = Hls::new(HlsConfig {
+            segment_duration: 2.0,
+            segments: 2,
+        });
+        assert_eq!(hls.media_playlist("/hls/", None), None);
+
+        let mut builder = SegmentBuilder::new(0.0, &frame("a", 1000.0));
+        assert!(builder.accepts(&frame("a", 1000.0), hls.target_duration));
+        builder.push(&frame("a", 1000.0));
+        builder.push(&frame("a", 1000.0));
+        assert!(!builder.accepts(&frame("a", 1000.0), hls.target_duration));
+        assert!(!builder.accepts(&frame("b", 10.0), hls.target_duration));
+        for sequence in 0..5 {
+            let segment = SegmentBuilder {
+                data: builder.data.clone(),
+                duration: builder.duration,
+                title: builder.title.clone(),
+                artist: builder.artist.clone(),
+            };
+            hls.publish(sequence, segment);
+        }
+
+        assert_eq!(
+            hls.media_playlist("/hls/", Some("token=abc")).unwrap(),
+            "#EXTM3U\n\
+            #EXT-X-VERSION:3\n\
+            #EXT-X-TARGETDURATION:2\n\
+            #EXT-X-MEDIA-SEQUENCE:3\n\
+            #EXTINF:2.000,Artist - a\n\
+            /hls/3.mp3?token=abc\n\
+            #EXTINF:2.000,Artist - a\n\
+            /hls/4.mp3?token=abc\n"
+        );
+        // the segments leaving the media playlist are still served
+        assert!(hls.segment(0).is_none());
+        assert!(hls.segment(1).is_some());
+        assert!(hls.segment(5).is_none());
+        let (response, body) = hls.response("hls", "1.mp3", None, false);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(body.starts_with(b"ID3"));
+        let (response, _) = hls.response("hls", "x.mp3", None, false);
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+}
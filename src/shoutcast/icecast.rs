@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+
+use serde_json::{Value, json};
+
+use crate::{CONTEXT, audio::AudioFormat};
+
+use super::{META_DATA_INTERVAL_STR, Mount};
+
+/// Server header of the Icecast output,
+/// Icecast tooling checks for the `Icecast` prefix
+pub const ICECAST_SERVER: &str = concat!(
+    "Icecast 2.4.4 compatible (RustCast ",
+    env!("CARGO_PKG_VERSION"),
+    ")"
+);
+
+/// path of the Icecast status page, without the leading `/`
+pub const STATUS_JSON_PATH: &str = "status-json.xsl";
+
+/// format the time in the format of the HTTP `Date` header
+fn http_date() -> String {
+    chrono::Utc::now()
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string()
+}
+
+/// Headers shared by all the Icecast responses, without the status line and the ending `\r\n`.
+fn common_headers() -> String {
+    format!(
+        "Server: {}\r\n\
+        Date: {}\r\n\
+        Cache-Control: no-cache, no-store\r\n\
+        Pragma: no-cache\r\n\
+        Expires: Mon, 26 Jul 1997 05:00:00 GMT\r\n\
+        Connection: Close\r\n\
+        Access-Control-Allow-Origin: *\r\n\
+        Access-Control-Allow-Headers: Origin, Accept, X-Requested-With, Content-Type, Icy-MetaData\r\n\
+        Access-Control-Allow-Methods: GET, OPTIONS, HEAD\r\n",
+        ICECAST_SERVER,
+        http_date(),
+    )
+}
+
+/// The response head sent to an Icecast listener before the stream,
+///     `audio_format` is advertised in `ice-audio-info` and `icy-br` if it is known.
+pub fn stream_start_response(
+    content_type: &str,
+    stream_name: &str,
+    audio_format: Option<AudioFormat>,
+    meta_data_support: bool,
+) -> String {
+    let mut res = format!(
+        "HTTP/1.1 200 OK\r\n\
+        {}\
+        Content-Type: {}\r\n\
+        icy-name: {}\r\n\
+        icy-description: {}\r\n\
+        icy-pub: 0\r\n",
+        common_headers(),
+        content_type,
+        stream_name,
+        stream_name,
+    );
+    if let Some(format) = audio_format {
+        let mut audio_info = vec![format!("ice-bitrate={}", format.bitrate)];
+        if let Some(sample_rate) = format.sample_rate {
+            audio_info.push(format!("ice-samplerate={sample_rate}"));
+        }
+        if let Some(channels) = format.channels {
+            audio_info.push(format!("ice-channels={channels}"));
+        }
+        res.push_str(&format!(
+            "ice-audio-info: {}\r\nicy-br: {}\r\n",
+            audio_info.join(";"),
+            format.bitrate
+        ));
+    }
+    if meta_data_support {
+        res.push_str("icy-metaint: ");
+        res.push_str(META_DATA_INTERVAL_STR);
+        res.push_str("\r\n");
+    }
+    res.push_str("\r\n");
+    res
+}
+
+/// The full response of `/status-json.xsl`, the body is omitted for `HEAD` requests.
+pub fn status_json_response(body: &str, head: bool) -> String {
+    let mut res = format!(
+        "HTTP/1.1 200 OK\r\n\
+        {}\
+        Content-Type: application/json; charset=utf-8\r\n\
+        Content-Length: {}\r\n\
+        \r\n",
+        common_headers(),
+        body.len(),
+    );
+    if !head {
+        res.push_str(body);
+    }
+    res
+}
+
+/// The head of a response with a body, `extra_headers` are added after the common headers
+///     and end with `\r\n`.
+pub fn response_head(
+    status: &str,
+    extra_headers: &str,
+    content_type: &str,
+    content_length: usize,
+) -> String {
+    format!(
+        "HTTP/1.1 {status}\r\n\
+        {}\
+        {extra_headers}\
+        Content-Type: {content_type}\r\n\
+        Content-Length: {content_length}\r\n\
+        \r\n",
+        common_headers(),
+    )
+}
+
+/// Build the body of `/status-json.xsl` in the shape of Icecast,
+///     `source` is an object if there is only one mount, and an array otherwise.
+/// The mounts with access control are not listed.
+/// `host` is the `host:port` the listeners use to connect to the server.
+pub async fn status_json(host: &str, mounts: &HashMap<String, Mount>) -> String {
+    let mut paths = mounts
+        .iter()
+        .filter(|(_, mount)| mount.auth.is_none())
+        .map(|(path, _)| path)
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    let mut sources = Vec::with_capacity(paths.len());
+    for path in paths {
+        let playlist = &mounts[path].playlist;
+        let (title, artist) = playlist.get_now_playing().await;
+        sources.push(json!({
+            "audio_info": Value::Null,
+            "genre": "various",
+            "listener_peak": playlist.get_listener_peak(),
+            "listeners": playlist.get_listeners(),
+            "listenurl": format!("http://{}/{}", host, path),
+            "server_description": playlist.name.as_str(),
+            "server_name": playlist.name.as_str(),
+            "server_type": playlist.get_content_type().await.as_str(),
+            "server_url": format!("http://{}/", host),
+            "stream_start": playlist.start_time.to_rfc2822(),
+            "stream_start_iso8601": playlist.start_time.format("%Y-%m-%dT%H:%M:%S%z").to_string(),
+            "title": title.as_str(),
+            "artist": artist.as_str(),
+            "dummy": Value::Null,
+        }));
+    }
+
+    let mut icestats = json!({
+        "admin": "icemaster@localhost",
+        "host": host.split(':').next().unwrap_or(host),
+        "location": "Earth",
+        "server_id": ICECAST_SERVER,
+        "server_start": CONTEXT.start_time.to_rfc2822(),
+        "server_start_iso8601": CONTEXT.start_time.format("%Y-%m-%dT%H:%M:%S%z").to_string(),
+    });
+    match sources.len() {
+        0 => {}
+        1 => icestats["source"] = sources.pop().unwrap(),
+        _ => icestats["source"] = Value::Array(sources),
+    }
+
+    json!({ "icestats": icestats }).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        config::{OutputAuthConfig, OutputType},
+        playlist::{Playlist, Silent},
+        shoutcast::OutputAuth,
+    };
+
+    #[test]
+    fn test_stream_start_response() {
+        let format = AudioFormat {
+            bitrate: 128,
+            sample_rate: Some(44100),
+            channels: Some(2),
+        };
+        let res = stream_start_response("audio/mpeg", "Main", Some(format), true);
+        assert!(res.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(res.contains(
+            "\r\nice-audio-info: ice-bitrate=128;ice-samplerate=44100;ice-channels=2\r\n"
+        ));
+        assert!(res.contains("\r\nicy-br: 128\r\n"));
+        assert!(res.contains("\r\nServer: Icecast"));
+        assert!(res.contains("\r\nCache-Control: no-cache, no-store\r\n"));
+        assert!(res.contains("\r\nContent-Type: audio/mpeg\r\n"));
+        assert!(res.contains("\r\nicy-name: Main\r\n"));
+        assert!(res.contains("\r\nicy-metaint: 65536\r\n"));
+        assert!(res.ends_with("\r\n\r\n"));
+
+        let res = stream_start_response("audio/mpeg", "Main", None, false);
+        assert!(!res.contains("icy-metaint"));
+        assert!(!res.contains("ice-audio-info"));
+    }
+
+    #[test]
+    fn test_status_json_response_head() {
+        let body = r#"{"icestats":{}}"#;
+        let res = status_json_response(body, false);
+        assert!(res.contains("\r\nContent-Length: 15\r\n"));
+        assert!(res.ends_with(body));
+
+        let res = status_json_response(body, true);
+        assert!(res.contains("\r\nContent-Length: 15\r\n"));
+        assert!(res.ends_with("\r\n\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_status_json_without_mounts() {
+        let body = status_json("localhost:8000", &HashMap::new()).await;
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["icestats"]["host"], "localhost");
+        assert!(body["icestats"]["source"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_status_json_skips_protected_mounts() {
+        let playlist = Arc::new(
+            Playlist::new("Main".to_string(), Box::new(Silent::new().unwrap()), None).await,
+        );
+        let mount = |auth: Option<Arc<OutputAuth>>| Mount {
+            playlist: playlist.clone(),
+            output_type: OutputType::ShoutCast,
+            metrics: Default::default(),
+            auth,
+            hls: None,
+        };
+        let auth = OutputAuth::from_config(OutputAuthConfig {
+            tokens: vec!["secret".to_string()],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let mounts = HashMap::from([
+            ("public".to_string(), mount(None)),
+            ("private".to_string(), mount(Some(Arc::new(auth)))),
+        ]);
+
+        let body = status_json("localhost:8000", &mounts).await;
+        let body: Value = serde_json::from_str(&body).unwrap();
+        let source = &body["icestats"]["source"];
+        assert_eq!(source["listenurl"], "http://localhost:8000/public");
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+
+use super::Mount;
+
+/// escape the text written in the HTML page
+fn escape_html(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&#39;"),
+            _ => res.push(c),
+        }
+    }
+    res
+}
+
+/// Build the HTML page listing the mounts of a server, with their current track and listeners.
+pub async fn landing_page(title: &str, mounts: &HashMap<String, Mount>) -> String {
+    let mut paths = mounts.keys().collect::<Vec<_>>();
+    paths.sort();
+
+    let title = escape_html(title);
+    let mut rows = String::new();
+    for path in paths {
+        let playlist = &mounts[path].playlist;
+        let (track_title, artist) = playlist.get_now_playing().await;
+        // nothing is played before the first listener connects
+        let now_playing = if track_title.is_empty() {
+            String::new()
+        } else {
+            escape_html(&format!("{artist} - {track_title}"))
+        };
+        let path = escape_html(path);
+        rows.push_str(&format!(
+            "<tr><td><a href=\"/{path}\">/{path}</a></td><td>{}</td><td>{now_playing}</td><td>{}</td></tr>\n",
+            escape_html(&playlist.name),
+            playlist.get_listeners(),
+        ));
+    }
+
+    format!(
+        "<!DOCTYPE html>\n\
+        <html>\n\
+        <head><meta charset=\"utf-8\"><title>{title}</title></head>\n\
+        <body>\n\
+        <h1>{title}</h1>\n\
+        <table>\n\
+        <tr><th>Mount</th><th>Stream</th><th>Now playing</th><th>Listeners</th></tr>\n\
+        {rows}\
+        </table>\n\
+        </body>\n\
+        </html>\n"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html("<a href=\"x\">Tom & Jerry's</a>"),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;"
+        );
+    }
+}
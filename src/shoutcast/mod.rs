@@ -1,16 +1,67 @@
-use crate::playlist::Playlist;
+use crate::{CONTEXT, config::OutputType, metrics::MountMetrics, playlist::Playlist};
 use bytes::BytesMut;
-use http::{Request, header::HeaderValue};
+use futures::SinkExt;
+use http::{Method, Request, header::HeaderValue};
 use log::{debug, error, info};
-use std::{collections::HashMap, io, sync::Arc, vec};
+use std::{
+    collections::HashMap,
+    io,
+    net::SocketAddr,
+    sync::{Arc, RwLock, atomic::Ordering},
+    vec,
+};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::Mutex;
 use tokio_stream::StreamExt;
 use tokio_util::codec::{Decoder, Framed};
 
+mod auth;
+mod hls;
+mod icecast;
+mod landing_page;
 mod request_handler;
+mod response;
+mod source;
 
-use request_handler::RequestHandler;
+use auth::AuthResult;
+pub use auth::OutputAuth;
+pub(crate) use auth::constant_time_eq;
+pub use hls::Hls;
+use request_handler::{META_DATA_INTERVAL_STR, RequestHandler};
+use response::{ALLOWED_METHODS, text_response};
+pub(crate) use source::basic_auth;
+pub use source::listen_shoutcast_v1;
+
+/// A mount is a path of a server that streams a playlist.
+#[derive(Clone)]
+pub struct Mount {
+    pub playlist: Arc<Playlist>,
+    pub output_type: OutputType,
+    /// bytes sent to the listeners of the mount
+    pub metrics: Arc<MountMetrics>,
+    /// access control of the listeners, every listener is accepted if None
+    pub auth: Option<Arc<OutputAuth>>,
+    /// the segments of an HLS mount, None for the other output types
+    pub hls: Option<Arc<Hls>>,
+}
+
+/// The mounts of a server by path, swapped when the configuration is reloaded.
+pub struct Mounts(RwLock<Arc<HashMap<String, Mount>>>);
+
+impl Mounts {
+    pub fn new(mounts: HashMap<String, Mount>) -> Self {
+        Self(RwLock::new(Arc::new(mounts)))
+    }
+
+    /// get the current mounts, the requests being served keep the mounts they started with
+    pub fn load(&self) -> Arc<HashMap<String, Mount>> {
+        self.0.read().unwrap().clone()
+    }
+
+    pub fn store(&self, mounts: HashMap<String, Mount>) {
+        *self.0.write().unwrap() = Arc::new(mounts);
+    }
+}
 
 #[derive(Debug)]
 pub struct ListenerID {
@@ -18,7 +69,13 @@ pub struct ListenerID {
     pub session_id: Option<String>,
 }
 
-pub async fn listen(host: String, port: u16, playlists: Arc<HashMap<String, Arc<Playlist>>>) {
+/// `landing_page` is the title of the page listing the mounts at `/`, the page is disabled if None.
+pub async fn listen(
+    host: String,
+    port: u16,
+    mounts: Arc<Mounts>,
+    landing_page: Option<Arc<String>>,
+) {
     let addr = format!("{host}:{port}");
     let server = match TcpListener::bind(&addr).await {
         Ok(server) => server,
@@ -30,16 +87,26 @@ pub async fn listen(host: String, port: u16, playlists: Arc<HashMap<String, Arc<
     info!("Listening on: {addr}");
 
     loop {
-        let (stream, _) = match server.accept().await {
+        let accepted = tokio::select! {
+            accepted = server.accept() => accepted,
+            _ = CONTEXT.shutdown.cancelled() => {
+                info!("Stop listening on: {addr}");
+                return;
+            }
+        };
+        let (stream, peer) = match accepted {
             Ok(s) => s,
             Err(e) => {
                 error!("failed to accept connection: {e}");
                 continue;
             }
         };
-        let playlists = playlists.clone();
-        tokio::spawn(async move {
-            if let Err(e) = process(stream, playlists).await {
+        let mounts = mounts.load();
+        let addr = addr.clone();
+        let landing_page = landing_page.clone();
+        // the shutdown waits for the connections being served
+        CONTEXT.tasks.spawn(async move {
+            if let Err(e) = process(stream, peer, mounts, addr, landing_page).await {
                 error!("failed to process connection; error = {e}");
             }
         });
@@ -48,26 +115,134 @@ pub async fn listen(host: String, port: u16, playlists: Arc<HashMap<String, Arc<
 
 async fn process(
     stream: TcpStream,
-    playlists: Arc<HashMap<String, Arc<Playlist>>>,
+    peer: SocketAddr,
+    mounts: Arc<HashMap<String, Mount>>,
+    addr: String,
+    landing_page: Option<Arc<String>>,
 ) -> anyhow::Result<()> {
-    let transport = Arc::new(Mutex::new(Framed::new(stream, Http)));
+    let mut transport = Framed::new(stream, Http);
 
-    let mut transport_lock = transport.lock().await;
-    let request = match transport_lock.next().await {
-        Some(request) => request?,
+    let request = match transport.next().await {
+        Some(Ok(request)) => request,
+        Some(Err(e)) => {
+            debug!("bad request from {peer}: {e}");
+            let response = text_response(OutputType::default(), "400 Bad Request", "", "", false);
+            transport.send(response).await?;
+            return Ok(());
+        }
         None => return Ok(()),
     };
-    drop(transport_lock);
 
     debug!("handle request: {:?}", request);
     let path = request.uri().path();
     let path = path.trim_matches('/');
-    let playlist = playlists.get(path);
-    if let Some(playlist) = playlist {
-        let mut handler = RequestHandler::new(transport.clone(), playlist.clone(), request).await?;
+    // the media playlist and the segments of an HLS mount are under the path of the mount
+    let (mount_path, mount, hls_file) = match mounts.get(path) {
+        Some(mount) => (path, Some(mount), ""),
+        None => {
+            let (mount_path, file) = path.rsplit_once('/').unwrap_or(("", path));
+            match mounts.get(mount_path) {
+                Some(mount) if mount.hls.is_some() => (mount_path, Some(mount), file),
+                _ => (path, None, ""),
+            }
+        }
+    };
+    let output_type = mount.map(|mount| mount.output_type).unwrap_or_default();
+    let head = request.method() == Method::HEAD;
+
+    if source::is_source_method(request.method()) {
+        // the source client owns the connection, it is not shared with a listener
+        return source::handle_source_request(transport, mount, request).await;
+    }
+
+    let allow = format!("Allow: {ALLOWED_METHODS}\r\n");
+    if request.method() == Method::OPTIONS {
+        // the preflight request of the browsers sending the `Icy-MetaData` header
+        let response = response::response(output_type, "200 OK", &allow, "text/plain", "", true);
+        transport.send(response).await?;
+        return Ok(());
+    }
+    if request.method() != Method::GET && !head {
+        let response = text_response(output_type, "405 Method Not Allowed", &allow, "", head);
+        transport.send(response).await?;
+        return Ok(());
+    }
+
+    if let Some(mount) = mount
+        && let Some(auth) = &mount.auth
+    {
+        let result = auth.check(peer.ip(), &request).await;
+        if result != AuthResult::Allowed {
+            info!("listener {peer} refused on /{path}: {result:?}");
+            let response = auth::refused_response(result, mount.output_type, head);
+            transport.send(response).await?;
+            return Ok(());
+        }
+    }
+
+    if let Some(mount) = mount
+        && let Some(hls) = &mount.hls
+    {
+        let (response, body) = hls.response(mount_path, hls_file, request.uri().query(), head);
+        mount
+            .metrics
+            .bytes_sent
+            .fetch_add(body.len() as u64, Ordering::Relaxed);
+        transport.send(response).await?;
+        transport.send(body).await?;
+        return Ok(());
+    }
+
+    if let Some(mount) = mount
+        && mount.playlist.is_finished().await?
+    {
+        let response = text_response(
+            mount.output_type,
+            "503 Service Unavailable",
+            "",
+            "The stream has ended",
+            head,
+        );
+        transport.send(response).await?;
+        return Ok(());
+    }
+
+    let transport = Arc::new(Mutex::new(transport));
+    if let Some(mount) = mount {
+        let mut handler = RequestHandler::new(transport.clone(), mount.clone(), request).await?;
         handler.handle_request().await?;
+    } else if path == icecast::STATUS_JSON_PATH {
+        // the host used by the listener to connect to the server
+        let host = match request.headers().get(http::header::HOST) {
+            Some(host) => host.to_str()?.to_string(),
+            None => addr,
+        };
+        let body = icecast::status_json(&host, &mounts).await;
+        let response = icecast::status_json_response(&body, head);
+        transport.lock().await.send(response).await?;
+    } else if path.is_empty()
+        && let Some(title) = landing_page
+    {
+        let body = landing_page::landing_page(&title, &mounts).await;
+        let response = response::response(
+            output_type,
+            "200 OK",
+            "",
+            "text/html; charset=utf-8",
+            &body,
+            head,
+        );
+        transport.lock().await.send(response).await?;
     } else {
         debug!("playlist not found for path: {path}");
+        let response = text_response(
+            output_type,
+            "404 Not Found",
+            "",
+            &format!("Mount not found: /{path}"),
+            head,
+        );
+        transport.lock().await.send(response).await?;
     }
 
     debug!("connection closed");
@@ -75,7 +250,7 @@ async fn process(
     Ok(())
 }
 
-struct Http;
+pub(crate) struct Http;
 
 /// Implementation of decoding an HTTP request from the bytes we've read so far.
 /// This leverages the `httparse` crate to do the actual parsing and then we use
@@ -86,7 +261,7 @@ impl Decoder for Http {
     type Error = io::Error;
 
     fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Request<()>>> {
-        let (method, path, amt, headers) = {
+        let (method, path, amt, headers, version) = {
             let mut parsed_headers = vec![httparse::EMPTY_HEADER; 16];
             let mut r = httparse::Request::new(&mut parsed_headers);
             let amt = loop {
@@ -100,10 +275,9 @@ impl Decoder for Http {
                         continue;
                     }
                     Err(e) => {
-                        return Err(io::Error::new(
-                            io::ErrorKind::Other,
-                            format!("failed to parse http request: {e:?}"),
-                        ));
+                        return Err(io::Error::other(format!(
+                            "failed to parse http request: {e:?}"
+                        )));
                     }
                 }
             };
@@ -122,40 +296,32 @@ impl Decoder for Http {
                 headers[i] = Some((k, v));
             }
 
-            let method = http::Method::try_from(r.method.ok_or(io::Error::new(
-                io::ErrorKind::Other,
-                "No HTTP Method specified",
-            ))?)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            let method = http::Method::try_from(
+                r.method
+                    .ok_or(io::Error::other("No HTTP Method specified"))?,
+            )
+            .map_err(io::Error::other)?;
 
             let path = match r.path {
                 Some(path) => to_slice_fn(path.as_bytes()),
                 None => {
-                    return Err(io::Error::new(
-                        io::ErrorKind::Other,
-                        "No HTTP Path specified",
-                    ));
+                    return Err(io::Error::other("No HTTP Path specified"));
                 }
             };
 
-            // check version
-            match r.version {
-                Some(1) => {}
+            // check version, HTTP/1.0 is used by the Icecast source clients
+            let version = match r.version {
+                Some(1) => http::Version::HTTP_11,
+                Some(0) => http::Version::HTTP_10,
                 Some(_) => {
-                    return Err(io::Error::new(
-                        io::ErrorKind::Other,
-                        "only HTTP/1.1 accepted",
-                    ));
+                    return Err(io::Error::other("only HTTP/1.0 and HTTP/1.1 accepted"));
                 }
                 None => {
-                    return Err(io::Error::new(
-                        io::ErrorKind::Other,
-                        "No HTTP version specified",
-                    ));
+                    return Err(io::Error::other("No HTTP version specified"));
                 }
             };
 
-            (method, path, amt, headers)
+            (method, path, amt, headers, version)
         };
 
         let data = src.split_to(amt).freeze();
@@ -163,22 +329,20 @@ impl Decoder for Http {
         ret = ret.method(method);
         let s = data.slice(path.0..path.1);
         let s = String::from_utf8(Vec::from(s.as_ref()))
-            .map_err(|_| io::Error::new(io::ErrorKind::Other, "path decode error"))?;
+            .map_err(|_| io::Error::other("path decode error"))?;
         ret = ret.uri(s);
-        ret = ret.version(http::Version::HTTP_11);
+        ret = ret.version(version);
         for header in headers.iter() {
             let (k, v) = match *header {
                 Some((ref k, ref v)) => (k, v),
                 None => break,
             };
             let value = HeaderValue::from_bytes(data.slice(v.0..v.1).as_ref())
-                .map_err(|_| io::Error::new(io::ErrorKind::Other, "header decode error"))?;
+                .map_err(|_| io::Error::other("header decode error"))?;
             ret = ret.header(&data[k.0..k.1], value);
         }
 
-        let req = ret
-            .body(())
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        let req = ret.body(()).map_err(io::Error::other)?;
         Ok(Some(req))
     }
 }
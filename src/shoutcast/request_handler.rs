@@ -1,22 +1,27 @@
 use async_trait::async_trait;
 use bytes::Bytes;
 use futures::SinkExt;
-use http::Request;
+use http::{Method, Request};
 use log::debug;
-use std::{sync::Arc, vec};
+use std::{
+    sync::{Arc, atomic::Ordering},
+    vec,
+};
 use tokio::sync::Mutex;
 use tokio_stream::StreamExt;
 
 use crate::{
     CONTEXT,
+    config::OutputType,
+    metrics::MountMetrics,
     playlist::{Playlist, PlaylistFrameStream},
 };
 
-use super::ListenerID;
+use super::{ListenerID, Mount, icecast, response::text_response};
 
 /// MetaDataInterval is the data interval in which meta data is send
 const META_DATA_INTERVAL: usize = 65536;
-const META_DATA_INTERVAL_STR: &str = "65536";
+pub(super) const META_DATA_INTERVAL_STR: &str = "65536";
 
 /// MaxMetaDataSize is the maximum size for meta data (everything over is truncated)
 ///
@@ -95,18 +100,30 @@ impl Send2Sink<Bytes> for MySink {
 pub struct RequestHandler {
     sink: MySink,
     playlist: Arc<Playlist>,
+    output_type: OutputType,
+    /// whether the request is a `HEAD` request, only the headers are sent
+    head: bool,
     meta_data_support: bool,
     id: ListenerID,
     title: Arc<String>,
     artist: Arc<String>,
+    /// the last stream header written to the listener
+    header: Option<Bytes>,
+    metrics: Arc<MountMetrics>,
 }
 impl RequestHandler {
     // new creates a new RequestHandler
     pub async fn new(
         sink: Arc<Mutex<tokio_util::codec::Framed<tokio::net::TcpStream, super::Http>>>,
-        playlist: Arc<Playlist>,
+        mount: Mount,
         request: Request<()>,
     ) -> anyhow::Result<Self> {
+        let Mount {
+            playlist,
+            output_type,
+            metrics,
+            ..
+        } = mount;
         let meta_data_support = meta_data_support(&request);
         let session_id = match request.headers().get("x-playback-session-id") {
             Some(v) => Some(v.to_str()?.to_string()),
@@ -133,6 +150,8 @@ impl RequestHandler {
         Ok(Self {
             sink: MySink(sink),
             playlist,
+            output_type,
+            head: request.method() == Method::HEAD,
             meta_data_support,
             id: ListenerID {
                 listener_id,
@@ -140,6 +159,8 @@ impl RequestHandler {
             },
             title: Arc::new("".to_string()),
             artist: Arc::new("".to_string()),
+            header: None,
+            metrics,
         })
     }
 
@@ -151,12 +172,23 @@ impl RequestHandler {
         debug!("handle request for playlist: {:?}", self.playlist.name);
 
         self.write_stream_start_response().await?;
+        if self.head {
+            return Ok(());
+        }
 
+        let _listener_guard = self.playlist.connect_listener();
+        let _mount_listener_guard = self.metrics.connect_listener();
         let mut frame_stream = PlaylistFrameStream::new(self.playlist.clone(), &self.id).await;
         let mut bytes_before_next_meta_data = META_DATA_INTERVAL;
 
         loop {
-            let frame = match frame_stream.next().await {
+            let frame = tokio::select! {
+                frame = frame_stream.next() => frame,
+                _ = CONTEXT.shutdown.cancelled() => {
+                    return self.write_goodbye(bytes_before_next_meta_data).await;
+                }
+            };
+            let frame = match frame {
                 Some(frame) => frame?,
                 None => {
                     return Ok(());
@@ -169,6 +201,18 @@ impl RequestHandler {
                 .log_current_frame(&self.id, frame.clone())
                 .await;
 
+            // the listener needs the stream header to decode the frames,
+            //     when it joins and when the stream changes
+            if frame.frame_with_meta.header.is_some() && frame.frame_with_meta.header != self.header
+            {
+                self.header = frame.frame_with_meta.header.clone();
+                if let Some(header) = self.header.clone() {
+                    bytes_before_next_meta_data = self
+                        .write_frame(header, bytes_before_next_meta_data)
+                        .await?;
+                }
+            }
+
             bytes_before_next_meta_data = self
                 .write_frame(frame.frame_with_meta.frame, bytes_before_next_meta_data)
                 .await?;
@@ -177,7 +221,36 @@ impl RequestHandler {
 
     /// writeStreamStartResponse writes the start response to the client.
     async fn write_stream_start_response(&mut self) -> anyhow::Result<()> {
-        let content_type = self.playlist.get_content_type().await;
+        let mut content_type = self.playlist.get_content_type().await;
+        if content_type.is_empty() {
+            // the content type is only known after the first frame is prepared
+            let prepared = self.playlist.prepare_frame().await;
+            content_type = self.playlist.get_content_type().await;
+            if prepared.is_err() || content_type.is_empty() {
+                let response = text_response(
+                    self.output_type,
+                    "503 Service Unavailable",
+                    "",
+                    "The stream is not available",
+                    self.head,
+                );
+                self.sink.send(response).await?;
+                prepared?;
+                return Err(anyhow::anyhow!("the playlist has no frame"));
+            }
+        }
+
+        if self.output_type == OutputType::Icecast {
+            debug!("write icecast stream start response");
+            let response = icecast::stream_start_response(
+                &content_type,
+                &self.playlist.name,
+                self.playlist.get_audio_format().await,
+                self.meta_data_support,
+            );
+            self.sink.send(response).await?;
+            return Ok(());
+        }
 
         debug!("write stream start response");
         self.sink.send("HTTP/1.0 200 OK\r\n").await?;
@@ -204,6 +277,40 @@ impl RequestHandler {
         Ok(())
     }
 
+    /// write the goodbye clip to the listener before it is disconnected by the shutdown,
+    ///     the clip is skipped if its content type differs from the stream
+    async fn write_goodbye(&mut self, bytes_before_next_meta_data: usize) -> anyhow::Result<()> {
+        let Some(goodbye) = CONTEXT.goodbye.get() else {
+            return Ok(());
+        };
+        let content_type = self.playlist.get_content_type().await;
+        if goodbye
+            .first()
+            .is_none_or(|frame| *frame.content_type != *content_type)
+        {
+            debug!("the goodbye clip is not played to a {content_type} stream");
+            return Ok(());
+        }
+
+        let mut bytes_before_next_meta_data = bytes_before_next_meta_data;
+        for frame in goodbye {
+            self.title = frame.title.clone();
+            self.artist = frame.artist.clone();
+            if frame.header.is_some() && frame.header != self.header {
+                self.header = frame.header.clone();
+                if let Some(header) = self.header.clone() {
+                    bytes_before_next_meta_data = self
+                        .write_frame(header, bytes_before_next_meta_data)
+                        .await?;
+                }
+            }
+            bytes_before_next_meta_data = self
+                .write_frame(frame.frame.clone(), bytes_before_next_meta_data)
+                .await?;
+        }
+        Ok(())
+    }
+
     /// writeFrame writes a frame to a client.
     async fn write_frame(
         &mut self,
@@ -214,6 +321,9 @@ impl RequestHandler {
         let mut bytes_before_next_meta_data = bytes_before_next_meta_data;
         while bytes_before_next_meta_data < frame.len() {
             let first = frame.split_to(bytes_before_next_meta_data);
+            self.metrics
+                .bytes_sent
+                .fetch_add(first.len() as u64, Ordering::Relaxed);
             self.sink.send(first).await?;
             self.write_stream_meta_data().await?;
             bytes_before_next_meta_data = META_DATA_INTERVAL;
@@ -221,6 +331,9 @@ impl RequestHandler {
 
         let len = frame.len();
         if len > 0 {
+            self.metrics
+                .bytes_sent
+                .fetch_add(len as u64, Ordering::Relaxed);
             self.sink.send(frame).await?;
             bytes_before_next_meta_data -= len;
         }
@@ -245,6 +358,9 @@ impl RequestHandler {
         // padding with 0 to make the length a multiple of 16
         let padding = 16 - (stream_title.len() % 16);
         let padding = if padding == 16 { 0 } else { padding };
+        self.metrics
+            .bytes_sent
+            .fetch_add((1 + stream_title.len() + padding) as u64, Ordering::Relaxed);
 
         self.sink
             .send(Bytes::from(vec![
@@ -258,20 +374,3 @@ impl RequestHandler {
         Ok(())
     }
 }
-
-/*
-
-/// writeStreamNotFoundResponse writes the not found response to the client.
-func (drh *DefaultRequestHandler) writeStreamNotFoundResponse(c net.Conn) error {
-    _, err := c.Write([]byte("HTTP/1.1 404 Not found\r\n\r\n"))
-
-    return err
-}
-
-/// writeUnauthorized writes the Unauthorized response to the client.
-func (drh *DefaultRequestHandler) writeUnauthorized(c net.Conn) error {
-    _, err := c.Write([]byte("HTTP/1.1 401 Authorization Required\r\nWWW-Authenticate: Basic realm=\"DudelDu Streaming Server\"\r\n\r\n"))
-
-    return err
-}
-*/
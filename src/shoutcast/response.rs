@@ -0,0 +1,99 @@
+use crate::config::OutputType;
+
+use super::icecast;
+
+/// methods accepted from the listeners
+pub const ALLOWED_METHODS: &str = "GET, HEAD, OPTIONS";
+
+/// The head of a response with a body of `content_length` bytes, in the flavour of the output type.
+/// `extra_headers` end with `\r\n`.
+pub fn response_head(
+    output_type: OutputType,
+    status: &str,
+    extra_headers: &str,
+    content_type: &str,
+    content_length: usize,
+) -> String {
+    match output_type {
+        // the browsers playing HLS need HTTP/1.1 and the CORS headers
+        OutputType::Icecast | OutputType::Hls => {
+            icecast::response_head(status, extra_headers, content_type, content_length)
+        }
+        OutputType::ShoutCast => format!(
+            "HTTP/1.0 {status}\r\n\
+            {extra_headers}\
+            Content-Type: {content_type}\r\n\
+            Content-Length: {content_length}\r\n\
+            \r\n",
+        ),
+    }
+}
+
+/// A full response with a small body, in the flavour of the output type.
+/// `extra_headers` end with `\r\n`, the body is omitted for `HEAD` requests.
+pub fn response(
+    output_type: OutputType,
+    status: &str,
+    extra_headers: &str,
+    content_type: &str,
+    body: &str,
+    head: bool,
+) -> String {
+    let mut res = response_head(output_type, status, extra_headers, content_type, body.len());
+    if !head {
+        res.push_str(body);
+    }
+    res
+}
+
+/// A full response with a plain text body, the status line is used as the body if `body` is empty.
+pub fn text_response(
+    output_type: OutputType,
+    status: &str,
+    extra_headers: &str,
+    body: &str,
+    head: bool,
+) -> String {
+    let body = if body.is_empty() {
+        format!("{status}\r\n")
+    } else {
+        format!("{body}\r\n")
+    };
+    response(
+        output_type,
+        status,
+        extra_headers,
+        "text/plain; charset=utf-8",
+        &body,
+        head,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_response() {
+        assert_eq!(
+            text_response(OutputType::ShoutCast, "404 Not Found", "", "", false),
+            "HTTP/1.0 404 Not Found\r\n\
+            Content-Type: text/plain; charset=utf-8\r\n\
+            Content-Length: 15\r\n\
+            \r\n\
+            404 Not Found\r\n"
+        );
+
+        let res = text_response(
+            OutputType::Icecast,
+            "405 Method Not Allowed",
+            "Allow: GET\r\n",
+            "",
+            true,
+        );
+        assert!(res.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+        assert!(res.contains("\r\nAllow: GET\r\n"));
+        assert!(res.contains("\r\nContent-Length: 24\r\n"));
+        assert!(res.ends_with("\r\n\r\n"));
+    }
+}
@@ -0,0 +1,296 @@
+use std::{collections::HashMap, sync::Arc};
+
+use base64::Engine;
+use futures::SinkExt;
+use http::Request;
+use log::{debug, error, info, warn};
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+use tokio_util::codec::Framed;
+
+use crate::{
+    CONTEXT,
+    playlist::{DEFAULT_LIVE_BITRATE, LiveSource, LiveSourceSession},
+};
+
+use super::{Http, Mount};
+
+/// maximum length in bytes of a line of the Shoutcast v1 handshake
+const MAX_HANDSHAKE_LINE_LENGTH: u64 = 8192;
+
+/// maximum number of `icy-*` headers of the Shoutcast v1 handshake
+const MAX_HANDSHAKE_HEADERS: usize = 64;
+
+/// whether the method is used by a source client to stream to a mount
+pub(super) fn is_source_method(method: &http::Method) -> bool {
+    method == http::Method::PUT || method.as_str() == "SOURCE"
+}
+
+/// get the user and password of a `Basic` authorization header
+pub(crate) fn basic_auth(request: &Request<()>) -> Option<(String, String)> {
+    let value = request
+        .headers()
+        .get(http::header::AUTHORIZATION)?
+        .to_str()
+        .ok()?;
+    let encoded = value.strip_prefix("Basic ")?;
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+    let (user, password) = decoded.split_once(':')?;
+    Some((user.to_string(), password.to_string()))
+}
+
+/// Connect a source client to the live source with the stream information sent by the client.
+/// `header` returns the value of a header by its lowercase name.
+fn connect_source(
+    live_source: &LiveSource,
+    header: impl Fn(&str) -> Option<String>,
+) -> anyhow::Result<LiveSourceSession> {
+    let name = header("ice-name")
+        .or_else(|| header("icy-name"))
+        .unwrap_or_else(|| "Live".to_string());
+    let content_type = header("content-type").unwrap_or_else(|| "audio/mpeg".to_string());
+    let bitrate = header("ice-audio-info")
+        .and_then(|info| {
+            info.split(';')
+                .find_map(|kv| match kv.trim().split_once('=') {
+                    Some(("bitrate" | "ice-bitrate", v)) => v.trim().parse().ok(),
+                    _ => None,
+                })
+        })
+        .or_else(|| header("ice-bitrate").and_then(|v| v.trim().parse().ok()))
+        .or_else(|| header("icy-br").and_then(|v| v.trim().parse().ok()))
+        .unwrap_or(DEFAULT_LIVE_BITRATE);
+
+    live_source.connect(
+        Arc::new(content_type),
+        Arc::new(name),
+        Arc::new("".to_string()),
+        bitrate,
+    )
+}
+
+/// Stream the data of a source client to its session until the client disconnects.
+async fn stream_source(
+    mut session: LiveSourceSession,
+    reader: impl AsyncRead + Unpin,
+) -> anyhow::Result<()> {
+    tokio::select! {
+        res = session.stream(reader) => res,
+        // the source client is disconnected on shutdown
+        _ = CONTEXT.shutdown.cancelled() => Ok(()),
+    }
+}
+
+/// Handle an Icecast `SOURCE` or HTTP `PUT` request,
+///     the body of the request is streamed to the live source of the mount.
+pub(super) async fn handle_source_request(
+    mut transport: Framed<TcpStream, Http>,
+    mount: Option<&Mount>,
+    request: Request<()>,
+) -> anyhow::Result<()> {
+    let path = request.uri().path().to_string();
+    let http_version = match request.version() {
+        http::Version::HTTP_10 => "HTTP/1.0",
+        _ => "HTTP/1.1",
+    };
+
+    let Some(mount) = mount else {
+        debug!("source client connected to unknown mount: {path}");
+        transport
+            .send(format!("{http_version} 404 Not Found\r\n\r\n"))
+            .await?;
+        return Ok(());
+    };
+    let Some(live_source) = mount.playlist.get_live_source() else {
+        debug!("mount does not accept source clients: {path}");
+        transport
+            .send(format!(
+                "{http_version} 403 Forbidden\r\n\r\nMount does not accept source clients\r\n"
+            ))
+            .await?;
+        return Ok(());
+    };
+
+    let authenticated = match basic_auth(&request) {
+        Some((user, password)) => live_source.authenticate(Some(&user), &password),
+        None => false,
+    };
+    if !authenticated {
+        warn!("source client failed to authenticate for mount: {path}");
+        transport
+            .send(format!(
+                "{http_version} 401 Unauthorized\r\n\
+                WWW-Authenticate: Basic realm=\"Icecast2 Server\"\r\n\r\n"
+            ))
+            .await?;
+        return Ok(());
+    }
+
+    let session = connect_source(&live_source, |name| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    });
+    let session = match session {
+        Ok(session) => session,
+        Err(e) => {
+            warn!("source client rejected for mount {path}: {e}");
+            transport
+                .send(format!(
+                    "{http_version} 403 Forbidden\r\n\r\nMountpoint in use\r\n"
+                ))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let expect_continue = request
+        .headers()
+        .get(http::header::EXPECT)
+        .is_some_and(|v| v.as_bytes().eq_ignore_ascii_case(b"100-continue"));
+    if expect_continue {
+        transport.send("HTTP/1.1 100 Continue\r\n\r\n").await?;
+    }
+    transport
+        .send(format!("{http_version} 200 OK\r\n\r\n"))
+        .await?;
+    info!("source client connected to mount: {path}");
+
+    // the data read after the request head belongs to the stream
+    let parts = transport.into_parts();
+    let reader = std::io::Cursor::new(parts.read_buf).chain(parts.io);
+    stream_source(session, reader).await?;
+
+    info!("source client disconnected from mount: {path}");
+    Ok(())
+}
+
+/// Listen for Shoutcast v1 source clients of a live source.
+pub async fn listen_shoutcast_v1(host: String, port: u16, live_source: Arc<LiveSource>) {
+    let addr = format!("{host}:{port}");
+    let server = match TcpListener::bind(&addr).await {
+        Ok(server) => server,
+        Err(e) => {
+            error!("failed to bind to: {addr}, error: {e}");
+            return;
+        }
+    };
+    info!("Listening for Shoutcast v1 source clients on: {addr}");
+
+    loop {
+        let accepted = tokio::select! {
+            accepted = server.accept() => accepted,
+            _ = CONTEXT.shutdown.cancelled() => return,
+        };
+        let (stream, _) = match accepted {
+            Ok(s) => s,
+            Err(e) => {
+                error!("failed to accept connection: {e}");
+                continue;
+            }
+        };
+        let live_source = live_source.clone();
+        CONTEXT.tasks.spawn(async move {
+            if let Err(e) = process_shoutcast_v1(stream, live_source).await {
+                error!("failed to process source connection; error = {e}");
+            }
+        });
+    }
+}
+
+/// Read a line of the Shoutcast v1 handshake without its line break,
+///     the client is unauthenticated so the line must end within `MAX_HANDSHAKE_LINE_LENGTH` bytes.
+/// Returns None at the end of the stream.
+async fn read_handshake_line(
+    reader: &mut (impl AsyncBufRead + Unpin),
+) -> anyhow::Result<Option<String>> {
+    let mut line = String::new();
+    let read = (&mut *reader)
+        .take(MAX_HANDSHAKE_LINE_LENGTH)
+        .read_line(&mut line)
+        .await?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if read as u64 == MAX_HANDSHAKE_LINE_LENGTH && !line.ends_with('\n') {
+        anyhow::bail!("Shoutcast v1 handshake line longer than {MAX_HANDSHAKE_LINE_LENGTH} bytes");
+    }
+    line.truncate(line.trim_end_matches(['\r', '\n']).len());
+    Ok(Some(line))
+}
+
+/// Handle the Shoutcast v1 handshake:
+///     the client sends its password on the first line,
+///     then the `icy-*` headers ended by an empty line, followed by the stream.
+async fn process_shoutcast_v1(
+    stream: TcpStream,
+    live_source: Arc<LiveSource>,
+) -> anyhow::Result<()> {
+    let mut reader = BufReader::new(stream);
+
+    let password = read_handshake_line(&mut reader).await?.unwrap_or_default();
+    if !live_source.authenticate(None, &password) {
+        warn!("Shoutcast v1 source client failed to authenticate");
+        reader.get_mut().write_all(b"invalid password\r\n").await?;
+        return Ok(());
+    }
+
+    if live_source.is_connected() {
+        warn!("Shoutcast v1 source client rejected, a source client is already connected");
+        reader.get_mut().write_all(b"Stream In Use\r\n").await?;
+        return Ok(());
+    }
+    reader
+        .get_mut()
+        .write_all(b"OK2\r\nicy-caps:11\r\n\r\n")
+        .await?;
+
+    let mut headers = HashMap::new();
+    loop {
+        let Some(line) = read_handshake_line(&mut reader).await? else {
+            return Ok(());
+        };
+        if line.is_empty() {
+            break;
+        }
+        if headers.len() >= MAX_HANDSHAKE_HEADERS {
+            anyhow::bail!("Shoutcast v1 handshake with more than {MAX_HANDSHAKE_HEADERS} headers");
+        }
+        if let Some((k, v)) = line.split_once(':') {
+            headers.insert(k.trim().to_ascii_lowercase(), v.trim().to_string());
+        }
+    }
+
+    let session = connect_source(&live_source, |name| headers.get(name).cloned())?;
+    info!("Shoutcast v1 source client connected");
+    stream_source(session, reader).await?;
+    info!("Shoutcast v1 source client disconnected");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_handshake_line() {
+        let mut reader = BufReader::new(&b"hackme\r\nicy-name:Live\nlast"[..]);
+        let mut lines = Vec::new();
+        while let Some(line) = read_handshake_line(&mut reader).await.unwrap() {
+            lines.push(line);
+        }
+        assert_eq!(lines, vec!["hackme", "icy-name:Live", "last"]);
+
+        let long = vec![b'a'; MAX_HANDSHAKE_LINE_LENGTH as usize + 1];
+        let mut reader = BufReader::new(&long[..]);
+        assert!(read_handshake_line(&mut reader).await.is_err());
+    }
+}
@@ -0,0 +1,83 @@
+use std::{sync::Arc, time::Duration};
+
+use futures::StreamExt;
+use log::{info, warn};
+
+use crate::{
+    CONTEXT, LocalFileProvider,
+    config::ShutdownConfig,
+    playlist::{LocalFileTrack, PlaylistChild},
+};
+
+/// time given to the aborted downloads to remove their temporary files
+const ABORT_GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+/// Read the frames of the goodbye clip, the clip is played to the listeners on shutdown.
+pub async fn load_goodbye(config: &ShutdownConfig) -> anyhow::Result<()> {
+    let Some(path) = &config.goodbye else {
+        return Ok(());
+    };
+    let mut track = LocalFileTrack::new(
+        Arc::new(path.clone()),
+        Arc::new(LocalFileProvider::new()),
+        Some(false),
+    )?;
+    let mut frames = Vec::new();
+    let mut stream = track.stream_frame_with_meta().await?;
+    while let Some(frame) = stream.next().await {
+        frames.push(frame?);
+    }
+    if frames.is_empty() {
+        return Err(anyhow::anyhow!(
+            "the goodbye clip {path} has no audio frame"
+        ));
+    }
+    info!("goodbye clip {path} loaded: {} frames", frames.len());
+    let _ = CONTEXT.goodbye.set(frames);
+    Ok(())
+}
+
+/// Wait for `SIGINT` or `SIGTERM`.
+pub async fn wait_for_signal() -> anyhow::Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            res = tokio::signal::ctrl_c() => res?,
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await?;
+
+    Ok(())
+}
+
+/// Stop accepting connections, play the goodbye clip to the listeners,
+///     and wait for the connections and the downloads until the deadline.
+/// The downloads still in flight at the deadline are aborted.
+pub async fn shutdown(config: &ShutdownConfig) {
+    // the timeout is checked by the preflight of the configuration
+    let timeout = config.timeout().unwrap_or_else(|e| {
+        warn!("shutdown.timeout: {e}, using the default");
+        ShutdownConfig::default().timeout().unwrap_or_default()
+    });
+    info!("shutting down, waiting up to {timeout:?} for the listeners and the downloads");
+    CONTEXT.shutdown.cancel();
+    CONTEXT.tasks.close();
+
+    if tokio::time::timeout(timeout, CONTEXT.tasks.wait())
+        .await
+        .is_err()
+    {
+        warn!(
+            "shutdown deadline reached, aborting {} connections and downloads",
+            CONTEXT.tasks.len()
+        );
+        CONTEXT.abort.cancel();
+        let _ = tokio::time::timeout(ABORT_GRACE_PERIOD, CONTEXT.tasks.wait()).await;
+    }
+    info!("shutdown complete");
+}
@@ -0,0 +1,423 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use log::{error, info, warn};
+use tokio::{sync::Mutex, task::JoinHandle};
+
+use crate::{
+    FileProvider,
+    admin::AdminMount,
+    cache, check,
+    config::{
+        AdminConfig, CacheConfig, ConfigFormat, FileProviderConfig, GlobalConfig, HlsConfig,
+        LandingPageConfig, LogLevel, OutputType, PlaylistConfig, ShutdownConfig,
+    },
+    file_provider::build_file_provider,
+    playlist::{
+        Playlist, PlaylistChild, PlaylistControl, build_playlist, build_playlist_child_from_config,
+    },
+    shoutcast::{self, Hls, Mount, Mounts, OutputAuth},
+};
+
+/// `host`, `port` and path of a mount, the path is trimmed of `/`
+type MountKey = (String, u16, String);
+
+/// the cache folder and the settings of its sweep
+type SweepSettings = (Option<Arc<String>>, CacheConfig);
+
+/// The playlists, the mounts and the file providers built from the configuration,
+///     replaced as a whole when the configuration is reloaded.
+#[derive(Default)]
+pub struct StationSnapshot {
+    /// the mounts in the order of the configuration
+    pub mounts: Vec<AdminMount>,
+    pub playlists: HashMap<String, Arc<Playlist>>,
+    pub file_providers: Arc<HashMap<String, Arc<dyn FileProvider>>>,
+}
+
+/// The fields of the configuration only read at start, a restart is required to change them.
+#[derive(PartialEq)]
+struct StartupConfig {
+    admin: Option<AdminConfig>,
+    log_level: Option<LogLevel>,
+    log_file: Vec<String>,
+    landing_page: LandingPageConfig,
+    shutdown: ShutdownConfig,
+}
+
+impl StartupConfig {
+    /// the names of the fields that differ from the running configuration
+    fn changed_fields(&self, running: &Self) -> Vec<&'static str> {
+        let fields = [
+            ("admin", self.admin != running.admin),
+            ("log_level", self.log_level != running.log_level),
+            ("log_file", self.log_file != running.log_file),
+            ("landing_page", self.landing_page != running.landing_page),
+            ("shutdown", self.shutdown != running.shutdown),
+        ];
+        fields
+            .into_iter()
+            .filter_map(|(name, changed)| changed.then_some(name))
+            .collect()
+    }
+}
+
+/// What the configuration built, kept to diff the next configuration.
+#[derive(Default)]
+struct StationState {
+    /// the fields read at start, kept to warn about their changes
+    startup: Option<StartupConfig>,
+    cache_dir: Option<Arc<String>>,
+    file_provider: HashMap<String, FileProviderConfig>,
+    playlists: HashMap<String, PlaylistConfig>,
+    /// the Shoutcast v1 source listeners of the playlists
+    source_listeners: HashMap<String, JoinHandle<()>>,
+    /// the HLS settings of the mounts, with the mounts
+    mounts: HashMap<MountKey, (HlsConfig, Mount)>,
+    servers: HashMap<(String, u16), (Arc<Mounts>, JoinHandle<()>)>,
+    /// the sweep of the cache folder and its settings, only run if there are file providers
+    sweeper: Option<(SweepSettings, JoinHandle<()>)>,
+}
+
+/// How a playlist of the new configuration is built.
+enum PlaylistChange {
+    Keep(Arc<Playlist>),
+    /// the child of the playlist is swapped at its next track boundary
+    Replace(Arc<Playlist>, Box<dyn PlaylistChild>),
+    New(Arc<Playlist>),
+}
+
+/// Station runs the servers of the configuration, and applies the reloaded configurations
+///     without disconnecting the listeners of the unchanged mounts.
+pub struct Station {
+    config_path: String,
+    /// the format given on the command line, guessed from the extension if None
+    config_format: Option<ConfigFormat>,
+    /// title of the landing page of the servers, the page is disabled if None
+    landing_page: Option<Arc<String>>,
+    snapshot: RwLock<Arc<StationSnapshot>>,
+    /// locked while a configuration is applied
+    state: Mutex<StationState>,
+}
+
+impl Station {
+    /// Start the servers of the configuration read from `config_path`.
+    pub async fn start(
+        config_path: String,
+        config_format: Option<ConfigFormat>,
+        config: GlobalConfig,
+    ) -> anyhow::Result<Arc<Self>> {
+        let landing_page = &config.landing_page;
+        let station = Arc::new(Self {
+            config_path,
+            config_format,
+            landing_page: landing_page
+                .enabled
+                .then(|| Arc::new(landing_page.title.clone())),
+            snapshot: Default::default(),
+            state: Default::default(),
+        });
+        station.apply(config).await?;
+        Ok(station)
+    }
+
+    /// get the playlists and the mounts currently served
+    pub fn snapshot(&self) -> Arc<StationSnapshot> {
+        self.snapshot.read().unwrap().clone()
+    }
+
+    /// Read the configuration file again and apply it,
+    ///     the current configuration is kept if the new one is invalid.
+    pub async fn reload(&self) -> anyhow::Result<()> {
+        info!("reloading the configuration from {}", self.config_path);
+        let config = GlobalConfig::from_path(&self.config_path, self.config_format).await?;
+        self.apply(config).await
+    }
+
+    /// Reload the configuration on each `SIGHUP`.
+    pub async fn reload_on_hangup(self: Arc<Self>) -> anyhow::Result<()> {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{SignalKind, signal};
+
+            let mut hangup = signal(SignalKind::hangup())?;
+            while hangup.recv().await.is_some() {
+                if let Err(e) = self.reload().await {
+                    error!("failed to reload the configuration: {e}");
+                }
+            }
+        }
+        #[cfg(not(unix))]
+        std::future::pending::<()>().await;
+
+        Ok(())
+    }
+
+    /// Diff the configuration with the current one and apply the changes.
+    /// Everything is built before the first change is applied,
+    ///     so an invalid configuration changes nothing.
+    async fn apply(&self, config: GlobalConfig) -> anyhow::Result<()> {
+        let problems = check::preflight(&config);
+        if !problems.is_empty() {
+            return Err(anyhow::anyhow!(
+                "invalid configuration:\n  {}",
+                problems.join("\n  ")
+            ));
+        }
+        let GlobalConfig {
+            playlists,
+            file_provider,
+            outputs,
+            cache_dir,
+            cache,
+            log_level,
+            log_file,
+            admin,
+            landing_page,
+            shutdown,
+        } = config;
+        let startup = StartupConfig {
+            admin,
+            log_level,
+            log_file,
+            landing_page,
+            shutdown,
+        };
+        let mut state = self.state.lock().await;
+        let old = self.snapshot();
+
+        // the children of the playlists hold the file providers,
+        //     new file providers replace the children of every playlist
+        let providers_changed =
+            state.cache_dir != cache_dir || state.file_provider != file_provider;
+        let file_providers = if providers_changed {
+            Arc::new(build_file_provider(cache_dir.clone(), file_provider.clone()).await?)
+        } else {
+            old.file_providers.clone()
+        };
+
+        let mut auths = Vec::with_capacity(outputs.len());
+        for output in &outputs {
+            let auth = match &output.auth {
+                Some(auth) => Some(Arc::new(OutputAuth::from_config(auth.clone()).await?)),
+                None => None,
+            };
+            auths.push(auth);
+        }
+
+        let mut changes = HashMap::with_capacity(playlists.len());
+        for (id, config) in &playlists {
+            let change = match (state.playlists.get(id), old.playlists.get(id)) {
+                // the name and the live source belong to the playlist itself
+                (Some(old_config), Some(playlist))
+                    if old_config.name == config.name
+                        && old_config.live == config.live
+                        && !playlist.is_finished().await? =>
+                {
+                    if old_config.child == config.child && !providers_changed {
+                        PlaylistChange::Keep(playlist.clone())
+                    } else {
+                        let child = build_playlist_child_from_config(
+                            config.child.clone(),
+                            file_providers.clone(),
+                        )
+                        .await?;
+                        PlaylistChange::Replace(playlist.clone(), child)
+                    }
+                }
+                _ => PlaylistChange::New(Arc::new(
+                    build_playlist(config.clone(), file_providers.clone()).await?,
+                )),
+            };
+            changes.insert(id.clone(), change);
+        }
+
+        // everything is built, apply the changes
+        let mut new_playlists = HashMap::with_capacity(changes.len());
+        for (id, change) in changes {
+            let playlist = match change {
+                PlaylistChange::Keep(playlist) => playlist,
+                PlaylistChange::Replace(playlist, child) => {
+                    info!("playlist {id} changed, its child is replaced after the current track");
+                    if let Err(e) = playlist.control(PlaylistControl::Replace(child)).await {
+                        warn!("failed to replace the child of playlist {id}: {e}");
+                    }
+                    playlist
+                }
+                PlaylistChange::New(playlist) => {
+                    if old.playlists.contains_key(&id) {
+                        info!("playlist {id} is rebuilt");
+                    }
+                    if let Some(listener) = state.source_listeners.remove(&id) {
+                        listener.abort();
+                    }
+                    // the Shoutcast v1 source listener of a playlist accepting live sources
+                    if let Some(live_source) = playlist.get_live_source()
+                        && let Some((host, port)) = live_source.shoutcast_v1.clone()
+                    {
+                        let listener =
+                            tokio::spawn(shoutcast::listen_shoutcast_v1(host, port, live_source));
+                        state.source_listeners.insert(id.clone(), listener);
+                    }
+                    playlist
+                }
+            };
+            new_playlists.insert(id, playlist);
+        }
+        // the removed playlists are dropped once their last listener disconnects
+        state.source_listeners.retain(|id, listener| {
+            let keep = new_playlists.contains_key(id);
+            if !keep {
+                info!("playlist {id} is removed");
+                listener.abort();
+            }
+            keep
+        });
+
+        let mut servers: HashMap<(String, u16), HashMap<String, Mount>> = HashMap::new();
+        let mut admin_mounts = Vec::with_capacity(outputs.len());
+        let mut mounts = HashMap::with_capacity(outputs.len());
+        for (output, auth) in outputs.into_iter().zip(auths) {
+            let path = output.path.trim_matches('/').to_string();
+            let key = (output.host.clone(), output.port, path.clone());
+            let playlist = new_playlists[&output.playlist].clone();
+
+            let old_mount = state.mounts.remove(&key);
+            // the segments of an unchanged HLS mount are kept
+            let hls = match &old_mount {
+                Some((hls_config, old))
+                    if output.output_type == OutputType::Hls
+                        && *hls_config == output.hls
+                        && Arc::ptr_eq(&old.playlist, &playlist)
+                        && old.hls.is_some() =>
+                {
+                    old.hls.clone()
+                }
+                _ => {
+                    if let Some((_, Mount { hls: Some(hls), .. })) = &old_mount {
+                        hls.retire();
+                    }
+                    // the segments of an HLS mount are cut even without listeners
+                    (output.output_type == OutputType::Hls).then(|| {
+                        let hls = Arc::new(Hls::new(output.hls.clone()));
+                        tokio::spawn(hls.clone().run(playlist.clone()));
+                        hls
+                    })
+                }
+            };
+            let mount = Mount {
+                playlist,
+                output_type: output.output_type,
+                metrics: old_mount
+                    .map(|(_, mount)| mount.metrics)
+                    .unwrap_or_default(),
+                auth,
+                hls,
+            };
+
+            admin_mounts.push(AdminMount {
+                output: format!("{}:{}", output.host, output.port),
+                path: path.clone(),
+                playlist: output.playlist,
+                mount: mount.clone(),
+            });
+            servers
+                .entry((output.host.clone(), output.port))
+                .or_default()
+                .insert(path, mount.clone());
+            mounts.insert(key, (output.hls, mount));
+        }
+        // the listeners of the removed mounts keep streaming until they disconnect
+        for ((host, port, path), (_, mount)) in state.mounts.drain() {
+            info!("mount /{path} of {host}:{port} is removed");
+            if let Some(hls) = mount.hls {
+                hls.retire();
+            }
+        }
+        state.mounts = mounts;
+
+        state.servers.retain(|(host, port), (_, server)| {
+            let keep = servers.contains_key(&(host.clone(), *port));
+            if !keep {
+                // the connections being served are not closed
+                info!("server {host}:{port} is stopped");
+                server.abort();
+            }
+            keep
+        });
+        for ((host, port), mut server_mounts) in servers {
+            server_mounts.shrink_to_fit();
+            match state.servers.get(&(host.clone(), port)) {
+                Some((mounts, _)) => mounts.store(server_mounts),
+                None => {
+                    let mounts = Arc::new(Mounts::new(server_mounts));
+                    let server = tokio::spawn(shoutcast::listen(
+                        host.clone(),
+                        port,
+                        mounts.clone(),
+                        self.landing_page.clone(),
+                    ));
+                    state.servers.insert((host, port), (mounts, server));
+                }
+            }
+        }
+
+        info!(
+            "configuration applied: {} playlists, {} mounts",
+            new_playlists.len(),
+            admin_mounts.len()
+        );
+        *self.snapshot.write().unwrap() = Arc::new(StationSnapshot {
+            mounts: admin_mounts,
+            playlists: new_playlists,
+            file_providers,
+        });
+        let sweeper = (!file_provider.is_empty()).then(|| (cache_dir.clone(), cache));
+        if state.sweeper.as_ref().map(|(settings, _)| settings) != sweeper.as_ref() {
+            if let Some((_, handle)) = state.sweeper.take() {
+                handle.abort();
+            }
+            state.sweeper = sweeper.map(|(dir, cache)| {
+                let handle = tokio::spawn(sweep_cache(dir.clone(), cache.clone()));
+                ((dir, cache), handle)
+            });
+        }
+
+        match &state.startup {
+            Some(running) => {
+                for field in startup.changed_fields(running) {
+                    warn!("`{field}` changed, a restart is required to apply it");
+                }
+            }
+            None => state.startup = Some(startup),
+        }
+        state.cache_dir = cache_dir;
+        state.file_provider = file_provider;
+        state.playlists = playlists;
+        Ok(())
+    }
+}
+
+/// Sweep the cache folder of the file providers periodically.
+async fn sweep_cache(dir: Option<Arc<String>>, config: CacheConfig) {
+    let dir = match dir {
+        Some(dir) => dir.as_str().into(),
+        None => cache::default_dir(),
+    };
+    // the durations are checked by the preflight of the configuration
+    let (max_age, interval) = match (config.max_age(), config.sweep_interval()) {
+        (Ok(max_age), Ok(interval)) => (max_age, interval),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("the cache {dir:?} is not swept: {e}");
+            return;
+        }
+    };
+    let limits = cache::CacheLimits {
+        max_size: config.max_size_mb.map(|mb| mb.saturating_mul(1024 * 1024)),
+        max_age,
+    };
+    info!("sweeping the cache {dir:?} every {interval:?}");
+    cache::sweep_periodically(dir, limits, interval).await
+}